@@ -1,34 +1,7 @@
-use chrono::{Datelike, Month, NaiveDate, Utc};
-use handlebars::{handlebars_helper, Handlebars};
-use num_traits::FromPrimitive;
+use chrono::Utc;
+use handlebars::Handlebars;
 use reports::*;
-use std::{fs, vec};
-
-fn _add_year_month(year: i32, month: u32) -> (i32, u32) {
-    let month = if let Some(month) = Month::from_u32(month) {
-        month.succ().number_from_month()
-    } else {
-        panic!("Invalid month")
-    };
-    let year = match month {
-        1 => year + 1,
-        _ => year,
-    };
-    (year, month)
-}
-
-fn _add_month(date: NaiveDate) -> NaiveDate {
-    let (year, month) = _add_year_month(date.year(), date.month());
-    let mut day = date.day();
-    let max_days = {
-        let (year_next, month_next) = _add_year_month(year, month);
-        NaiveDate::from_ymd(year_next, month_next, 1)
-            .signed_duration_since(NaiveDate::from_ymd(year, month, 1))
-            .num_days() as u32
-    };
-    day = if day > max_days { max_days } else { day };
-    NaiveDate::from_ymd(year, month, day)
-}
+use std::{fs, path::Path, vec};
 
 fn main() {
     // Assumes sample data (not distributed) is already in database:
@@ -37,6 +10,16 @@ fn main() {
 
     let metrics = Metric::read().expect("Error reading metrics from database");
 
+    let mut tables = Tables::new(Utc::now().date_naive());
+    let mut targets = Vec::new();
+    for metric in metrics.values() {
+        if let Ok(points) = Datapoint::read(metric.clone()) {
+            tables.add_metric(metric.clone(), points);
+        }
+        targets.extend(Target::read(metric.clone()).unwrap_or_default());
+    }
+    let tables = tables.with_targets(targets);
+
     let users_points = Datapoint::read(match metrics.get("users") {
         Some(metric) => metric.clone(),
         None => panic!("Couldn't find users in database"),
@@ -63,7 +46,7 @@ fn main() {
             Some(metric) => metric.clone(),
             None => panic!("Couldn't find metric in database"),
         },
-        Utc::today().naive_utc(),
+        Utc::now().date_naive(),
         100.0,
         164.58,
     );
@@ -86,13 +69,31 @@ fn main() {
         ],
     };
 
-    handlebars_helper!(pretty_print: | obj: FigChange | obj.to_string());
+    println!("\n{}", paragraph.render(RenderContext::Ansi));
+
+    let mut commands = CommandRegistry::new();
+    commands.register_figure::<FigChange>("pp");
+    commands.set_templates(&CommandTemplate::read().unwrap_or_default());
     let mut hbs = Handlebars::new();
-    hbs.register_helper("pp", Box::new(pretty_print));
+    commands.install(&mut hbs);
+    tables.install(&mut hbs);
 
     hbs.register_template_file("tpl", "templates/template.md")
         .unwrap();
 
     let file = fs::File::create("ignore/output.md").unwrap();
     hbs.render_to_write("tpl", &paragraph, &file).unwrap();
+
+    fs::write("ignore/output.json", paragraph.to_json().to_string()).unwrap();
+
+    for warning in tables.take_warnings() {
+        eprintln!("warning: {}", warning);
+    }
+
+    // Tables asked to attach their CSV are written alongside the output, never elsewhere
+    for (name, csv) in tables.attachments() {
+        if let Some(file_name) = Path::new(&name).file_name() {
+            fs::write(Path::new("ignore").join(file_name), csv).unwrap();
+        }
+    }
 }
@@ -0,0 +1,473 @@
+use std::{collections::BTreeMap, error, fmt, marker::PhantomData, sync::Arc};
+
+use handlebars::{
+    Context, Handlebars, Helper, HelperDef, HelperResult, JsonValue, Output, RenderError,
+    TemplateError,
+};
+use rusqlite::{params, Connection};
+use serde::de::DeserializeOwned;
+
+use crate::{
+    phrasing::{PhraseChoice, Phrasings},
+    Figure, RenderContext, DATABASE_FILE,
+};
+
+/// A command which couldn't produce its text, with the reason why
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandError(pub String);
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "command failed: {}", self.0)
+    }
+}
+
+impl error::Error for CommandError {}
+
+/// Describes error from rendering template where it happened, e.g. "unknown arg
+/// `weeklyy` at line 12, column 8", followed by that line with a caret under the column
+pub fn describe_template_error(template: &str, error: &RenderError) -> String {
+    let parse_error = error::Error::source(error)
+        .and_then(|cause| cause.downcast_ref::<TemplateError>())
+        .filter(|_| error.line_no.is_none());
+    let (desc, line_no, column_no) = match parse_error {
+        Some(cause) => (cause.reason().to_string(), cause.line_no, cause.column_no),
+        None => (error.desc.clone(), error.line_no, error.column_no),
+    };
+    let desc = desc
+        .strip_prefix("command failed: ")
+        .unwrap_or(&desc)
+        .trim_end_matches('.');
+    let (line_no, column_no) = match line_no.zip(column_no) {
+        Some(position) => position,
+        None => return desc.to_string(),
+    };
+    let line = template
+        .lines()
+        .nth(line_no.saturating_sub(1))
+        .unwrap_or("");
+    let gutter = " ".repeat(line_no.to_string().len());
+    format!(
+        "{} at line {}, column {}\n{} | {}\n{} | {}^",
+        desc,
+        line_no,
+        column_no,
+        line_no,
+        line,
+        gutter,
+        " ".repeat(column_no.saturating_sub(1))
+    )
+}
+
+/// Produces report text from the arguments a template passes to a command
+pub trait CommandFn: Send + Sync {
+    fn call(&self, args: &[JsonValue]) -> Result<String, CommandError>;
+
+    /// Produces the text phrased by template instead. Commands which can't be phrased by
+    /// a template ignore it
+    fn call_templated(&self, args: &[JsonValue], _template: &str) -> Result<String, CommandError> {
+        self.call(args)
+    }
+}
+
+impl<F> CommandFn for F
+where
+    F: Fn(&[JsonValue]) -> Result<String, CommandError> + Send + Sync,
+{
+    fn call(&self, args: &[JsonValue]) -> Result<String, CommandError> {
+        self(args)
+    }
+}
+
+/// Named commands available to report templates, which library users can extend with
+/// their own
+#[derive(Clone, Default)]
+pub struct CommandRegistry {
+    commands: BTreeMap<String, Arc<dyn CommandFn>>,
+    templates: BTreeMap<String, Arc<Phrasings>>,
+    choice: PhraseChoice,
+}
+
+impl CommandRegistry {
+    pub fn new() -> CommandRegistry {
+        CommandRegistry::default()
+    }
+
+    /// Adds a command, replacing any already registered under name
+    pub fn register(&mut self, name: &str, command: impl CommandFn + 'static) {
+        self.commands.insert(name.to_string(), Arc::new(command));
+    }
+
+    /// Adds a command which reads its argument as a figure of type F and renders it, so
+    /// figures defined outside this crate can be used in templates
+    pub fn register_figure<F>(&mut self, name: &str)
+    where
+        F: Figure + fmt::Display + DeserializeOwned + 'static,
+    {
+        self.register_figure_in::<F>(name, RenderContext::Words);
+    }
+
+    /// Adds a command which renders its figure argument for context
+    pub fn register_figure_in<F>(&mut self, name: &str, context: RenderContext)
+    where
+        F: Figure + fmt::Display + DeserializeOwned + 'static,
+    {
+        self.register(name, RenderFigure::<F>(context, PhantomData));
+    }
+
+    /// Phrases the Words output of the command registered under name with template, e.g.
+    /// "{direction} {pct} compared with {prev_span}" for a change
+    pub fn set_template(&mut self, name: &str, template: &str) {
+        self.templates
+            .insert(name.to_string(), Arc::new(Phrasings::new(template)));
+    }
+
+    /// Adds template as an alternative phrasing of the command registered under name,
+    /// one of which is picked each time the command runs
+    pub fn add_template(&mut self, name: &str, template: &str) {
+        match self.templates.get_mut(name) {
+            Some(phrasings) => Arc::make_mut(phrasings).add(template),
+            None => self.set_template(name, template),
+        }
+    }
+
+    /// Sets each of templates, such as those read from the database, replacing any
+    /// phrasings already held for their commands
+    pub fn set_templates(&mut self, templates: &[CommandTemplate]) {
+        for template in templates {
+            self.templates.remove(&template.command);
+        }
+        for template in templates {
+            self.add_template(&template.command, &template.words);
+        }
+    }
+
+    /// Sets how one of a command's phrasings is picked, in turn by default
+    pub fn set_phrase_choice(&mut self, choice: PhraseChoice) {
+        self.choice = choice;
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.commands.contains_key(name)
+    }
+
+    /// Names of the registered commands in alphabetical order
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.commands.keys().map(|name| name.as_str())
+    }
+
+    /// Runs the command registered under name, None when there isn't one
+    pub fn call(&self, name: &str, args: &[JsonValue]) -> Option<Result<String, CommandError>> {
+        let command = self.commands.get(name)?;
+        Some(call_phrased(
+            command.as_ref(),
+            self.templates.get(name).map(Arc::as_ref),
+            self.choice,
+            args,
+        ))
+    }
+
+    /// Registers every command as a Handlebars helper of the same name
+    pub fn install(&self, hbs: &mut Handlebars) {
+        for (name, command) in &self.commands {
+            let helper = CommandHelper {
+                command: Arc::clone(command),
+                phrasings: self.templates.get(name).cloned(),
+                choice: self.choice,
+            };
+            hbs.register_helper(name, Box::new(helper));
+        }
+    }
+}
+
+/// Runs command with one of phrasings, if there are any, picked for args
+fn call_phrased(
+    command: &dyn CommandFn,
+    phrasings: Option<&Phrasings>,
+    choice: PhraseChoice,
+    args: &[JsonValue],
+) -> Result<String, CommandError> {
+    let key = JsonValue::from(args.to_vec()).to_string();
+    match phrasings.and_then(|phrasings| phrasings.choose(choice, &key)) {
+        Some(template) => command.call_templated(args, template),
+        None => command.call(args),
+    }
+}
+
+/// A template phrasing a command's Words output, stored so that phrasing can be changed
+/// without recompiling. A command can have several, one of which is picked each time
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandTemplate {
+    command: String,
+    words: String,
+}
+
+impl CommandTemplate {
+    pub fn new(command: &str, words: &str) -> CommandTemplate {
+        CommandTemplate {
+            command: command.to_string(),
+            words: words.to_string(),
+        }
+    }
+
+    pub fn write(&self) -> rusqlite::Result<()> {
+        let conn = Connection::open(DATABASE_FILE)?;
+
+        conn.execute(
+            r#"CREATE TABLE IF NOT EXISTS command_template (
+            command TEXT NOT NULL, 
+            words TEXT NOT NULL, 
+            PRIMARY KEY (command, words))"#,
+            [],
+        )?;
+
+        conn.execute(
+            "INSERT OR REPLACE INTO command_template (command, words) VALUES (?1, ?2)",
+            params![self.command, self.words],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn read() -> rusqlite::Result<Vec<CommandTemplate>> {
+        let conn = Connection::open(DATABASE_FILE)?;
+
+        let mut stmt = conn.prepare("SELECT command, words FROM command_template")?;
+
+        let templates: Result<Vec<_>, _> = stmt
+            .query_map([], |row| {
+                Ok(CommandTemplate {
+                    command: row.get(0)?,
+                    words: row.get(1)?,
+                })
+            })?
+            .collect();
+
+        templates
+    }
+}
+
+struct RenderFigure<F>(RenderContext, PhantomData<fn() -> F>);
+
+impl<F> RenderFigure<F>
+where
+    F: DeserializeOwned,
+{
+    fn figure(args: &[JsonValue]) -> Result<F, CommandError> {
+        let arg = args
+            .first()
+            .ok_or_else(|| CommandError(String::from("expected a figure")))?;
+        serde_json::from_value(arg.clone()).map_err(|e| CommandError(e.to_string()))
+    }
+}
+
+impl<F> CommandFn for RenderFigure<F>
+where
+    F: Figure + fmt::Display + DeserializeOwned,
+{
+    fn call(&self, args: &[JsonValue]) -> Result<String, CommandError> {
+        Ok(Self::figure(args)?.render_in(self.0))
+    }
+
+    fn call_templated(&self, args: &[JsonValue], template: &str) -> Result<String, CommandError> {
+        let figure = Self::figure(args)?;
+        Ok(match self.0 {
+            RenderContext::Words => figure.templated(template),
+            context => figure.render_in(context),
+        })
+    }
+}
+
+struct CommandHelper {
+    command: Arc<dyn CommandFn>,
+    phrasings: Option<Arc<Phrasings>>,
+    choice: PhraseChoice,
+}
+
+impl HelperDef for CommandHelper {
+    fn call<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'reg, 'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc Context,
+        _: &mut handlebars::RenderContext<'reg, 'rc>,
+        out: &mut dyn Output,
+    ) -> HelperResult {
+        let args: Vec<JsonValue> = h.params().iter().map(|p| p.value().clone()).collect();
+        let text = call_phrased(
+            self.command.as_ref(),
+            self.phrasings.as_deref(),
+            self.choice,
+            &args,
+        )
+        .map_err(|e| RenderError::new(e.to_string()))?;
+        out.write(&text)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::{FigChange, FigDiff, Metric, TimeFrequency};
+    use chrono::NaiveDate;
+
+    #[test]
+    fn registered_commands_render() {
+        let mut registry = CommandRegistry::new();
+        registry.register("double", |args: &[JsonValue]| {
+            args.first()
+                .and_then(|arg| arg.as_f64())
+                .map(|value| (value * 2.0).to_string())
+                .ok_or_else(|| CommandError(String::from("expected a number")))
+        });
+        assert!(registry.contains("double"));
+        assert_eq!(registry.names().collect::<Vec<_>>(), vec!["double"]);
+        assert_eq!(
+            registry.call("double", &[JsonValue::from(4)]),
+            Some(Ok(String::from("8")))
+        );
+        assert!(registry.call("halve", &[]).is_none());
+
+        let mut hbs = Handlebars::new();
+        registry.install(&mut hbs);
+        assert_eq!(
+            hbs.render_template("Purrs were {{double 21}}", &())
+                .unwrap(),
+            "Purrs were 42"
+        );
+        assert!(hbs.render_template("{{double \"lots\"}}", &()).is_err());
+        assert_eq!(
+            hbs.render_template("Write \\{{double 21}} for {{double 21}}", &())
+                .unwrap(),
+            "Write {{double 21}} for 42"
+        );
+    }
+
+    #[test]
+    fn template_errors_point_at_their_source() {
+        let mut registry = CommandRegistry::new();
+        registry.register("double", |args: &[JsonValue]| {
+            args.first()
+                .and_then(|arg| arg.as_f64())
+                .map(|value| (value * 2.0).to_string())
+                .ok_or_else(|| {
+                    let arg = args.first().and_then(JsonValue::as_str).unwrap_or_default();
+                    CommandError(format!("unknown arg `{}`", arg))
+                })
+        });
+        let mut hbs = Handlebars::new();
+        registry.install(&mut hbs);
+
+        let template = "Purrs:\n\nWere {{double \"weeklyy\"}}";
+        let error = hbs.render_template(template, &()).unwrap_err();
+        assert_eq!(
+            describe_template_error(template, &error),
+            "unknown arg `weeklyy` at line 3, column 6\n\
+             3 | Were {{double \"weeklyy\"}}\n  |      ^"
+        );
+
+        let template = "Purrs were {{double 2}";
+        let error = hbs.render_template(template, &()).unwrap_err();
+        assert_eq!(
+            describe_template_error(template, &error),
+            "invalid handlebars syntax at line 1, column 22\n\
+             1 | Purrs were {{double 2}\n  |                      ^"
+        );
+    }
+
+    #[test]
+    fn figures_render_through_registry() {
+        let metric = Metric::new(
+            String::from("cat_purrs"),
+            None,
+            String::from("Purrs were {}"),
+            TimeFrequency::Weekly,
+        );
+        let when = NaiveDate::from_ymd_opt(2022, 1, 10).unwrap();
+        let figure = FigDiff::new(metric, when, 100.0, 142.0);
+
+        let mut registry = CommandRegistry::new();
+        registry.register_figure::<FigDiff>("diff");
+        let mut hbs = Handlebars::new();
+        registry.install(&mut hbs);
+        assert_eq!(
+            hbs.render_template("{{diff this}}", &figure).unwrap(),
+            "Purrs were up 42"
+        );
+        assert!(hbs.render_template("{{diff 3}}", &()).is_err());
+    }
+
+    #[test]
+    fn templates_rephrase_figures() {
+        let metric = Metric::new(
+            String::from("users"),
+            None,
+            String::from("Website users were {}"),
+            TimeFrequency::Weekly,
+        );
+        let when = NaiveDate::from_ymd_opt(2022, 1, 12).unwrap();
+        let figure = FigChange::new(metric, when, 100.0, 125.0);
+
+        let mut registry = CommandRegistry::new();
+        registry.register_figure::<FigChange>("change");
+        registry.set_templates(&[CommandTemplate::new(
+            "change",
+            "{direction} {pct} compared with {prev_span}",
+        )]);
+        let mut hbs = Handlebars::new();
+        registry.install(&mut hbs);
+        assert_eq!(
+            hbs.render_template("{{change this}}", &figure).unwrap(),
+            "Website users were up 25.0% compared with the week of 3 Jan"
+        );
+        assert_eq!(
+            registry.call("change", &[serde_json::to_value(&figure).unwrap()]),
+            Some(Ok(
+                figure.templated("{direction} {pct} compared with {prev_span}")
+            ))
+        );
+
+        let shorter = [CommandTemplate::new("change", "{direction} {pct}")];
+        registry.set_templates(&shorter);
+        registry.set_templates(&shorter);
+        for _ in 0..2 {
+            assert_eq!(
+                registry.call("change", &[serde_json::to_value(&figure).unwrap()]),
+                Some(Ok(figure.templated("{direction} {pct}")))
+            );
+        }
+    }
+
+    #[test]
+    fn phrasings_rotate() {
+        let metric = Metric::new(
+            String::from("users"),
+            None,
+            String::from("Website users were {}"),
+            TimeFrequency::Weekly,
+        );
+        let when = NaiveDate::from_ymd_opt(2022, 1, 12).unwrap();
+        let figure = FigChange::new(metric, when, 100.0, 125.0);
+
+        let mut registry = CommandRegistry::new();
+        registry.register_figure::<FigChange>("change");
+        registry.add_template("change", "{direction} {pct}");
+        registry.add_template("change", "{direction} {pct} on {prev_span}");
+        let mut hbs = Handlebars::new();
+        registry.install(&mut hbs);
+        assert_eq!(
+            hbs.render_template("{{change this}}. {{change this}}.", &figure)
+                .unwrap(),
+            "Website users were up 25.0%. Website users were up 25.0% on the week of 3 Jan."
+        );
+
+        registry.set_phrase_choice(PhraseChoice::Seeded(3));
+        let args = [serde_json::to_value(&figure).unwrap()];
+        assert_eq!(
+            registry.call("change", &args),
+            registry.call("change", &args)
+        );
+    }
+}
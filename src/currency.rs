@@ -0,0 +1,92 @@
+use serde::{Deserialize, Serialize};
+
+use crate::figures::with_separators;
+
+/// Which side of the amount a currency symbol is written
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolPosition {
+    /// Directly before the amount, e.g. $12.40
+    Before,
+    /// After the amount and a space, e.g. 12,40 €
+    After,
+}
+
+/// How a metric's monetary amounts are written
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Currency {
+    code: String,
+    symbol: String,
+    position: SymbolPosition,
+    thousands: char,
+    decimal: char,
+}
+
+impl Currency {
+    /// A currency with amounts written as 1,203.50
+    pub fn new(code: &str, symbol: &str, position: SymbolPosition) -> Currency {
+        Currency {
+            code: code.to_string(),
+            symbol: symbol.to_string(),
+            position,
+            thousands: ',',
+            decimal: '.',
+        }
+    }
+
+    /// Sets the characters between groups of thousands and before decimals, e.g. '.' and ','
+    /// for 1.203,50
+    pub fn with_separators(mut self, thousands: char, decimal: char) -> Currency {
+        self.thousands = thousands;
+        self.decimal = decimal;
+        self
+    }
+
+    /// ISO 4217 code, e.g. EUR
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    /// Writes amount with the currency symbol and decimals decimal places
+    pub fn format(&self, amount: f64, decimals: usize) -> String {
+        self.format_with_suffix(amount, decimals, "")
+    }
+
+    /// Writes amount like format with suffix straight after the number, e.g. $1.2M
+    pub fn format_with_suffix(&self, amount: f64, decimals: usize, suffix: &str) -> String {
+        let number: String = with_separators(amount.abs(), decimals)
+            .chars()
+            .map(|c| match c {
+                ',' => self.thousands,
+                '.' => self.decimal,
+                _ => c,
+            })
+            .collect();
+        let sign = if amount < 0.0 && number.chars().any(|c| ('1'..='9').contains(&c)) {
+            "-"
+        } else {
+            ""
+        };
+        match self.position {
+            SymbolPosition::Before => format!("{}{}{}{}", sign, self.symbol, number, suffix),
+            SymbolPosition::After => format!("{}{}{} {}", sign, number, suffix, self.symbol),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn currency_formats() {
+        let dollars = Currency::new("USD", "$", SymbolPosition::Before);
+        assert_eq!(dollars.format(12400.0, 0), "$12,400");
+        assert_eq!(dollars.format(-5.5, 2), "-$5.50");
+
+        let euros = Currency::new("EUR", "€", SymbolPosition::After).with_separators('.', ',');
+        assert_eq!(euros.format(1203.5, 2), "1.203,50 €");
+        assert_eq!(euros.code(), "EUR");
+        assert_eq!(euros.format_with_suffix(3.4, 1, "M"), "3,4M €");
+    }
+}
@@ -0,0 +1,141 @@
+use std::{error, fmt};
+
+use chrono::{Datelike, NaiveDate};
+
+/// What happens to a day of month which doesn't exist in the target month
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MonthEnd {
+    /// Uses the last day of the target month, e.g. 31 January + 1 month = 28 February
+    Clamp,
+    /// Also keeps dates at the end of a month at the end, e.g. 28 February + 1 month = 31 March
+    Preserve,
+}
+
+/// Reasons a date or span could not be constructed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DateError {
+    /// The date would fall outside the range chrono can represent, or doesn't exist
+    OutOfRange,
+    /// A span must cover at least one period
+    EmptySpan,
+    /// Text which couldn't be read as a date or span
+    Parse(String),
+}
+
+impl fmt::Display for DateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DateError::OutOfRange => write!(f, "date is out of range"),
+            DateError::EmptySpan => write!(f, "a span must cover at least one period"),
+            DateError::Parse(text) => write!(f, "couldn't read `{}` as a date", text),
+        }
+    }
+}
+
+impl error::Error for DateError {}
+
+/// Reads an ISO 8601 date such as 2022-02-04
+pub fn parse_date(text: &str) -> Result<NaiveDate, DateError> {
+    NaiveDate::parse_from_str(text.trim(), "%Y-%m-%d")
+        .map_err(|_| DateError::Parse(text.to_string()))
+}
+
+/// Fallible replacement for the deprecated NaiveDate::from_ymd
+pub fn from_ymd(year: i32, month: u32, day: u32) -> Result<NaiveDate, DateError> {
+    NaiveDate::from_ymd_opt(year, month, day).ok_or(DateError::OutOfRange)
+}
+
+/// Number of days in the given month
+pub fn days_in_month(year: i32, month: u32) -> Result<u32, DateError> {
+    let first = from_ymd(year, month, 1)?;
+    let next = match month {
+        12 => from_ymd(year + 1, 1, 1)?,
+        _ => from_ymd(year, month + 1, 1)?,
+    };
+    Ok((next - first).num_days() as u32)
+}
+
+/// Moves date by a number of calendar months, backwards when months is negative
+pub fn add_months(
+    date: &NaiveDate,
+    months: i32,
+    month_end: MonthEnd,
+) -> Result<NaiveDate, DateError> {
+    let total = date.year() * 12 + date.month0() as i32 + months;
+    let (year, month) = (total.div_euclid(12), total.rem_euclid(12) as u32 + 1);
+    let last_day = days_in_month(year, month)?;
+    let at_month_end = date.day() == days_in_month(date.year(), date.month())?;
+    let day = match month_end {
+        MonthEnd::Preserve if at_month_end => last_day,
+        _ => date.day().min(last_day),
+    };
+    from_ymd(year, month, day)
+}
+
+/// Moves date by a number of quarters, backwards when quarters is negative
+pub fn add_quarters(
+    date: &NaiveDate,
+    quarters: i32,
+    month_end: MonthEnd,
+) -> Result<NaiveDate, DateError> {
+    add_months(date, quarters * 3, month_end)
+}
+
+/// Moves date by a number of years, backwards when years is negative
+pub fn add_years(
+    date: &NaiveDate,
+    years: i32,
+    month_end: MonthEnd,
+) -> Result<NaiveDate, DateError> {
+    add_months(date, years * 12, month_end)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn malformed_dates_are_errors() {
+        assert_eq!(parse_date("2022-02-04"), from_ymd(2022, 2, 4));
+        assert_eq!(
+            parse_date("2022-02-30"),
+            Err(DateError::Parse(String::from("2022-02-30")))
+        );
+        assert_eq!(from_ymd(2022, 13, 1), Err(DateError::OutOfRange));
+    }
+
+    #[test]
+    fn month_arithmetic() {
+        let jan_31 = from_ymd(2022, 1, 31).unwrap();
+        assert_eq!(
+            add_months(&jan_31, 1, MonthEnd::Clamp),
+            from_ymd(2022, 2, 28)
+        );
+        assert_eq!(
+            add_months(&jan_31, -2, MonthEnd::Clamp),
+            from_ymd(2021, 11, 30)
+        );
+
+        let feb_28 = from_ymd(2022, 2, 28).unwrap();
+        assert_eq!(
+            add_months(&feb_28, 1, MonthEnd::Clamp),
+            from_ymd(2022, 3, 28)
+        );
+        assert_eq!(
+            add_months(&feb_28, 1, MonthEnd::Preserve),
+            from_ymd(2022, 3, 31)
+        );
+
+        let leap_day = from_ymd(2024, 2, 29).unwrap();
+        assert_eq!(
+            add_years(&leap_day, 1, MonthEnd::Clamp),
+            from_ymd(2025, 2, 28)
+        );
+        assert_eq!(
+            add_quarters(&leap_day, -1, MonthEnd::Preserve),
+            from_ymd(2023, 11, 30)
+        );
+        assert_eq!(days_in_month(2024, 2), Ok(29));
+    }
+}
@@ -0,0 +1,3678 @@
+use core::fmt;
+use std::{collections::HashMap, fmt::Display};
+
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    inflection, stats, Budget, Datapoint, DateError, DateStyle, Figure, Indicators, Metric,
+    MetricGroup, MetricWeights, PartialPolicy, Target, TimeFrequency, TimeSpan,
+};
+
+/// Totals of the datapoints falling within each single period of span, oldest first
+pub(crate) fn period_totals(
+    points: &[Datapoint],
+    span: &TimeSpan,
+) -> Result<Vec<(TimeSpan, f64)>, DateError> {
+    let mut period = TimeSpan::anchored(&span.start(), span.frequency(), span.week_start())?;
+    let mut totals = Vec::new();
+    while period.start() <= span.end() {
+        totals.push((period, total_within(points, &period)));
+        period = period.next()?;
+    }
+    Ok(totals)
+}
+
+/// Writes value with a comma between each group of thousands
+pub(crate) fn with_separators(value: f64, decimals: usize) -> String {
+    let text = format!("{:.*}", decimals, value.abs());
+    let (whole, fraction) = match text.split_once('.') {
+        Some((whole, fraction)) => (whole, Some(fraction)),
+        None => (text.as_str(), None),
+    };
+    let mut output = String::new();
+    for (i, digit) in whole.chars().enumerate() {
+        if i > 0 && (whole.len() - i) % 3 == 0 {
+            output.push(',');
+        }
+        output.push(digit);
+    }
+    if let Some(fraction) = fraction {
+        output.push('.');
+        output.push_str(fraction);
+    }
+    if value < 0.0 && output.chars().any(|c| c.is_ascii_digit() && c != '0') {
+        output.insert(0, '-');
+    }
+    output
+}
+
+/// Writes value with separators, showing one decimal place only when it isn't whole
+pub(crate) fn format_value(value: f64) -> String {
+    with_separators(value, if value.fract() == 0.0 { 0 } else { 1 })
+}
+
+/// Scales large values down for writing with a suffix, e.g. (1.2, "k"), (3.4, "M").
+/// Values which would round up to the next scale move to it, so 999,960 is 1.0M
+pub(crate) fn abbreviated(value: f64) -> (f64, &'static str) {
+    match value.abs() {
+        v if v >= 999.95e6 => (value / 1e9, "B"),
+        v if v >= 999.95e3 => (value / 1e6, "M"),
+        v if v >= 1e3 => (value / 1e3, "k"),
+        _ => (value, ""),
+    }
+}
+
+/// Names a single period for use in a sentence with dates in style, e.g. "the week of
+/// 14 Jan"
+pub(crate) fn describe_period(period: &TimeSpan, style: DateStyle) -> String {
+    let start = period.start();
+    match period.frequency() {
+        TimeFrequency::Yearly => start.format("%Y").to_string(),
+        TimeFrequency::Quarterly => format!("Q{} {}", start.month0() / 3 + 1, start.year()),
+        TimeFrequency::Monthly if style == DateStyle::Iso => start.format("%Y-%m").to_string(),
+        TimeFrequency::Monthly => start.format("%B %Y").to_string(),
+        TimeFrequency::Weekly => format!("the week of {}", style.day(&start)),
+        TimeFrequency::Daily => style.date(&start),
+    }
+}
+
+/// Names a proportion as a common fraction, e.g. "a quarter" for 0.25 or "about a third"
+/// for 0.31. None when it isn't within a tenth of one
+pub(crate) fn fraction_words(proportion: f64) -> Option<String> {
+    const FRACTIONS: [(f64, &str); 7] = [
+        (0.1, "a tenth"),
+        (0.2, "a fifth"),
+        (0.25, "a quarter"),
+        (1.0 / 3.0, "a third"),
+        (0.5, "half"),
+        (2.0 / 3.0, "two thirds"),
+        (0.75, "three quarters"),
+    ];
+    let (fraction, words) = FRACTIONS
+        .iter()
+        .copied()
+        .min_by(|(a, _), (b, _)| (a - proportion).abs().total_cmp(&(b - proportion).abs()))?;
+    let error = (proportion - fraction).abs() / fraction;
+    match error {
+        _ if error < 0.001 => Some(words.to_string()),
+        _ if error <= 0.1 => Some(format!("about {}", words)),
+        _ => None,
+    }
+}
+
+/// Writes n as an English ordinal, e.g. 1st, 22nd, 90th
+pub(crate) fn ordinal(n: u32) -> String {
+    let suffix = match (n % 10, n % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{}{}", n, suffix)
+}
+
+/// Total of the datapoints falling within span
+pub(crate) fn total_within(points: &[Datapoint], span: &TimeSpan) -> f64 {
+    points
+        .iter()
+        .filter(|point| span.contains(&point.when()))
+        .map(|point| point.value())
+        .sum()
+}
+
+/// Writes n as an ordinal word up to tenth, and in digits beyond
+pub(crate) fn ordinal_word(n: u32) -> String {
+    const WORDS: [&str; 10] = [
+        "first", "second", "third", "fourth", "fifth", "sixth", "seventh", "eighth", "ninth",
+        "tenth",
+    ];
+    match n {
+        1..=10 => WORDS[n as usize - 1].to_string(),
+        _ => ordinal(n),
+    }
+}
+
+/// Draws values as a row of block characters scaled between their minimum and maximum
+pub(crate) fn sparkline(values: &[f64]) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    values
+        .iter()
+        .map(|value| {
+            if max == min {
+                BARS[3]
+            } else {
+                BARS[((value - min) / (max - min) * 7.0).round() as usize]
+            }
+        })
+        .collect()
+}
+
+/// Writes the time between two dates in the largest sensible unit, e.g. "14 months"
+pub(crate) fn describe_elapsed(from: &NaiveDate, to: &NaiveDate) -> String {
+    let months = (to.year() - from.year()) * 12 + to.month() as i32 - from.month() as i32;
+    let days = (*to - *from).num_days();
+    let (count, noun) = match () {
+        _ if months >= 24 => (i64::from(months / 12), "year"),
+        _ if months >= 2 => (i64::from(months), "month"),
+        _ if days >= 14 => (days / 7, "week"),
+        _ => (days, "day"),
+    };
+    inflection::count(count, noun)
+}
+
+/// Joins items as an English list, e.g. "a, b and c"
+pub(crate) fn join_list(items: &[String]) -> String {
+    match items {
+        [] => String::new(),
+        [only] => only.clone(),
+        [rest @ .., last] => format!("{} and {}", rest.join(", "), last),
+    }
+}
+
+/// Proportional change in total from the span before span, None when that total is zero
+pub(crate) fn change_over(points: &[Datapoint], span: &TimeSpan) -> Result<Option<f64>, DateError> {
+    let old = total_within(points, &span.prev()?);
+    let new = total_within(points, span);
+    Ok((old != 0.0).then(|| (new - old) / old))
+}
+
+/// The mean of a metric's per-period totals over a trailing window
+#[derive(Serialize, Deserialize)]
+pub struct FigMovingAvg {
+    average: f64,
+    window: TimeSpan,
+    metric: Metric,
+    when: NaiveDate,
+}
+
+impl Figure for FigMovingAvg {
+    fn metric_info(&self) -> &Metric {
+        &self.metric
+    }
+
+    fn when(&self) -> &NaiveDate {
+        &self.when
+    }
+
+    fn raw_value(&self) -> Option<f64> {
+        Some(self.average)
+    }
+}
+
+impl FigMovingAvg {
+    /// Averages the last periods periods of the metric's frequency up to and including when
+    pub fn new(
+        metric: Metric,
+        when: NaiveDate,
+        periods: u32,
+        points: &[Datapoint],
+    ) -> Result<FigMovingAvg, DateError> {
+        let window = metric.trailing(periods, &when)?;
+        let totals = period_totals(points, &window)?;
+        let average = totals.iter().map(|(_, total)| total).sum::<f64>() / totals.len() as f64;
+        Ok(FigMovingAvg {
+            average,
+            window,
+            metric,
+            when,
+        })
+    }
+
+    pub fn average(&self) -> f64 {
+        self.average
+    }
+
+    pub fn window(&self) -> &TimeSpan {
+        &self.window
+    }
+}
+
+impl Display for FigMovingAvg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            self.format(
+                &self.metric.print_text,
+                format!(
+                    "averaging {} over {}",
+                    self.metric
+                        .in_units(&self.metric.format_fixed(self.average, 1)),
+                    inflection::last(self.window.periods(), self.window.frequency().noun())
+                )
+            )
+        )
+    }
+}
+
+/// The running total of a metric from an anchor date, by default the start of the year
+#[derive(Serialize, Deserialize)]
+pub struct FigCumulative {
+    total: f64,
+    from: NaiveDate,
+    year_to_date: bool,
+    metric: Metric,
+    when: NaiveDate,
+}
+
+impl Figure for FigCumulative {
+    fn metric_info(&self) -> &Metric {
+        &self.metric
+    }
+
+    fn when(&self) -> &NaiveDate {
+        &self.when
+    }
+
+    fn raw_value(&self) -> Option<f64> {
+        Some(self.total)
+    }
+}
+
+impl FigCumulative {
+    /// Sums the datapoints from the first of January up to and including when
+    pub fn year_to_date(
+        metric: Metric,
+        when: NaiveDate,
+        points: &[Datapoint],
+    ) -> Result<FigCumulative, DateError> {
+        let from = TimeSpan::to_date(&when, TimeFrequency::Yearly, metric.week_start())?.start();
+        let mut figure = FigCumulative::since(metric, from, when, points);
+        figure.year_to_date = true;
+        Ok(figure)
+    }
+
+    /// Sums the datapoints from from up to and including when
+    pub fn since(
+        metric: Metric,
+        from: NaiveDate,
+        when: NaiveDate,
+        points: &[Datapoint],
+    ) -> FigCumulative {
+        let total = points
+            .iter()
+            .filter(|point| from <= point.when() && point.when() <= when)
+            .map(|point| point.value())
+            .sum();
+        FigCumulative {
+            total,
+            from,
+            year_to_date: false,
+            metric,
+            when,
+        }
+    }
+
+    pub fn total(&self) -> f64 {
+        self.total
+    }
+}
+
+impl Display for FigCumulative {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let period = if self.year_to_date {
+            String::from("so far this year")
+        } else {
+            format!("since {}", self.metric.date_style().date(&self.from))
+        };
+        write!(
+            f,
+            "{}",
+            self.format(
+                &self.metric.print_text,
+                format!("{} {}", self.metric.format_amount(self.total), period)
+            )
+        )
+    }
+}
+
+/// Whether a figure looks for the highest or lowest value
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Extreme {
+    Max,
+    Min,
+}
+
+/// The period with the highest or lowest total, preferring the most recent on ties
+fn extreme_of(
+    extreme: Extreme,
+    totals: Vec<(TimeSpan, f64)>,
+) -> Result<(TimeSpan, f64), DateError> {
+    totals
+        .into_iter()
+        .reduce(|best, next| {
+            let better = match extreme {
+                Extreme::Max => next.1 >= best.1,
+                Extreme::Min => next.1 <= best.1,
+            };
+            if better {
+                next
+            } else {
+                best
+            }
+        })
+        .ok_or(DateError::EmptySpan)
+}
+
+/// The highest or lowest per-period total of a metric within a lookback window
+#[derive(Serialize, Deserialize)]
+pub struct FigExtreme {
+    extreme: Extreme,
+    value: f64,
+    period: TimeSpan,
+    window: TimeSpan,
+    metric: Metric,
+    when: NaiveDate,
+}
+
+impl Figure for FigExtreme {
+    fn metric_info(&self) -> &Metric {
+        &self.metric
+    }
+
+    fn when(&self) -> &NaiveDate {
+        &self.when
+    }
+
+    fn raw_value(&self) -> Option<f64> {
+        Some(self.value)
+    }
+}
+
+impl FigExtreme {
+    /// Finds the extreme total among the last periods periods up to and including when,
+    /// preferring the most recent period on ties
+    pub fn new(
+        extreme: Extreme,
+        metric: Metric,
+        when: NaiveDate,
+        periods: u32,
+        points: &[Datapoint],
+    ) -> Result<FigExtreme, DateError> {
+        let window = metric.trailing(periods, &when)?;
+        let (period, value) = extreme_of(extreme, period_totals(points, &window)?)?;
+        Ok(FigExtreme {
+            extreme,
+            value,
+            period,
+            window,
+            metric,
+            when,
+        })
+    }
+
+    pub fn max_over(
+        metric: Metric,
+        when: NaiveDate,
+        periods: u32,
+        points: &[Datapoint],
+    ) -> Result<FigExtreme, DateError> {
+        FigExtreme::new(Extreme::Max, metric, when, periods, points)
+    }
+
+    pub fn min_over(
+        metric: Metric,
+        when: NaiveDate,
+        periods: u32,
+        points: &[Datapoint],
+    ) -> Result<FigExtreme, DateError> {
+        FigExtreme::new(Extreme::Min, metric, when, periods, points)
+    }
+
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+
+    /// The period the extreme value occurred in
+    pub fn period(&self) -> &TimeSpan {
+        &self.period
+    }
+}
+
+impl Display for FigExtreme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let noun = match self.extreme {
+            Extreme::Max => "a peak",
+            Extreme::Min => "a low",
+        };
+        write!(
+            f,
+            "{}",
+            self.format(
+                &self.metric.print_text,
+                format!(
+                    "{} of {} in {}",
+                    noun,
+                    self.metric.format_amount(self.value),
+                    describe_period(&self.period, self.metric.date_style())
+                )
+            )
+        )
+    }
+}
+
+/// A percentile (by default the median) of a metric's per-period totals over a window
+#[derive(Serialize, Deserialize)]
+pub struct FigPercentile {
+    percentile: f64,
+    value: f64,
+    window: TimeSpan,
+    metric: Metric,
+    when: NaiveDate,
+}
+
+impl Figure for FigPercentile {
+    fn metric_info(&self) -> &Metric {
+        &self.metric
+    }
+
+    fn when(&self) -> &NaiveDate {
+        &self.when
+    }
+
+    fn raw_value(&self) -> Option<f64> {
+        Some(self.value)
+    }
+}
+
+impl FigPercentile {
+    /// Takes the percentile of the totals of the last periods periods up to and including when
+    pub fn new(
+        percentile: f64,
+        metric: Metric,
+        when: NaiveDate,
+        periods: u32,
+        points: &[Datapoint],
+    ) -> Result<FigPercentile, DateError> {
+        let window = metric.trailing(periods, &when)?;
+        let totals: Vec<f64> = period_totals(points, &window)?
+            .into_iter()
+            .map(|(_, total)| total)
+            .collect();
+        let value = stats::percentile(&totals, percentile).ok_or(DateError::EmptySpan)?;
+        Ok(FigPercentile {
+            percentile,
+            value,
+            window,
+            metric,
+            when,
+        })
+    }
+
+    pub fn median(
+        metric: Metric,
+        when: NaiveDate,
+        periods: u32,
+        points: &[Datapoint],
+    ) -> Result<FigPercentile, DateError> {
+        FigPercentile::new(50.0, metric, when, periods, points)
+    }
+
+    /// Parses `median` or `percentile:<p>` into the percentile it asks for
+    pub fn parse(spec: &str) -> Option<f64> {
+        let p = match spec {
+            "median" => 50.0,
+            _ => spec.strip_prefix("percentile:")?.parse().ok()?,
+        };
+        (0.0..=100.0).contains(&p).then_some(p)
+    }
+
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+}
+
+impl Display for FigPercentile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let data = if self.percentile == 50.0 {
+            format!("a median of {}", self.metric.format_amount(self.value))
+        } else {
+            format!(
+                "{} of {}",
+                inflection::with_article(&format!(
+                    "{} percentile",
+                    ordinal(self.percentile.round() as u32)
+                )),
+                self.metric.format_amount(self.value)
+            )
+        };
+        write!(f, "{}", self.format(&self.metric.print_text, data))
+    }
+}
+
+/// The spread of a metric's per-period totals over a trailing window
+#[derive(Serialize, Deserialize)]
+pub struct FigVolatility {
+    std_dev: f64,
+    mean: f64,
+    window: TimeSpan,
+    metric: Metric,
+    when: NaiveDate,
+}
+
+impl Figure for FigVolatility {
+    fn metric_info(&self) -> &Metric {
+        &self.metric
+    }
+
+    fn when(&self) -> &NaiveDate {
+        &self.when
+    }
+
+    fn raw_value(&self) -> Option<f64> {
+        Some(self.std_dev)
+    }
+}
+
+impl FigVolatility {
+    /// Measures the totals of the last periods periods up to and including when, which
+    /// needs at least two periods
+    pub fn new(
+        metric: Metric,
+        when: NaiveDate,
+        periods: u32,
+        points: &[Datapoint],
+    ) -> Result<FigVolatility, DateError> {
+        let window = metric.trailing(periods, &when)?;
+        let totals: Vec<f64> = period_totals(points, &window)?
+            .into_iter()
+            .map(|(_, total)| total)
+            .collect();
+        Ok(FigVolatility {
+            std_dev: stats::std_dev(&totals).ok_or(DateError::EmptySpan)?,
+            mean: stats::mean(&totals).ok_or(DateError::EmptySpan)?,
+            window,
+            metric,
+            when,
+        })
+    }
+
+    pub fn std_dev(&self) -> f64 {
+        self.std_dev
+    }
+
+    /// Standard deviation as a proportion of the mean, None when the mean is zero
+    pub fn coefficient_of_variation(&self) -> Option<f64> {
+        (self.mean != 0.0).then(|| self.std_dev / self.mean.abs())
+    }
+}
+
+impl Display for FigVolatility {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut data = format!(
+            "a standard deviation of {}",
+            self.metric
+                .in_units(&self.metric.format_fixed(self.std_dev, 1))
+        );
+        if let Some(cv) = self.coefficient_of_variation() {
+            data.push_str(&format!(
+                " ({} of the mean)",
+                self.metric.format_percent(100.0 * cv, 1)
+            ));
+        }
+        write!(f, "{}", self.format(&self.metric.print_text, data))
+    }
+}
+
+/// How a metric's actual value over a span compares with its target
+#[derive(Serialize, Deserialize)]
+pub struct FigVsTarget {
+    actual: f64,
+    target: f64,
+    absolute: bool,
+    metric: Metric,
+    when: NaiveDate,
+}
+
+impl Figure for FigVsTarget {
+    fn metric_info(&self) -> &Metric {
+        &self.metric
+    }
+
+    fn when(&self) -> &NaiveDate {
+        &self.when
+    }
+
+    fn raw_value(&self) -> Option<f64> {
+        Some(self.gap())
+    }
+}
+
+impl FigVsTarget {
+    pub fn new(metric: Metric, when: NaiveDate, actual: f64, target: f64) -> FigVsTarget {
+        FigVsTarget {
+            actual,
+            target,
+            absolute: false,
+            metric,
+            when,
+        }
+    }
+
+    /// Compares the total over span with the target set for that span, if there is one
+    pub fn for_span(
+        metric: Metric,
+        span: &TimeSpan,
+        points: &[Datapoint],
+        targets: &[Target],
+    ) -> Option<FigVsTarget> {
+        let target = Target::find(targets, span)?;
+        Some(FigVsTarget::new(
+            metric,
+            span.end(),
+            total_within(points, span),
+            target.value(),
+        ))
+    }
+
+    /// Renders the gap in the metric's units rather than as a percentage
+    pub fn absolute(mut self) -> FigVsTarget {
+        self.absolute = true;
+        self
+    }
+
+    /// Actual minus target, positive when ahead
+    pub fn gap(&self) -> f64 {
+        self.actual - self.target
+    }
+}
+
+impl Display for FigVsTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let gap = self.gap();
+        let data = if gap == 0.0 {
+            String::from("on target")
+        } else if self.absolute || self.target == 0.0 {
+            // A gap from a zero target has no proportion, so is written in units
+            let side = if gap > 0.0 { "above" } else { "below" };
+            format!("{} {} target", self.metric.format_amount(gap.abs()), side)
+        } else {
+            let side = if gap > 0.0 { "ahead of" } else { "behind" };
+            format!(
+                "{} {} target",
+                self.metric
+                    .format_percent(100.0 * (gap / self.target).abs(), 1),
+                side
+            )
+        };
+        write!(f, "{}", self.format(&self.metric.print_text, data))
+    }
+}
+
+/// How a forecast extends the history in its window
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trend {
+    /// Continues the least squares straight line through the window
+    Linear,
+    /// Assumes the next period matches the window's average
+    Mean,
+}
+
+/// How a projection's likely range is written
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntervalStyle {
+    /// After the sentence, e.g. ", likely between 580 and 660"
+    Words,
+    /// In brackets after the projection, e.g. "620 (580–660)"
+    Numbers,
+}
+
+/// Standard deviations either side of a projection its likely range covers, roughly 90%
+/// of outcomes if they vary as they have historically
+const INTERVAL_Z: f64 = 1.645;
+
+/// Writes the range around a projection in style, as a suffix for the projected amount and
+/// one for the sentence
+fn describe_interval(
+    metric: &Metric,
+    (low, high): (f64, f64),
+    style: Option<IntervalStyle>,
+) -> (String, String) {
+    match style {
+        None => (String::new(), String::new()),
+        Some(IntervalStyle::Words) => (
+            String::new(),
+            format!(
+                ", likely between {} and {}",
+                metric.format_number(low),
+                metric.format_amount(high)
+            ),
+        ),
+        Some(IntervalStyle::Numbers) => (
+            format!(
+                " ({}–{})",
+                metric.format_number(low),
+                metric.format_number(high)
+            ),
+            String::new(),
+        ),
+    }
+}
+
+/// A projection of a metric's total for the period after the window
+#[derive(Serialize, Deserialize)]
+pub struct FigForecast {
+    projected: f64,
+    spread: f64,
+    interval: Option<IntervalStyle>,
+    next: TimeSpan,
+    trend: Trend,
+    window: TimeSpan,
+    metric: Metric,
+    when: NaiveDate,
+}
+
+impl Figure for FigForecast {
+    fn metric_info(&self) -> &Metric {
+        &self.metric
+    }
+
+    fn when(&self) -> &NaiveDate {
+        &self.when
+    }
+
+    fn raw_value(&self) -> Option<f64> {
+        Some(self.projected)
+    }
+}
+
+impl FigForecast {
+    /// Fits trend to the totals of the last periods periods up to and including when,
+    /// which needs at least two periods
+    pub fn new(
+        trend: Trend,
+        metric: Metric,
+        when: NaiveDate,
+        periods: u32,
+        points: &[Datapoint],
+    ) -> Result<FigForecast, DateError> {
+        let window = metric.trailing(periods, &when)?;
+        let totals: Vec<f64> = period_totals(points, &window)?
+            .into_iter()
+            .map(|(_, total)| total)
+            .collect();
+        let fitted: Vec<f64> = match trend {
+            Trend::Linear => {
+                let (slope, intercept) = stats::linear_fit(&totals).ok_or(DateError::EmptySpan)?;
+                (0..=totals.len())
+                    .map(|i| intercept + slope * i as f64)
+                    .collect()
+            }
+            Trend::Mean => {
+                let mean = stats::mean(&totals).ok_or(DateError::EmptySpan)?;
+                vec![mean; totals.len() + 1]
+            }
+        };
+        let residuals: Vec<f64> = totals
+            .iter()
+            .zip(&fitted)
+            .map(|(total, fit)| total - fit)
+            .collect();
+        Ok(FigForecast {
+            projected: fitted[totals.len()],
+            spread: stats::std_dev(&residuals).unwrap_or(0.0),
+            interval: None,
+            next: metric.span(&window.end())?.next()?,
+            trend,
+            window,
+            metric,
+            when,
+        })
+    }
+
+    pub fn projected(&self) -> f64 {
+        self.projected
+    }
+
+    /// Also writes the range the total is likely to fall in, from how far the window's
+    /// totals strayed from the trend
+    pub fn with_interval(mut self, style: IntervalStyle) -> FigForecast {
+        self.interval = Some(style);
+        self
+    }
+
+    /// The lowest and highest totals likely for the period being forecast
+    pub fn likely_range(&self) -> (f64, f64) {
+        let margin = INTERVAL_Z * self.spread;
+        (self.projected - margin, self.projected + margin)
+    }
+
+    /// The period being forecast
+    pub fn next(&self) -> &TimeSpan {
+        &self.next
+    }
+}
+
+impl Display for FigForecast {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            self.format(&self.metric.print_text, {
+                let (low, high) = self.likely_range();
+                let (amount_suffix, suffix) =
+                    describe_interval(&self.metric, (low.round(), high.round()), self.interval);
+                format!(
+                    "on course for around {}{} in {}{}",
+                    self.metric.format_amount(self.projected.round()),
+                    amount_suffix,
+                    describe_period(&self.next, self.metric.date_style()),
+                    suffix
+                )
+            })
+        )
+    }
+}
+
+/// The constant yearly growth rate which takes a metric from one value to another
+#[derive(Serialize, Deserialize)]
+pub struct FigCagr {
+    start_value: f64,
+    end_value: f64,
+    from: NaiveDate,
+    metric: Metric,
+    when: NaiveDate,
+}
+
+impl Figure for FigCagr {
+    fn metric_info(&self) -> &Metric {
+        &self.metric
+    }
+
+    fn when(&self) -> &NaiveDate {
+        &self.when
+    }
+
+    fn raw_value(&self) -> Option<f64> {
+        self.rate()
+    }
+}
+
+impl FigCagr {
+    pub fn new(
+        metric: Metric,
+        from: NaiveDate,
+        when: NaiveDate,
+        start_value: f64,
+        end_value: f64,
+    ) -> FigCagr {
+        FigCagr {
+            start_value,
+            end_value,
+            from,
+            metric,
+            when,
+        }
+    }
+
+    /// Compares the totals of the metric's periods containing from and when
+    pub fn since(
+        metric: Metric,
+        from: NaiveDate,
+        when: NaiveDate,
+        points: &[Datapoint],
+    ) -> Result<FigCagr, DateError> {
+        let first = metric.span(&from)?;
+        let last = metric.span(&when)?;
+        Ok(FigCagr::new(
+            metric,
+            first.start(),
+            last.start(),
+            total_within(points, &first),
+            total_within(points, &last),
+        ))
+    }
+
+    /// Growth per year as a proportion, None when it can't be calculated from the values
+    pub fn rate(&self) -> Option<f64> {
+        let years = (self.when - self.from).num_days() as f64 / 365.25;
+        if years <= 0.0 || self.start_value <= 0.0 || self.end_value < 0.0 {
+            return None;
+        }
+        Some((self.end_value / self.start_value).powf(1.0 / years) - 1.0)
+    }
+}
+
+impl Display for FigCagr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let data = match self.rate() {
+            Some(rate) => format!(
+                "a {} compound annual growth rate since {}",
+                self.metric.format_percent(100.0 * rate, 1),
+                self.from.format("%Y")
+            ),
+            None => format!(
+                "n/a as a compound annual growth rate since {}",
+                self.from.format("%Y")
+            ),
+        };
+        write!(f, "{}", self.format(&self.metric.print_text, data))
+    }
+}
+
+/// One metric divided by another over the same span, such as conversions per visit
+#[derive(Serialize, Deserialize)]
+pub struct FigRatio {
+    quotient: f64,
+    percent: bool,
+    metric: Metric,
+    per: Metric,
+    when: NaiveDate,
+}
+
+impl Figure for FigRatio {
+    fn metric_info(&self) -> &Metric {
+        &self.metric
+    }
+
+    fn when(&self) -> &NaiveDate {
+        &self.when
+    }
+
+    fn raw_value(&self) -> Option<f64> {
+        Some(self.quotient)
+    }
+}
+
+impl FigRatio {
+    /// None when denominator is zero
+    pub fn new(
+        metric: Metric,
+        per: Metric,
+        when: NaiveDate,
+        numerator: f64,
+        denominator: f64,
+    ) -> Option<FigRatio> {
+        (denominator != 0.0).then(|| FigRatio {
+            quotient: numerator / denominator,
+            percent: false,
+            metric,
+            per,
+            when,
+        })
+    }
+
+    /// Divides the total of points by the total of per_points over span
+    pub fn for_span(
+        metric: Metric,
+        per: Metric,
+        span: &TimeSpan,
+        points: &[Datapoint],
+        per_points: &[Datapoint],
+    ) -> Option<FigRatio> {
+        FigRatio::new(
+            metric,
+            per,
+            span.end(),
+            total_within(points, span),
+            total_within(per_points, span),
+        )
+    }
+
+    /// Normalises the metric by another over span, such as purrs per cat or revenue per
+    /// employee
+    pub fn per(
+        metric: Metric,
+        per: Metric,
+        span: &TimeSpan,
+        points: &[Datapoint],
+        per_points: &[Datapoint],
+    ) -> Option<FigRatio> {
+        FigRatio::for_span(metric, per, span, points, per_points)
+    }
+
+    /// The metric's share of a total metric over span, such as mobile visits out of all
+    /// visits, rendered as a percentage
+    pub fn share_of(
+        metric: Metric,
+        total: Metric,
+        span: &TimeSpan,
+        points: &[Datapoint],
+        total_points: &[Datapoint],
+    ) -> Option<FigRatio> {
+        FigRatio::for_span(metric, total, span, points, total_points).map(FigRatio::percent)
+    }
+
+    /// Renders the quotient as a percentage of the denominator metric
+    pub fn percent(mut self) -> FigRatio {
+        self.percent = true;
+        self
+    }
+
+    pub fn quotient(&self) -> f64 {
+        self.quotient
+    }
+}
+
+impl Display for FigRatio {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let data = if self.percent {
+            format!(
+                "{} of {}",
+                self.metric.format_percent(100.0 * self.quotient, 1),
+                self.per.name
+            )
+        } else {
+            let per = self
+                .per
+                .unit()
+                .map_or(self.per.name(), inflection::singular);
+            let quotient = self
+                .metric
+                .in_units(&self.metric.format_fixed(self.quotient, 2));
+            format!("{} per {}", quotient, per)
+        };
+        write!(f, "{}", self.format(&self.metric.print_text, data))
+    }
+}
+
+/// Phrases for describing a change, each used while the size of the change is below
+/// its limit
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Thresholds {
+    bands: Vec<Band>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct Band {
+    below: f64,
+    rising: String,
+    falling: String,
+}
+
+impl Thresholds {
+    /// An empty set of bands, which describes every change with the final phrases given
+    pub fn new(rising: &str, falling: &str) -> Thresholds {
+        Thresholds {
+            bands: vec![Band {
+                below: f64::INFINITY,
+                rising: rising.to_string(),
+                falling: falling.to_string(),
+            }],
+        }
+    }
+
+    /// Adds phrases for changes smaller than below, a proportion such as 0.05 for 5%
+    pub fn band(mut self, below: f64, rising: &str, falling: &str) -> Thresholds {
+        let band = Band {
+            below,
+            rising: rising.to_string(),
+            falling: falling.to_string(),
+        };
+        let position = self.bands.partition_point(|other| other.below < below);
+        self.bands.insert(position, band);
+        self
+    }
+
+    /// The phrase for a proportional change
+    pub fn phrase(&self, change: f64) -> &str {
+        let band = self
+            .bands
+            .iter()
+            .find(|band| change.abs() < band.below)
+            .unwrap_or(&self.bands[self.bands.len() - 1]);
+        if change < 0.0 {
+            &band.falling
+        } else {
+            &band.rising
+        }
+    }
+}
+
+impl Default for Thresholds {
+    fn default() -> Thresholds {
+        Thresholds::new("up dramatically", "down dramatically")
+            .band(0.01, "roughly flat", "roughly flat")
+            .band(0.05, "up slightly", "down slightly")
+            .band(0.15, "up modestly", "down modestly")
+            .band(0.5, "up sharply", "down sharply")
+    }
+}
+
+/// A change described in words chosen by its size rather than as a percentage
+#[derive(Serialize, Deserialize)]
+pub struct FigDescribe {
+    old: f64,
+    new: f64,
+    thresholds: Thresholds,
+    metric: Metric,
+    when: NaiveDate,
+}
+
+impl Figure for FigDescribe {
+    fn metric_info(&self) -> &Metric {
+        &self.metric
+    }
+
+    fn when(&self) -> &NaiveDate {
+        &self.when
+    }
+
+    fn compared(&self) -> Option<(f64, f64)> {
+        Some((self.old, self.new))
+    }
+
+    fn raw_value(&self) -> Option<f64> {
+        (self.old != 0.0).then(|| (self.new - self.old) / self.old)
+    }
+}
+
+impl FigDescribe {
+    pub fn new(
+        metric: Metric,
+        when: NaiveDate,
+        old: f64,
+        new: f64,
+        thresholds: Thresholds,
+    ) -> FigDescribe {
+        FigDescribe {
+            old,
+            new,
+            thresholds,
+            metric,
+            when,
+        }
+    }
+}
+
+impl Display for FigDescribe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            self.format(
+                &self.metric.print_text,
+                match self.raw_value() {
+                    Some(change) => self.thresholds.phrase(change),
+                    // Nothing to nothing is flat, but any change from nothing has no size
+                    None if self.new == 0.0 => self.thresholds.phrase(0.0),
+                    None => "n/a",
+                }
+                .to_string()
+            )
+        )
+    }
+}
+
+/// Where a metric's change falls among the changes of a group of metrics
+#[derive(Serialize, Deserialize)]
+pub struct FigRank {
+    rank: usize,
+    increase: bool,
+    group: String,
+    metric: Metric,
+    when: NaiveDate,
+}
+
+impl Figure for FigRank {
+    fn metric_info(&self) -> &Metric {
+        &self.metric
+    }
+
+    fn when(&self) -> &NaiveDate {
+        &self.when
+    }
+
+    fn raw_value(&self) -> Option<f64> {
+        Some(self.rank as f64)
+    }
+}
+
+impl FigRank {
+    /// Ranks the metric's change among the increases, or the decreases if it fell, in
+    /// changes, which maps each member of group to its proportional change. None when the
+    /// metric has no change in changes
+    pub fn new(
+        metric: Metric,
+        when: NaiveDate,
+        group: &MetricGroup,
+        changes: &HashMap<String, f64>,
+    ) -> Option<FigRank> {
+        let change = *changes.get(&metric.name)?;
+        let increase = change >= 0.0;
+        let rank = group
+            .members()
+            .iter()
+            .filter_map(|member| changes.get(member))
+            .filter(|other| {
+                if increase {
+                    **other > change
+                } else {
+                    **other < change
+                }
+            })
+            .count()
+            + 1;
+        Some(FigRank {
+            rank,
+            increase,
+            group: group.name().to_string(),
+            metric,
+            when,
+        })
+    }
+
+    /// Ranks the change over span against the previous span, given each member's datapoints
+    pub fn for_span(
+        metric: Metric,
+        span: &TimeSpan,
+        group: &MetricGroup,
+        points: &HashMap<String, Vec<Datapoint>>,
+    ) -> Result<Option<FigRank>, DateError> {
+        let mut changes = HashMap::new();
+        for (name, points) in points {
+            if let Some(change) = change_over(points, span)? {
+                changes.insert(name.clone(), change);
+            }
+        }
+        Ok(FigRank::new(metric, span.end(), group, &changes))
+    }
+
+    /// Position among the increases or decreases, starting from 1 for the largest
+    pub fn rank(&self) -> usize {
+        self.rank
+    }
+}
+
+impl Display for FigRank {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let place = match self.rank {
+            1 => String::from("the largest"),
+            rank => format!("the {} largest", ordinal(rank as u32)),
+        };
+        let direction = if self.increase {
+            "increase"
+        } else {
+            "decrease"
+        };
+        write!(
+            f,
+            "{}",
+            self.format(
+                &self.metric.print_text,
+                format!("{} {} across {}", place, direction, self.group)
+            )
+        )
+    }
+}
+
+/// How many periods in a row a metric has risen, or fallen
+#[derive(Serialize, Deserialize)]
+pub struct FigStreak {
+    length: u32,
+    growth: bool,
+    period: TimeSpan,
+    metric: Metric,
+    when: NaiveDate,
+}
+
+impl Figure for FigStreak {
+    fn metric_info(&self) -> &Metric {
+        &self.metric
+    }
+
+    fn when(&self) -> &NaiveDate {
+        &self.when
+    }
+
+    fn raw_value(&self) -> Option<f64> {
+        Some(self.length as f64)
+    }
+}
+
+impl FigStreak {
+    /// Counts back from the period containing when through the metric's history, for as
+    /// long as each period's total moved the same way as the latest one
+    pub fn new(
+        metric: Metric,
+        when: NaiveDate,
+        points: &[Datapoint],
+    ) -> Result<FigStreak, DateError> {
+        let period = metric.span(&when)?;
+        let first = points
+            .iter()
+            .map(|point| point.when())
+            .filter(|date| *date <= period.end())
+            .min()
+            .unwrap_or(period.start());
+        let history = metric.span(&first)?.through(&period.end())?;
+        let totals: Vec<f64> = period_totals(points, &history)?
+            .into_iter()
+            .map(|(_, total)| total)
+            .collect();
+        let moves: Vec<f64> = totals.windows(2).map(|pair| pair[1] - pair[0]).collect();
+        let growth = moves.last().is_some_and(|change| *change > 0.0);
+        let length = moves
+            .iter()
+            .rev()
+            .take_while(|change| {
+                if growth {
+                    **change > 0.0
+                } else {
+                    **change < 0.0
+                }
+            })
+            .count() as u32;
+        Ok(FigStreak {
+            length,
+            growth,
+            period,
+            metric,
+            when,
+        })
+    }
+
+    /// Number of consecutive periods, 0 when the latest period was unchanged
+    pub fn length(&self) -> u32 {
+        self.length
+    }
+
+    pub fn growth(&self) -> bool {
+        self.growth
+    }
+}
+
+impl Display for FigStreak {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let noun = self.period.frequency().noun();
+        let data = match self.length {
+            0 => format!("unchanged on the previous {}", noun),
+            length => format!(
+                "the {}{} {} of {}",
+                ordinal_word(length),
+                if length > 1 { " straight" } else { "" },
+                noun,
+                if self.growth { "growth" } else { "decline" }
+            ),
+        };
+        write!(f, "{}", self.format(&self.metric.print_text, data))
+    }
+}
+
+/// A unicode sparkline of a metric's most recent datapoints
+#[derive(Serialize, Deserialize)]
+pub struct FigSpark {
+    values: Vec<f64>,
+    metric: Metric,
+    when: NaiveDate,
+}
+
+impl Figure for FigSpark {
+    fn metric_info(&self) -> &Metric {
+        &self.metric
+    }
+
+    fn when(&self) -> &NaiveDate {
+        &self.when
+    }
+
+    fn raw_value(&self) -> Option<f64> {
+        self.values.last().copied()
+    }
+}
+
+impl FigSpark {
+    /// Takes the last count datapoints on or before when, oldest first
+    pub fn new(metric: Metric, when: NaiveDate, count: usize, points: &[Datapoint]) -> FigSpark {
+        let mut recent: Vec<&Datapoint> =
+            points.iter().filter(|point| point.when() <= when).collect();
+        recent.sort_by_key(|point| point.when());
+        let values = recent
+            .iter()
+            .skip(recent.len().saturating_sub(count))
+            .map(|point| point.value())
+            .collect();
+        FigSpark {
+            values,
+            metric,
+            when,
+        }
+    }
+
+    pub fn values(&self) -> &[f64] {
+        &self.values
+    }
+}
+
+impl Display for FigSpark {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", sparkline(&self.values))
+    }
+}
+
+/// A metric's total rebased so that its total over a baseline span equals 100
+#[derive(Serialize, Deserialize)]
+pub struct FigIndex {
+    index: f64,
+    baseline: TimeSpan,
+    metric: Metric,
+    when: NaiveDate,
+}
+
+impl Figure for FigIndex {
+    fn metric_info(&self) -> &Metric {
+        &self.metric
+    }
+
+    fn when(&self) -> &NaiveDate {
+        &self.when
+    }
+
+    fn raw_value(&self) -> Option<f64> {
+        Some(self.index)
+    }
+}
+
+impl FigIndex {
+    /// Indexes the total over span against the total over baseline, None when the baseline
+    /// total is zero
+    pub fn new(
+        metric: Metric,
+        span: &TimeSpan,
+        baseline: TimeSpan,
+        points: &[Datapoint],
+    ) -> Option<FigIndex> {
+        let base = total_within(points, &baseline);
+        (base != 0.0).then(|| FigIndex {
+            index: 100.0 * total_within(points, span) / base,
+            baseline,
+            metric,
+            when: span.end(),
+        })
+    }
+
+    pub fn index(&self) -> f64 {
+        self.index
+    }
+}
+
+impl Display for FigIndex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let baseline = if self.baseline.periods() == 1 {
+            describe_period(&self.baseline, self.metric.date_style())
+        } else {
+            self.baseline.to_string()
+        };
+        write!(
+            f,
+            "{}",
+            self.format(
+                &self.metric.print_text,
+                format!(
+                    "{} (index, {} = 100)",
+                    self.metric.format_fixed(self.index, 1),
+                    baseline
+                )
+            )
+        )
+    }
+}
+
+/// The change in a metric in its own units rather than as a percentage
+#[derive(Serialize, Deserialize)]
+pub struct FigDiff {
+    old: f64,
+    new: f64,
+    metric: Metric,
+    when: NaiveDate,
+}
+
+impl Figure for FigDiff {
+    fn metric_info(&self) -> &Metric {
+        &self.metric
+    }
+
+    fn when(&self) -> &NaiveDate {
+        &self.when
+    }
+
+    fn compared(&self) -> Option<(f64, f64)> {
+        Some((self.old, self.new))
+    }
+
+    fn raw_value(&self) -> Option<f64> {
+        Some(self.new - self.old)
+    }
+}
+
+impl FigDiff {
+    pub fn new(metric: Metric, when: NaiveDate, old: f64, new: f64) -> FigDiff {
+        FigDiff {
+            old,
+            new,
+            metric,
+            when,
+        }
+    }
+
+    /// Compares the total over span with the total over the span before it
+    pub fn for_span(
+        metric: Metric,
+        span: &TimeSpan,
+        points: &[Datapoint],
+    ) -> Result<FigDiff, DateError> {
+        let old = total_within(points, &span.prev()?);
+        Ok(FigDiff::new(
+            metric,
+            span.end(),
+            old,
+            total_within(points, span),
+        ))
+    }
+
+    pub fn diff(&self) -> f64 {
+        self.new - self.old
+    }
+}
+
+impl Display for FigDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let diff = self.diff();
+        let data = if diff == 0.0 {
+            String::from("unchanged")
+        } else {
+            let direction = self.metric.direction_word(diff);
+            format!("{} {}", direction, self.metric.format_amount(diff.abs()))
+        };
+        write!(f, "{}", self.format(&self.metric.print_text, data))
+    }
+}
+
+/// The strongest or weakest of the metric's periods within a window such as a quarter
+#[derive(Serialize, Deserialize)]
+pub struct FigBestPeriod {
+    extreme: Extreme,
+    value: f64,
+    period: TimeSpan,
+    window: TimeSpan,
+    metric: Metric,
+    when: NaiveDate,
+}
+
+impl Figure for FigBestPeriod {
+    fn metric_info(&self) -> &Metric {
+        &self.metric
+    }
+
+    fn when(&self) -> &NaiveDate {
+        &self.when
+    }
+
+    fn raw_value(&self) -> Option<f64> {
+        Some(self.value)
+    }
+}
+
+impl FigBestPeriod {
+    /// Splits window into periods of the metric's frequency and finds the extreme total
+    pub fn new(
+        extreme: Extreme,
+        metric: Metric,
+        window: &TimeSpan,
+        points: &[Datapoint],
+    ) -> Result<FigBestPeriod, DateError> {
+        let periods = TimeSpan::anchored(&window.start(), metric.frequency, metric.week_start)?
+            .through(&window.end())?;
+        let (period, value) = extreme_of(extreme, period_totals(points, &periods)?)?;
+        Ok(FigBestPeriod {
+            extreme,
+            value,
+            period,
+            window: *window,
+            when: window.end(),
+            metric,
+        })
+    }
+
+    pub fn best_period(
+        metric: Metric,
+        window: &TimeSpan,
+        points: &[Datapoint],
+    ) -> Result<FigBestPeriod, DateError> {
+        FigBestPeriod::new(Extreme::Max, metric, window, points)
+    }
+
+    pub fn worst_period(
+        metric: Metric,
+        window: &TimeSpan,
+        points: &[Datapoint],
+    ) -> Result<FigBestPeriod, DateError> {
+        FigBestPeriod::new(Extreme::Min, metric, window, points)
+    }
+
+    /// The period with the extreme total
+    pub fn period(&self) -> &TimeSpan {
+        &self.period
+    }
+}
+
+impl Display for FigBestPeriod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let adjective = match self.extreme {
+            Extreme::Max => "strongest",
+            Extreme::Min => "weakest",
+        };
+        let window = match self.window.periods() {
+            1 => format!(
+                "in {}",
+                describe_period(&self.window, self.metric.date_style())
+            ),
+            _ => format!("from {}", self.window),
+        };
+        write!(
+            f,
+            "{}",
+            self.format(
+                &self.metric.print_text,
+                format!(
+                    "{} in {} ({}) of any {} {}",
+                    adjective,
+                    describe_period(&self.period, self.metric.date_style()),
+                    self.metric.format_amount(self.value),
+                    self.metric.frequency.noun(),
+                    window
+                )
+            )
+        )
+    }
+}
+
+/// How long since the metric last had a period higher than the latest one
+#[derive(Serialize, Deserialize)]
+pub struct FigSincePeak {
+    value: f64,
+    period: TimeSpan,
+    higher: Option<TimeSpan>,
+    metric: Metric,
+    when: NaiveDate,
+}
+
+impl Figure for FigSincePeak {
+    fn metric_info(&self) -> &Metric {
+        &self.metric
+    }
+
+    fn when(&self) -> &NaiveDate {
+        &self.when
+    }
+
+    fn raw_value(&self) -> Option<f64> {
+        Some(self.value)
+    }
+}
+
+impl FigSincePeak {
+    /// Looks back from the period containing when through the metric's history for the
+    /// most recent period with a higher total
+    pub fn new(
+        metric: Metric,
+        when: NaiveDate,
+        points: &[Datapoint],
+    ) -> Result<FigSincePeak, DateError> {
+        let period = metric.span(&when)?;
+        let first = points
+            .iter()
+            .map(|point| point.when())
+            .filter(|date| *date <= period.end())
+            .min()
+            .unwrap_or(period.start());
+        let history = metric.span(&first)?.through(&period.end())?;
+        let mut totals = period_totals(points, &history)?;
+        let (_, value) = totals.pop().ok_or(DateError::EmptySpan)?;
+        let higher = totals
+            .into_iter()
+            .rev()
+            .find(|(_, total)| *total > value)
+            .map(|(span, _)| span);
+        Ok(FigSincePeak {
+            value,
+            period,
+            higher,
+            metric,
+            when,
+        })
+    }
+
+    /// The latest period with a higher total, None when the latest is the highest on record
+    pub fn higher(&self) -> Option<&TimeSpan> {
+        self.higher.as_ref()
+    }
+}
+
+impl Display for FigSincePeak {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let adjective = match self.metric.frequency {
+            TimeFrequency::Yearly => "yearly",
+            TimeFrequency::Quarterly => "quarterly",
+            TimeFrequency::Monthly => "monthly",
+            TimeFrequency::Weekly => "weekly",
+            TimeFrequency::Daily => "daily",
+        };
+        let since = match &self.higher {
+            Some(higher) => format!(
+                "in {}",
+                describe_elapsed(&higher.start(), &self.period.start())
+            ),
+            None => String::from("on record"),
+        };
+        write!(
+            f,
+            "{}",
+            self.format(
+                &self.metric.print_text,
+                format!(
+                    "{}, the highest {} figure {}",
+                    self.metric.format_amount(self.value),
+                    adjective,
+                    since
+                )
+            )
+        )
+    }
+}
+
+/// The weighted average of several metrics' values, for composite KPIs
+#[derive(Serialize, Deserialize)]
+pub struct FigWeightedAvg {
+    value: f64,
+    group: String,
+    metric: Metric,
+    when: NaiveDate,
+}
+
+impl Figure for FigWeightedAvg {
+    fn metric_info(&self) -> &Metric {
+        &self.metric
+    }
+
+    fn when(&self) -> &NaiveDate {
+        &self.when
+    }
+
+    fn raw_value(&self) -> Option<f64> {
+        Some(self.value)
+    }
+}
+
+impl FigWeightedAvg {
+    /// Averages values, which maps each metric in weights to its value. None when a
+    /// weighted metric has no value or the weights sum to zero
+    pub fn new(
+        metric: Metric,
+        when: NaiveDate,
+        weights: &MetricWeights,
+        values: &HashMap<String, f64>,
+    ) -> Option<FigWeightedAvg> {
+        let mut total = 0.0;
+        let mut total_weight = 0.0;
+        for (member, weight) in weights.weights() {
+            total += values.get(member)? * weight;
+            total_weight += weight;
+        }
+        (total_weight != 0.0).then(|| FigWeightedAvg {
+            value: total / total_weight,
+            group: weights.name().to_string(),
+            metric,
+            when,
+        })
+    }
+
+    /// Averages each weighted metric's total over span, given each metric's datapoints
+    pub fn for_span(
+        metric: Metric,
+        span: &TimeSpan,
+        weights: &MetricWeights,
+        points: &HashMap<String, Vec<Datapoint>>,
+    ) -> Option<FigWeightedAvg> {
+        let values = points
+            .iter()
+            .map(|(name, points)| (name.clone(), total_within(points, span)))
+            .collect();
+        FigWeightedAvg::new(metric, span.end(), weights, &values)
+    }
+
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+}
+
+impl Display for FigWeightedAvg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            self.format(
+                &self.metric.print_text,
+                format!(
+                    "{} (weighted across {})",
+                    self.metric
+                        .in_units(&self.metric.format_fixed(self.value, 2)),
+                    self.group
+                )
+            )
+        )
+    }
+}
+
+/// A round-number threshold the metric's cumulative total passed during a span
+#[derive(Serialize, Deserialize)]
+pub struct FigMilestone {
+    milestone: f64,
+    span: TimeSpan,
+    metric: Metric,
+    when: NaiveDate,
+}
+
+impl Figure for FigMilestone {
+    fn metric_info(&self) -> &Metric {
+        &self.metric
+    }
+
+    fn when(&self) -> &NaiveDate {
+        &self.when
+    }
+
+    fn raw_value(&self) -> Option<f64> {
+        Some(self.milestone)
+    }
+}
+
+impl FigMilestone {
+    /// Finds the highest multiple of step the all-time total passed during span, None when
+    /// it didn't pass one, so that the figure only appears in reports when there's news
+    pub fn new(
+        metric: Metric,
+        span: &TimeSpan,
+        step: f64,
+        points: &[Datapoint],
+    ) -> Option<FigMilestone> {
+        if step <= 0.0 {
+            return None;
+        }
+        let before: f64 = points
+            .iter()
+            .filter(|point| point.when() < span.start())
+            .map(|point| point.value())
+            .sum();
+        let after = before + total_within(points, span);
+        let milestone = (after / step).floor() * step;
+        (milestone > before).then(|| FigMilestone {
+            milestone,
+            span: *span,
+            metric,
+            when: span.end(),
+        })
+    }
+
+    pub fn milestone(&self) -> f64 {
+        self.milestone
+    }
+}
+
+impl Display for FigMilestone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let during = match self.span.periods() {
+            1 => describe_period(&self.span, self.metric.date_style()),
+            _ => self.span.to_string(),
+        };
+        write!(
+            f,
+            "{}",
+            self.format(
+                &self.metric.print_text,
+                format!(
+                    "past {} in total for the first time in {}",
+                    self.metric.format_amount(self.milestone),
+                    during
+                )
+            )
+        )
+    }
+}
+
+/// A span's total compared with the average of the same span over previous years, to tell
+/// seasonal swings apart from genuine growth
+#[derive(Serialize, Deserialize)]
+pub struct FigSeasonal {
+    value: f64,
+    seasonal: f64,
+    years: u32,
+    metric: Metric,
+    when: NaiveDate,
+}
+
+impl Figure for FigSeasonal {
+    fn metric_info(&self) -> &Metric {
+        &self.metric
+    }
+
+    fn when(&self) -> &NaiveDate {
+        &self.when
+    }
+
+    fn raw_value(&self) -> Option<f64> {
+        Some(self.change())
+    }
+}
+
+impl FigSeasonal {
+    /// Compares span with the mean of the same span in each of the previous years years,
+    /// None when that mean is zero
+    pub fn new(
+        metric: Metric,
+        span: &TimeSpan,
+        years: u32,
+        points: &[Datapoint],
+    ) -> Result<Option<FigSeasonal>, DateError> {
+        let mut earlier = Vec::new();
+        for year in 1..=years {
+            earlier.push(total_within(points, &span.years_earlier(year)?));
+        }
+        let seasonal = stats::mean(&earlier).ok_or(DateError::EmptySpan)?;
+        Ok((seasonal != 0.0).then(|| FigSeasonal {
+            value: total_within(points, span),
+            seasonal,
+            years,
+            metric,
+            when: span.end(),
+        }))
+    }
+
+    /// Mean total of the same span in previous years
+    pub fn seasonal(&self) -> f64 {
+        self.seasonal
+    }
+
+    /// Proportional difference from the seasonal average
+    pub fn change(&self) -> f64 {
+        (self.value - self.seasonal) / self.seasonal
+    }
+}
+
+impl Display for FigSeasonal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let change = self.change();
+        let comparison = match self.years {
+            1 => String::from("the same time last year"),
+            years => format!(
+                "the average for the same time over the last {} years",
+                years
+            ),
+        };
+        let data = if change == 0.0 {
+            format!("level with {}", comparison)
+        } else {
+            let direction = self.metric.direction_word(change);
+            format!(
+                "{} {} on {}",
+                direction,
+                self.metric.format_percent(100.0 * change.abs(), 1),
+                comparison
+            )
+        };
+        write!(f, "{}", self.format(&self.metric.print_text, data))
+    }
+}
+
+/// Changes on the previous period at several frequencies, combined into one sentence
+#[derive(Serialize, Deserialize)]
+pub struct FigSummary {
+    changes: Vec<(TimeFrequency, f64)>,
+    metric: Metric,
+    when: NaiveDate,
+}
+
+impl Figure for FigSummary {
+    fn metric_info(&self) -> &Metric {
+        &self.metric
+    }
+
+    fn when(&self) -> &NaiveDate {
+        &self.when
+    }
+}
+
+impl FigSummary {
+    /// Compares the period of each frequency containing when with the one before, skipping
+    /// frequencies whose previous total is zero. None when every frequency is skipped
+    pub fn new(
+        metric: Metric,
+        when: NaiveDate,
+        frequencies: &[TimeFrequency],
+        points: &[Datapoint],
+    ) -> Result<Option<FigSummary>, DateError> {
+        let mut changes = Vec::new();
+        for frequency in frequencies {
+            let span = TimeSpan::anchored(&when, *frequency, metric.week_start)?;
+            if let Some(change) = change_over(points, &span)? {
+                changes.push((*frequency, change));
+            }
+        }
+        Ok((!changes.is_empty()).then_some(FigSummary {
+            changes,
+            metric,
+            when,
+        }))
+    }
+
+    /// Each frequency with its proportional change on the previous period
+    pub fn changes(&self) -> &[(TimeFrequency, f64)] {
+        &self.changes
+    }
+}
+
+impl Display for FigSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut previous = None;
+        let parts: Vec<String> = self
+            .changes
+            .iter()
+            .map(|(frequency, change)| {
+                let direction = self.metric.direction_word(*change);
+                let amount = format!(
+                    "{} on last {}",
+                    self.metric.format_percent(100.0 * change.abs(), 1),
+                    frequency.noun()
+                );
+                // Only repeat the direction when it differs from the part before
+                let part = if previous == Some(direction) {
+                    amount
+                } else {
+                    format!("{} {}", direction, amount)
+                };
+                previous = Some(direction);
+                part
+            })
+            .collect();
+        write!(
+            f,
+            "{}",
+            self.format(&self.metric.print_text, join_list(&parts))
+        )
+    }
+}
+
+/// A metric's total compared with its budget, as both an amount and a percentage
+#[derive(Serialize, Deserialize)]
+pub struct FigVsBudget {
+    actual: f64,
+    budget: f64,
+    metric: Metric,
+    when: NaiveDate,
+}
+
+impl Figure for FigVsBudget {
+    fn metric_info(&self) -> &Metric {
+        &self.metric
+    }
+
+    fn when(&self) -> &NaiveDate {
+        &self.when
+    }
+
+    fn raw_value(&self) -> Option<f64> {
+        Some(self.variance())
+    }
+}
+
+impl FigVsBudget {
+    pub fn new(metric: Metric, when: NaiveDate, actual: f64, budget: f64) -> FigVsBudget {
+        FigVsBudget {
+            actual,
+            budget,
+            metric,
+            when,
+        }
+    }
+
+    /// Compares the total over span with its budget, spreading a longer budget over span
+    /// when none was set for span itself
+    pub fn for_span(
+        metric: Metric,
+        span: &TimeSpan,
+        points: &[Datapoint],
+        budgets: &[Budget],
+    ) -> Option<FigVsBudget> {
+        let budget = Budget::for_span(budgets, span)?;
+        Some(FigVsBudget::new(
+            metric,
+            span.end(),
+            total_within(points, span),
+            budget,
+        ))
+    }
+
+    /// Actual minus budget, positive when over budget
+    pub fn variance(&self) -> f64 {
+        self.actual - self.budget
+    }
+}
+
+impl Display for FigVsBudget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let variance = self.variance();
+        let data = if variance == 0.0 {
+            String::from("on budget")
+        } else {
+            let side = if variance > 0.0 { "over" } else { "under" };
+            let amount = self.metric.format_amount(variance.abs());
+            if self.budget == 0.0 {
+                format!("{} {} budget", amount, side)
+            } else {
+                let percent = self
+                    .metric
+                    .format_percent(100.0 * (variance / self.budget).abs(), 1);
+                format!("{} ({}) {} budget", amount, percent, side)
+            }
+        };
+        write!(f, "{}", self.format(&self.metric.print_text, data))
+    }
+}
+
+/// A period-to-date total projected forward to the whole period
+#[derive(Serialize, Deserialize)]
+pub struct FigRunRate {
+    value: f64,
+    projected: bool,
+    spread: f64,
+    interval: Option<IntervalStyle>,
+    span: TimeSpan,
+    metric: Metric,
+    when: NaiveDate,
+}
+
+impl Figure for FigRunRate {
+    fn metric_info(&self) -> &Metric {
+        &self.metric
+    }
+
+    fn when(&self) -> &NaiveDate {
+        &self.when
+    }
+
+    fn raw_value(&self) -> Option<f64> {
+        Some(self.value)
+    }
+}
+
+impl FigRunRate {
+    /// Projects the total for span as of report_date. Only the extrapolate policy projects
+    /// an unfinished span; otherwise the total is read over whichever span policy picks
+    pub fn new(
+        metric: Metric,
+        span: &TimeSpan,
+        report_date: NaiveDate,
+        policy: PartialPolicy,
+        points: &[Datapoint],
+    ) -> Result<FigRunRate, DateError> {
+        let (read, factor) = span.with_policy(policy, &report_date)?;
+        let projected = factor != 1.0;
+        let spread = if projected {
+            let days = TimeSpan::new(&read.start(), TimeFrequency::Daily)?.through(&read.end())?;
+            let daily: Vec<f64> = period_totals(points, &days)?
+                .into_iter()
+                .map(|(_, total)| total)
+                .collect();
+            let remaining = (span.days() - read.days()) as f64;
+            stats::std_dev(&daily).unwrap_or(0.0) * remaining.sqrt()
+        } else {
+            0.0
+        };
+        Ok(FigRunRate {
+            value: total_within(points, &read) * factor,
+            projected,
+            spread,
+            interval: None,
+            span: if factor != 1.0 { *span } else { read },
+            metric,
+            when: report_date,
+        })
+    }
+
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+
+    /// Whether the value is a projection rather than a total of actual datapoints
+    pub fn is_projected(&self) -> bool {
+        self.projected
+    }
+
+    /// Also writes the range a projected total is likely to fall in, from how much daily
+    /// totals have varied so far
+    pub fn with_interval(mut self, style: IntervalStyle) -> FigRunRate {
+        self.interval = Some(style);
+        self
+    }
+
+    /// The lowest and highest totals likely for the span, the value itself when it isn't
+    /// projected
+    pub fn likely_range(&self) -> (f64, f64) {
+        let margin = INTERVAL_Z * self.spread;
+        (self.value - margin, self.value + margin)
+    }
+}
+
+impl Display for FigRunRate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let data = if self.projected {
+            let (low, high) = self.likely_range();
+            let (amount_suffix, suffix) = describe_interval(
+                &self.metric,
+                (round_significant(low, 2), round_significant(high, 2)),
+                self.interval,
+            );
+            format!(
+                "on track for roughly {}{} this {}{}",
+                self.metric.format_amount(round_significant(self.value, 2)),
+                amount_suffix,
+                self.span.frequency().noun(),
+                suffix
+            )
+        } else if self.span.is_to_date() {
+            format!(
+                "{} so far this {}",
+                self.metric.format_amount(self.value),
+                self.span.frequency().noun()
+            )
+        } else {
+            format!(
+                "{} in {}",
+                self.metric.format_amount(self.value),
+                describe_period(&self.span, self.metric.date_style())
+            )
+        };
+        write!(f, "{}", self.format(&self.metric.print_text, data))
+    }
+}
+
+/// Date of the most recent of points on or before report_date
+fn latest_before(points: &[Datapoint], report_date: &NaiveDate) -> Option<NaiveDate> {
+    points
+        .iter()
+        .map(|point| point.when())
+        .filter(|date| date <= report_date)
+        .max()
+}
+
+/// Whether data last recorded on latest is too old to report on report_date, meaning
+/// nothing was recorded in the metric's current or previous period
+fn is_stale(
+    metric: &Metric,
+    latest: Option<NaiveDate>,
+    report_date: &NaiveDate,
+) -> Result<bool, DateError> {
+    let cutoff = metric.span(report_date)?.prev()?.start();
+    Ok(latest.is_none_or(|latest| latest < cutoff))
+}
+
+/// How many days old the metric's most recent datapoint is
+#[derive(Serialize, Deserialize)]
+pub struct FigFreshness {
+    latest: Option<NaiveDate>,
+    stale: bool,
+    metric: Metric,
+    when: NaiveDate,
+}
+
+impl Figure for FigFreshness {
+    fn metric_info(&self) -> &Metric {
+        &self.metric
+    }
+
+    fn when(&self) -> &NaiveDate {
+        &self.when
+    }
+
+    fn raw_value(&self) -> Option<f64> {
+        self.days().map(|days| days as f64)
+    }
+}
+
+impl FigFreshness {
+    pub fn new(
+        metric: Metric,
+        report_date: NaiveDate,
+        points: &[Datapoint],
+    ) -> Result<FigFreshness, DateError> {
+        let latest = latest_before(points, &report_date);
+        Ok(FigFreshness {
+            latest,
+            stale: is_stale(&metric, latest, &report_date)?,
+            metric,
+            when: report_date,
+        })
+    }
+
+    /// Days between the latest datapoint and the report date, None when there is no data
+    pub fn days(&self) -> Option<i64> {
+        self.latest.map(|latest| (self.when - latest).num_days())
+    }
+
+    /// Whether nothing was recorded in the metric's current or previous period
+    pub fn is_stale(&self) -> bool {
+        self.stale
+    }
+}
+
+impl Display for FigFreshness {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let data = match self.days() {
+            None => String::from("never recorded"),
+            Some(0) => String::from("last recorded today"),
+            Some(1) => String::from("last recorded yesterday"),
+            Some(days) => format!("last recorded {} days ago", days),
+        };
+        write!(f, "{}", self.format(&self.metric.print_text, data))
+    }
+}
+
+/// Another figure followed by a warning when its metric's data is out of date
+#[derive(Serialize, Deserialize)]
+pub struct FigStaleWarning<F> {
+    figure: F,
+    latest: Option<NaiveDate>,
+    stale: bool,
+}
+
+impl<F: Figure> Figure for FigStaleWarning<F> {
+    fn metric_info(&self) -> &Metric {
+        self.figure.metric_info()
+    }
+
+    fn when(&self) -> &NaiveDate {
+        self.figure.when()
+    }
+
+    fn raw_value(&self) -> Option<f64> {
+        self.figure.raw_value()
+    }
+
+    fn compared(&self) -> Option<(f64, f64)> {
+        self.figure.compared()
+    }
+}
+
+impl<F: Figure> FigStaleWarning<F> {
+    /// Checks the freshness of points, the datapoints figure was built from, as of the
+    /// figure's date
+    pub fn new(figure: F, points: &[Datapoint]) -> Result<FigStaleWarning<F>, DateError> {
+        let latest = latest_before(points, figure.when());
+        let stale = is_stale(figure.metric_info(), latest, figure.when())?;
+        Ok(FigStaleWarning {
+            figure,
+            latest,
+            stale,
+        })
+    }
+}
+
+impl<F: Figure + Display> Display for FigStaleWarning<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.stale, self.latest) {
+            (false, _) => write!(f, "{}", self.figure),
+            (true, Some(latest)) => write!(
+                f,
+                "{} (data last recorded {})",
+                self.figure,
+                self.figure.metric_info().date_style().date(&latest)
+            ),
+            (true, None) => write!(f, "{} (no data recorded)", self.figure),
+        }
+    }
+}
+
+/// How far the metric's total so far this year has come towards its target for the year
+#[derive(Serialize, Deserialize)]
+pub struct FigProgress {
+    achieved: f64,
+    goal: f64,
+    remaining: f64,
+    metric: Metric,
+    when: NaiveDate,
+}
+
+impl Figure for FigProgress {
+    fn metric_info(&self) -> &Metric {
+        &self.metric
+    }
+
+    fn when(&self) -> &NaiveDate {
+        &self.when
+    }
+
+    fn raw_value(&self) -> Option<f64> {
+        Some(self.complete())
+    }
+}
+
+impl FigProgress {
+    /// Compares the total from the start of the year up to report_date with the target set
+    /// for the whole year. None when there isn't one, or it is zero
+    pub fn new(
+        metric: Metric,
+        report_date: NaiveDate,
+        points: &[Datapoint],
+        targets: &[Target],
+    ) -> Result<Option<FigProgress>, DateError> {
+        let year = TimeSpan::new(&report_date, TimeFrequency::Yearly)?;
+        let goal = match Target::find(targets, &year) {
+            Some(target) if target.value() != 0.0 => target.value(),
+            _ => return Ok(None),
+        };
+        let so_far = TimeSpan::to_date(&report_date, TimeFrequency::Yearly, metric.week_start())?;
+        Ok(Some(FigProgress {
+            achieved: total_within(points, &so_far),
+            goal,
+            remaining: 1.0 - so_far.days() as f64 / year.days() as f64,
+            metric,
+            when: report_date,
+        }))
+    }
+
+    /// Proportion of the goal achieved so far
+    pub fn complete(&self) -> f64 {
+        self.achieved / self.goal
+    }
+
+    /// Proportion of the year still to come after the report date
+    pub fn remaining(&self) -> f64 {
+        self.remaining
+    }
+}
+
+impl Display for FigProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            self.format(
+                &self.metric.print_text,
+                format!(
+                    "{} of the yearly target with {} of the year remaining",
+                    self.metric.format_percent(100.0 * self.complete(), 0),
+                    self.metric.format_percent(100.0 * self.remaining, 0)
+                )
+            )
+        )
+    }
+}
+
+/// The minimum, quartiles and maximum of a metric's per-period totals over a trailing
+/// window, for metrics where an average alone misleads
+#[derive(Serialize, Deserialize)]
+pub struct FigDistribution {
+    /// Minimum, lower quartile, median, upper quartile and maximum
+    summary: [f64; 5],
+    periods: u32,
+    metric: Metric,
+    when: NaiveDate,
+}
+
+impl Figure for FigDistribution {
+    fn metric_info(&self) -> &Metric {
+        &self.metric
+    }
+
+    fn when(&self) -> &NaiveDate {
+        &self.when
+    }
+
+    fn raw_value(&self) -> Option<f64> {
+        Some(self.summary[2])
+    }
+}
+
+impl FigDistribution {
+    /// Summarises the totals of the last periods periods up to and including when
+    pub fn new(
+        metric: Metric,
+        when: NaiveDate,
+        periods: u32,
+        points: &[Datapoint],
+    ) -> Result<FigDistribution, DateError> {
+        let window = metric.trailing(periods, &when)?;
+        let totals: Vec<f64> = period_totals(points, &window)?
+            .into_iter()
+            .map(|(_, total)| total)
+            .collect();
+        let mut summary = [0.0; 5];
+        for (value, p) in summary.iter_mut().zip([0.0, 25.0, 50.0, 75.0, 100.0]) {
+            *value = stats::percentile(&totals, p).ok_or(DateError::EmptySpan)?;
+        }
+        Ok(FigDistribution {
+            summary,
+            periods,
+            metric,
+            when,
+        })
+    }
+
+    /// Minimum, lower quartile, median, upper quartile and maximum, in that order
+    pub fn summary(&self) -> [f64; 5] {
+        self.summary
+    }
+}
+
+impl Display for FigDistribution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [min, lower, median, upper, max] =
+            self.summary.map(|value| self.metric.format_amount(value));
+        write!(
+            f,
+            "{}",
+            self.format(
+                &self.metric.print_text,
+                format!(
+                    "{} to {} over {}, with a median of {} and half between {} and {}",
+                    min,
+                    max,
+                    inflection::last(self.periods, self.metric.frequency.noun()),
+                    median,
+                    lower,
+                    upper
+                )
+            )
+        )
+    }
+}
+
+/// Another figure with a symbol in front showing its direction
+#[derive(Serialize, Deserialize)]
+pub struct FigIndicator<F> {
+    figure: F,
+    indicators: Indicators,
+}
+
+impl<F: Figure> Figure for FigIndicator<F> {
+    fn metric_info(&self) -> &Metric {
+        self.figure.metric_info()
+    }
+
+    fn when(&self) -> &NaiveDate {
+        self.figure.when()
+    }
+
+    fn raw_value(&self) -> Option<f64> {
+        self.figure.raw_value()
+    }
+
+    fn compared(&self) -> Option<(f64, f64)> {
+        self.figure.compared()
+    }
+
+    fn markup_class(&self) -> &str {
+        self.figure.markup_class()
+    }
+}
+
+impl<F: Figure> FigIndicator<F> {
+    pub fn new(figure: F, indicators: Indicators) -> FigIndicator<F> {
+        FigIndicator { figure, indicators }
+    }
+}
+
+impl<F: Figure + Display> Display for FigIndicator<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbol = self.indicators.symbol(self.figure.direction());
+        write!(f, "{} {}", symbol, self.figure)
+    }
+}
+
+/// Rules for turning a value into a human-friendly approximation
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Approximation {
+    /// Significant figures kept in "about" approximations
+    pub significant: u32,
+    /// How close, as a proportion, a value must be to a round number to be described as
+    /// just under or just over it
+    pub near: f64,
+}
+
+impl Default for Approximation {
+    fn default() -> Approximation {
+        Approximation {
+            significant: 2,
+            near: 0.05,
+        }
+    }
+}
+
+impl Approximation {
+    /// Describes value as e.g. "about 1.2 thousand" or "just under 10", appending suffix
+    /// to the number
+    pub fn describe(&self, value: f64, suffix: &str) -> String {
+        let round = round_significant(value, 1);
+        if value != round && round != 0.0 && ((value - round) / round).abs() <= self.near {
+            let side = if value.abs() < round.abs() {
+                "just under"
+            } else {
+                "just over"
+            };
+            return format!("{} {}{}", side, scaled_words(round), suffix);
+        }
+        let approximate = round_significant(value, self.significant);
+        if approximate == value {
+            format!("{}{}", scaled_words(value), suffix)
+        } else {
+            format!("about {}{}", scaled_words(approximate), suffix)
+        }
+    }
+}
+
+pub(crate) fn round_significant(value: f64, digits: u32) -> f64 {
+    if value == 0.0 {
+        return 0.0;
+    }
+    let magnitude = value.abs().log10().floor() as i32;
+    let factor = 10f64.powi(digits as i32 - 1 - magnitude);
+    (value * factor).round() / factor
+}
+
+/// Writes large values with a scale word, e.g. 1.2 thousand, 3.4 million
+fn scaled_words(value: f64) -> String {
+    let (scaled, word) = match value.abs() {
+        v if v >= 1e9 => (value / 1e9, " billion"),
+        v if v >= 1e6 => (value / 1e6, " million"),
+        v if v >= 1e3 => (value / 1e3, " thousand"),
+        _ => (value, ""),
+    };
+    format!("{}{}", (scaled * 1000.0).round() / 1000.0, word)
+}
+
+/// A value rendered as a rounded, human-friendly approximation
+#[derive(Serialize, Deserialize)]
+pub struct FigApprox {
+    value: f64,
+    percent: bool,
+    rules: Approximation,
+    metric: Metric,
+    when: NaiveDate,
+}
+
+impl Figure for FigApprox {
+    fn metric_info(&self) -> &Metric {
+        &self.metric
+    }
+
+    fn when(&self) -> &NaiveDate {
+        &self.when
+    }
+
+    fn raw_value(&self) -> Option<f64> {
+        Some(self.value)
+    }
+}
+
+impl FigApprox {
+    pub fn new(metric: Metric, when: NaiveDate, value: f64, rules: Approximation) -> FigApprox {
+        FigApprox {
+            value,
+            percent: false,
+            rules,
+            metric,
+            when,
+        }
+    }
+
+    /// Approximates the metric's total over span
+    pub fn for_span(
+        metric: Metric,
+        span: &TimeSpan,
+        points: &[Datapoint],
+        rules: Approximation,
+    ) -> FigApprox {
+        FigApprox::new(metric, span.end(), total_within(points, span), rules)
+    }
+
+    /// Treats the value as a percentage, which is already multiplied by 100
+    pub fn percent(mut self) -> FigApprox {
+        self.percent = true;
+        self
+    }
+}
+
+impl Display for FigApprox {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let suffix = match (self.percent, self.metric.unit()) {
+            (true, _) | (false, Some("%")) => String::from("%"),
+            (false, Some(unit)) => format!(" {}", unit),
+            (false, None) => String::new(),
+        };
+        write!(
+            f,
+            "{}",
+            self.format(
+                &self.metric.print_text,
+                self.rules.describe(self.value, &suffix)
+            )
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::{Currency, FigChange, Precision, RenderContext, SymbolPosition, Vocabulary};
+
+    fn weekly_points(metric: &Metric, values: &[f64]) -> Vec<Datapoint> {
+        let first = NaiveDate::from_ymd_opt(2022, 1, 3).unwrap();
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, value)| {
+                Datapoint::new(
+                    *value,
+                    metric.clone(),
+                    first + chrono::Duration::weeks(i as i64),
+                )
+            })
+            .collect()
+    }
+
+    fn purrs() -> Metric {
+        Metric::new(
+            String::from("cat_purrs"),
+            None,
+            String::from("Purrs were {}"),
+            TimeFrequency::Weekly,
+        )
+    }
+
+    #[test]
+    fn moving_average() {
+        let metric = purrs();
+        let points = weekly_points(&metric, &[100.0, 80.0, 120.0, 110.0, 150.0]);
+        let when = NaiveDate::from_ymd_opt(2022, 2, 2).unwrap();
+
+        let figure = FigMovingAvg::new(metric, when, 3, &points).unwrap();
+        assert_eq!(figure.average(), 380.0 / 3.0);
+        assert_eq!(
+            figure.to_string(),
+            "Purrs were averaging 126.7 over the last 3 weeks"
+        );
+    }
+
+    #[test]
+    fn cumulative_total() {
+        let metric = purrs();
+        let points = weekly_points(&metric, &[4000.0, 3000.0, 5400.0]);
+        let when = NaiveDate::from_ymd_opt(2022, 1, 20).unwrap();
+
+        let figure = FigCumulative::year_to_date(metric.clone(), when, &points).unwrap();
+        assert_eq!(figure.to_string(), "Purrs were 12,400 so far this year");
+
+        let from = NaiveDate::from_ymd_opt(2022, 1, 10).unwrap();
+        let figure = FigCumulative::since(metric, from, when, &points);
+        assert_eq!(figure.total(), 8400.0);
+        assert_eq!(figure.to_string(), "Purrs were 8,400 since 10 Jan 2022");
+
+        assert_eq!(with_separators(-1234567.891, 2), "-1,234,567.89");
+        assert_eq!(with_separators(999.0, 0), "999");
+    }
+
+    #[test]
+    fn extremes_over_window() {
+        let metric = purrs();
+        let points = weekly_points(&metric, &[100.0, 164.0, 120.0, 80.0, 150.0]);
+        let when = NaiveDate::from_ymd_opt(2022, 2, 2).unwrap();
+
+        let peak = FigExtreme::max_over(metric.clone(), when, 5, &points).unwrap();
+        assert_eq!(peak.value(), 164.0);
+        assert_eq!(
+            peak.to_string(),
+            "Purrs were a peak of 164 in the week of 10 Jan"
+        );
+
+        let low = FigExtreme::min_over(metric, when, 2, &points).unwrap();
+        assert_eq!(low.value(), 80.0);
+        assert_eq!(
+            low.period().start(),
+            NaiveDate::from_ymd_opt(2022, 1, 24).unwrap()
+        );
+    }
+
+    #[test]
+    fn median_and_percentiles() {
+        let metric = purrs();
+        let points = weekly_points(&metric, &[156.0, 90.0, 110.0, 120.0, 100.0]);
+        let when = NaiveDate::from_ymd_opt(2022, 2, 2).unwrap();
+
+        let median = FigPercentile::median(metric.clone(), when, 5, &points).unwrap();
+        assert_eq!(median.to_string(), "Purrs were a median of 110");
+
+        let p = FigPercentile::parse("percentile:90").unwrap();
+        let figure = FigPercentile::new(p, metric, when, 5, &points).unwrap();
+        assert_eq!(figure.to_string(), "Purrs were a 90th percentile of 141.6");
+
+        assert_eq!(FigPercentile::parse("median"), Some(50.0));
+        assert_eq!(FigPercentile::parse("percentile:120"), None);
+        assert_eq!(ordinal(22), "22nd");
+        assert_eq!(ordinal(13), "13th");
+    }
+
+    #[test]
+    fn volatility() {
+        let metric = purrs();
+        let points = weekly_points(&metric, &[90.0, 110.0, 100.0]);
+        let when = NaiveDate::from_ymd_opt(2022, 1, 20).unwrap();
+
+        let figure = FigVolatility::new(metric.clone(), when, 3, &points).unwrap();
+        assert_eq!(figure.std_dev(), 10.0);
+        assert_eq!(figure.coefficient_of_variation(), Some(0.1));
+        assert_eq!(
+            figure.to_string(),
+            "Purrs were a standard deviation of 10.0 (10.0% of the mean)"
+        );
+
+        let whole = metric.clone().with_precision(Precision::Decimals(0));
+        assert_eq!(
+            FigVolatility::new(whole, when, 3, &points)
+                .unwrap()
+                .to_string(),
+            "Purrs were a standard deviation of 10 (10% of the mean)"
+        );
+
+        assert!(FigVolatility::new(metric, when, 1, &points).is_err());
+    }
+
+    #[test]
+    fn versus_target() {
+        let metric = purrs();
+        let points = weekly_points(&metric, &[100.0, 103.0]);
+        let span = metric
+            .span(&NaiveDate::from_ymd_opt(2022, 1, 10).unwrap())
+            .unwrap();
+        let targets = [Target::new(100.0, metric.clone(), &span)];
+
+        let figure = FigVsTarget::for_span(metric.clone(), &span, &points, &targets).unwrap();
+        assert_eq!(figure.to_string(), "Purrs were 3.0% ahead of target");
+
+        let figure = FigVsTarget::new(metric.clone(), span.end(), 88.0, 100.0).absolute();
+        assert_eq!(figure.to_string(), "Purrs were 12 below target");
+
+        let figure = FigVsTarget::new(metric.clone(), span.end(), 5.0, 0.0);
+        assert_eq!(figure.to_string(), "Purrs were 5 above target");
+
+        let other = span.prev().unwrap();
+        assert!(FigVsTarget::for_span(metric, &other, &points, &targets).is_none());
+    }
+
+    #[test]
+    fn linear_forecast() {
+        let metric = purrs();
+        let points = weekly_points(&metric, &[100.0, 110.0, 120.0, 130.0]);
+        let when = NaiveDate::from_ymd_opt(2022, 1, 24).unwrap();
+
+        let figure = FigForecast::new(Trend::Linear, metric.clone(), when, 4, &points).unwrap();
+        assert_eq!(figure.projected(), 140.0);
+        assert_eq!(
+            figure.to_string(),
+            "Purrs were on course for around 140 in the week of 31 Jan"
+        );
+
+        let figure = FigForecast::new(Trend::Mean, metric, when, 4, &points).unwrap();
+        assert_eq!(figure.projected(), 115.0);
+        let figure = figure.with_interval(IntervalStyle::Words);
+        assert_eq!(
+            figure.to_string(),
+            "Purrs were on course for around 115 in the week of 31 Jan, likely between 94 and 136"
+        );
+        let figure = figure.with_interval(IntervalStyle::Numbers);
+        assert_eq!(
+            figure.to_string(),
+            "Purrs were on course for around 115 (94–136) in the week of 31 Jan"
+        );
+    }
+
+    #[test]
+    fn compound_annual_growth() {
+        let metric = Metric::new(
+            String::from("revenue"),
+            None,
+            String::from("Revenue grew at {}"),
+            TimeFrequency::Yearly,
+        );
+        let points: Vec<Datapoint> = [(2019, 100.0), (2020, 150.0), (2021, 121.0)]
+            .iter()
+            .map(|(year, value)| {
+                let date = NaiveDate::from_ymd_opt(*year, 6, 30).unwrap();
+                Datapoint::new(*value, metric.clone(), date)
+            })
+            .collect();
+        let from = NaiveDate::from_ymd_opt(2019, 3, 1).unwrap();
+        let when = NaiveDate::from_ymd_opt(2021, 3, 1).unwrap();
+
+        let figure = FigCagr::since(metric, from, when, &points).unwrap();
+        assert!((figure.rate().unwrap() - 0.1).abs() < 1e-3);
+        assert_eq!(
+            figure.to_string(),
+            "Revenue grew at a 10.0% compound annual growth rate since 2019"
+        );
+
+        let revenue = figure.metric_info().clone();
+        let flat = FigCagr::new(revenue.clone(), from, from, 100.0, 121.0);
+        assert_eq!(flat.rate(), None);
+        assert_eq!(
+            flat.to_string(),
+            "Revenue grew at n/a as a compound annual growth rate since 2019"
+        );
+        assert!(FigCagr::new(revenue, from, when, 0.0, 121.0)
+            .to_string()
+            .contains("n/a"));
+    }
+
+    #[test]
+    fn ratio_of_metrics() {
+        let conversions = Metric::new(
+            String::from("conversions"),
+            None,
+            String::from("Conversions were {}"),
+            TimeFrequency::Weekly,
+        );
+        let visits = Metric::new(
+            String::from("visits"),
+            None,
+            String::from("Visits were {}"),
+            TimeFrequency::Weekly,
+        );
+        let points = weekly_points(&conversions, &[12.0, 16.0]);
+        let per_points = weekly_points(&visits, &[400.0, 500.0]);
+        let span = visits
+            .span(&NaiveDate::from_ymd_opt(2022, 1, 10).unwrap())
+            .unwrap();
+
+        let figure = FigRatio::for_span(
+            conversions.clone(),
+            visits.clone(),
+            &span,
+            &points,
+            &per_points,
+        )
+        .unwrap();
+        assert_eq!(figure.quotient(), 0.032);
+        assert_eq!(figure.to_string(), "Conversions were 0.03 per visits");
+        assert_eq!(
+            figure.percent().to_string(),
+            "Conversions were 3.2% of visits"
+        );
+
+        let when = span.end();
+        assert!(FigRatio::new(conversions, visits, when, 1.0, 0.0).is_none());
+    }
+
+    #[test]
+    fn described_changes() {
+        let metric = purrs();
+        let when = NaiveDate::from_ymd_opt(2022, 1, 10).unwrap();
+        let describe =
+            |old, new| FigDescribe::new(metric.clone(), when, old, new, Thresholds::default());
+
+        assert_eq!(
+            describe(100.0, 100.5).to_string(),
+            "Purrs were roughly flat"
+        );
+        assert_eq!(describe(100.0, 110.0).to_string(), "Purrs were up modestly");
+        assert_eq!(describe(100.0, 80.0).to_string(), "Purrs were down sharply");
+        assert_eq!(
+            describe(100.0, 250.0).to_string(),
+            "Purrs were up dramatically"
+        );
+        assert_eq!(describe(0.0, 0.0).to_string(), "Purrs were roughly flat");
+        assert_eq!(describe(0.0, 40.0).to_string(), "Purrs were n/a");
+
+        let thresholds = Thresholds::new("rose", "fell").band(0.02, "held steady", "held steady");
+        assert_eq!(thresholds.phrase(-0.01), "held steady");
+        assert_eq!(thresholds.phrase(-0.3), "fell");
+    }
+
+    #[test]
+    fn rank_within_group() {
+        let channel = |name: &str| {
+            Metric::new(
+                String::from(name),
+                None,
+                String::from("It was {}"),
+                TimeFrequency::Weekly,
+            )
+        };
+        let group = MetricGroup::new(
+            String::from("all channels"),
+            vec![
+                String::from("email"),
+                String::from("search"),
+                String::from("social"),
+            ],
+        );
+        let mut points = HashMap::new();
+        points.insert(
+            String::from("email"),
+            weekly_points(&channel("email"), &[100.0, 130.0]),
+        );
+        points.insert(
+            String::from("search"),
+            weekly_points(&channel("search"), &[100.0, 110.0]),
+        );
+        points.insert(
+            String::from("social"),
+            weekly_points(&channel("social"), &[100.0, 90.0]),
+        );
+        let span = channel("search")
+            .span(&NaiveDate::from_ymd_opt(2022, 1, 10).unwrap())
+            .unwrap();
+
+        let figure = FigRank::for_span(channel("search"), &span, &group, &points)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            figure.to_string(),
+            "It was the 2nd largest increase across all channels"
+        );
+
+        let figure = FigRank::for_span(channel("social"), &span, &group, &points)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            figure.to_string(),
+            "It was the largest decrease across all channels"
+        );
+    }
+
+    #[test]
+    fn growth_streak() {
+        let metric = Metric::new(
+            String::from("cat_purrs"),
+            None,
+            String::from("This was {}"),
+            TimeFrequency::Weekly,
+        );
+        let points = weekly_points(&metric, &[120.0, 90.0, 100.0, 110.0, 130.0, 131.0, 140.0]);
+        let when = NaiveDate::from_ymd_opt(2022, 2, 16).unwrap();
+
+        let figure = FigStreak::new(metric.clone(), when, &points).unwrap();
+        assert_eq!(figure.length(), 5);
+        assert_eq!(
+            figure.to_string(),
+            "This was the fifth straight week of growth"
+        );
+
+        let when = NaiveDate::from_ymd_opt(2022, 1, 10).unwrap();
+        let figure = FigStreak::new(metric, when, &points).unwrap();
+        assert_eq!(figure.to_string(), "This was the first week of decline");
+    }
+
+    #[test]
+    fn sparklines() {
+        let metric = purrs();
+        let points = weekly_points(&metric, &[50.0, 10.0, 20.0, 50.0, 80.0, 45.0]);
+        let when = NaiveDate::from_ymd_opt(2022, 2, 1).unwrap();
+
+        let figure = FigSpark::new(metric, when, 4, &points);
+        assert_eq!(figure.values(), &[10.0, 20.0, 50.0, 80.0]);
+        assert_eq!(figure.to_string(), "▁▂▅█");
+        assert_eq!(sparkline(&[3.0, 3.0]), "▄▄");
+    }
+
+    #[test]
+    fn share_of_total() {
+        let mobile = Metric::new(
+            String::from("mobile_visits"),
+            None,
+            String::from("Mobile made up {}"),
+            TimeFrequency::Weekly,
+        );
+        let visits = Metric::new(
+            String::from("visits"),
+            None,
+            String::from("Visits were {}"),
+            TimeFrequency::Weekly,
+        );
+        let points = weekly_points(&mobile, &[310.0]);
+        let total_points = weekly_points(&visits, &[500.0]);
+        let span = visits
+            .span(&NaiveDate::from_ymd_opt(2022, 1, 3).unwrap())
+            .unwrap();
+
+        let figure = FigRatio::share_of(mobile, visits, &span, &points, &total_points).unwrap();
+        assert_eq!(figure.to_string(), "Mobile made up 62.0% of visits");
+    }
+
+    #[test]
+    fn indexed_values() {
+        let metric = Metric::new(
+            String::from("cat_purrs"),
+            None,
+            String::from("Purrs stood at {}"),
+            TimeFrequency::Monthly,
+        );
+        let points = weekly_points(&metric, &[40.0, 20.0, 20.0, 20.0, 0.0, 30.0]);
+        let baseline = metric
+            .span(&NaiveDate::from_ymd_opt(2022, 1, 1).unwrap())
+            .unwrap();
+        let span = baseline.next().unwrap();
+
+        let figure = FigIndex::new(metric.clone(), &span, baseline, &points).unwrap();
+        assert_eq!(figure.index(), 30.0);
+        assert_eq!(
+            figure.to_string(),
+            "Purrs stood at 30.0 (index, January 2022 = 100)"
+        );
+        assert!(FigIndex::new(metric, &baseline, span.next().unwrap(), &points).is_none());
+    }
+
+    #[test]
+    fn absolute_difference() {
+        let metric = purrs();
+        let points = weekly_points(&metric, &[1200.0, 1242.0, 1000.5]);
+        let span = metric
+            .span(&NaiveDate::from_ymd_opt(2022, 1, 10).unwrap())
+            .unwrap();
+
+        let figure = FigDiff::for_span(metric.clone(), &span, &points).unwrap();
+        assert_eq!(figure.to_string(), "Purrs were up 42");
+
+        let figure = FigDiff::for_span(metric, &span.next().unwrap(), &points).unwrap();
+        assert_eq!(figure.to_string(), "Purrs were down 241.5");
+    }
+
+    #[test]
+    fn approximations() {
+        let rules = Approximation::default();
+        assert_eq!(rules.describe(1234.0, ""), "about 1.2 thousand");
+        assert_eq!(rules.describe(9.7, "%"), "just under 10%");
+        assert_eq!(rules.describe(1_020_000.0, ""), "just over 1 million");
+        assert_eq!(rules.describe(47.0, ""), "47");
+        assert_eq!(rules.describe(3_456_000_000.0, ""), "about 3.5 billion");
+
+        let precise = Approximation {
+            significant: 3,
+            near: 0.0,
+        };
+        assert_eq!(precise.describe(1234.0, ""), "about 1.23 thousand");
+
+        let metric = purrs();
+        let when = NaiveDate::from_ymd_opt(2022, 1, 10).unwrap();
+        let figure = FigApprox::new(metric, when, 9.7, rules).percent();
+        assert_eq!(figure.to_string(), "Purrs were just under 10%");
+    }
+
+    #[test]
+    fn best_and_worst_periods() {
+        let metric = purrs();
+        let points = weekly_points(&metric, &[10.0, 42.0, 7.0, 42.0, 12.0, 3.0]);
+        let quarter = TimeSpan::new(
+            &NaiveDate::from_ymd_opt(2022, 1, 1).unwrap(),
+            TimeFrequency::Quarterly,
+        )
+        .unwrap();
+
+        let best = FigBestPeriod::best_period(metric.clone(), &quarter, &points).unwrap();
+        assert_eq!(
+            best.period().start(),
+            NaiveDate::from_ymd_opt(2022, 1, 24).unwrap()
+        );
+        assert_eq!(
+            best.to_string(),
+            "Purrs were strongest in the week of 24 Jan (42) of any week in Q1 2022"
+        );
+
+        let worst = FigBestPeriod::worst_period(metric, &quarter, &points).unwrap();
+        assert_eq!(
+            worst.period().start(),
+            NaiveDate::from_ymd_opt(2022, 3, 28).unwrap()
+        );
+    }
+
+    #[test]
+    fn time_since_peak() {
+        let metric = purrs();
+        let mut values = vec![50.0, 20.0];
+        values.extend([10.0; 60]);
+        values.push(45.0);
+        let points = weekly_points(&metric, &values);
+        let when = points.last().unwrap().when();
+
+        let figure = FigSincePeak::new(metric.clone(), when, &points).unwrap();
+        assert_eq!(
+            figure.higher().map(|span| span.start()),
+            NaiveDate::from_ymd_opt(2022, 1, 3)
+        );
+        assert_eq!(
+            figure.to_string(),
+            "Purrs were 45, the highest weekly figure in 14 months"
+        );
+
+        let record = FigSincePeak::new(metric, when, &points[1..]).unwrap();
+        assert_eq!(
+            record.to_string(),
+            "Purrs were 45, the highest weekly figure on record"
+        );
+        assert_eq!(
+            describe_elapsed(
+                &NaiveDate::from_ymd_opt(2022, 1, 3).unwrap(),
+                &NaiveDate::from_ymd_opt(2022, 1, 24).unwrap()
+            ),
+            "3 weeks"
+        );
+    }
+
+    #[test]
+    fn weighted_average() {
+        let metric = Metric::new(
+            String::from("satisfaction"),
+            Some(String::from("Blended satisfaction score")),
+            String::from("Satisfaction was {}"),
+            TimeFrequency::Weekly,
+        );
+        let weights = MetricWeights::new(
+            String::from("satisfaction"),
+            vec![
+                (String::from("survey"), 3.0),
+                (String::from("reviews"), 1.0),
+            ],
+        );
+        let when = NaiveDate::from_ymd_opt(2022, 1, 10).unwrap();
+
+        let mut values = HashMap::new();
+        values.insert(String::from("survey"), 4.0);
+        assert!(FigWeightedAvg::new(metric.clone(), when, &weights, &values).is_none());
+
+        values.insert(String::from("reviews"), 4.6);
+        let figure = FigWeightedAvg::new(metric, when, &weights, &values).unwrap();
+        assert!((figure.value() - 4.15).abs() < 1e-9);
+        assert_eq!(
+            figure.to_string(),
+            "Satisfaction was 4.15 (weighted across satisfaction)"
+        );
+    }
+
+    #[test]
+    fn milestones() {
+        let metric = purrs();
+        let points = weekly_points(&metric, &[4_000.0, 4_000.0, 3_000.0, 500.0]);
+        let week = |day| {
+            TimeSpan::new(
+                &NaiveDate::from_ymd_opt(2022, 1, day).unwrap(),
+                TimeFrequency::Weekly,
+            )
+            .unwrap()
+        };
+
+        assert!(FigMilestone::new(metric.clone(), &week(10), 10_000.0, &points).is_none());
+        let figure = FigMilestone::new(metric.clone(), &week(17), 10_000.0, &points).unwrap();
+        assert_eq!(figure.milestone(), 10_000.0);
+        assert_eq!(
+            figure.to_string(),
+            "Purrs were past 10,000 in total for the first time in the week of 17 Jan"
+        );
+        assert!(FigMilestone::new(metric, &week(24), 10_000.0, &points).is_none());
+    }
+
+    #[test]
+    fn seasonal_comparison() {
+        let metric = purrs();
+        let points: Vec<Datapoint> = [(2019, 80.0), (2020, 100.0), (2021, 120.0), (2022, 110.0)]
+            .iter()
+            .map(|(year, value)| {
+                let date = NaiveDate::from_ymd_opt(*year, 12, 1).unwrap();
+                Datapoint::new(*value, metric.clone(), date)
+            })
+            .collect();
+        let span = TimeSpan::new(
+            &NaiveDate::from_ymd_opt(2022, 12, 1).unwrap(),
+            TimeFrequency::Monthly,
+        )
+        .unwrap();
+
+        let figure = FigSeasonal::new(metric.clone(), &span, 2, &points)
+            .unwrap()
+            .unwrap();
+        assert_eq!(figure.seasonal(), 110.0);
+        assert_eq!(
+            figure.to_string(),
+            "Purrs were level with the average for the same time over the last 2 years"
+        );
+
+        let figure = FigSeasonal::new(metric.clone(), &span, 3, &points)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            figure.to_string(),
+            "Purrs were up 10.0% on the average for the same time over the last 3 years"
+        );
+        assert!(FigSeasonal::new(metric, &span, 5, &points[3..])
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn multi_frequency_summary() {
+        let metric = purrs();
+        let mut values = vec![10.0; 12];
+        values.extend([8.0, 10.0]);
+        let points = weekly_points(&metric, &values);
+        let when = points.last().unwrap().when();
+
+        let figure = FigSummary::new(
+            metric.clone(),
+            when,
+            &[TimeFrequency::Weekly, TimeFrequency::Quarterly],
+            &points,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            figure.to_string(),
+            "Purrs were up 25.0% on last week and down 92.2% on last quarter"
+        );
+
+        let figure = FigSummary::new(metric.clone(), when, &[TimeFrequency::Yearly], &points);
+        assert!(figure.unwrap().is_none());
+        assert_eq!(
+            join_list(&[String::from("a"), String::from("b"), String::from("c")]),
+            "a, b and c"
+        );
+    }
+
+    #[test]
+    fn budget_variance() {
+        let metric = purrs();
+        let year = TimeSpan::new(
+            &NaiveDate::from_ymd_opt(2022, 1, 1).unwrap(),
+            TimeFrequency::Yearly,
+        )
+        .unwrap();
+        let week = TimeSpan::new(
+            &NaiveDate::from_ymd_opt(2022, 1, 10).unwrap(),
+            TimeFrequency::Weekly,
+        )
+        .unwrap();
+        let points = weekly_points(&metric, &[100.0, 80.0]);
+
+        let budgets = vec![Budget::new(3650.0, metric.clone(), &year)];
+        assert_eq!(Budget::for_span(&budgets, &week), Some(70.0));
+        let figure = FigVsBudget::for_span(metric.clone(), &week, &points, &budgets).unwrap();
+        assert_eq!(figure.variance(), 10.0);
+        assert_eq!(figure.to_string(), "Purrs were 10 (14.3%) over budget");
+
+        let budgets = vec![
+            Budget::new(3650.0, metric.clone(), &year),
+            Budget::new(100.0, metric.clone(), &week),
+        ];
+        let figure = FigVsBudget::for_span(metric, &week, &points, &budgets).unwrap();
+        assert_eq!(figure.to_string(), "Purrs were 20 (20.0%) under budget");
+    }
+
+    #[test]
+    fn run_rate() {
+        let metric = Metric::new(
+            String::from("visits"),
+            None,
+            String::from("Visits are {}"),
+            TimeFrequency::Daily,
+        );
+        let points: Vec<Datapoint> = (1..=10)
+            .map(|day| {
+                let date = NaiveDate::from_ymd_opt(2022, 1, day).unwrap();
+                Datapoint::new(20.0, metric.clone(), date)
+            })
+            .collect();
+        let report_date = NaiveDate::from_ymd_opt(2022, 1, 10).unwrap();
+        let month = TimeSpan::new(&report_date, TimeFrequency::Monthly).unwrap();
+
+        let projected = FigRunRate::new(
+            metric.clone(),
+            &month,
+            report_date,
+            PartialPolicy::Extrapolate,
+            &points,
+        )
+        .unwrap();
+        assert!(projected.is_projected());
+        assert_eq!(projected.value(), 620.0);
+        assert_eq!(
+            projected.to_string(),
+            "Visits are on track for roughly 620 this month"
+        );
+        assert_eq!(projected.likely_range(), (620.0, 620.0));
+
+        let so_far = FigRunRate::new(
+            metric.clone(),
+            &month,
+            report_date,
+            PartialPolicy::Include,
+            &points,
+        )
+        .unwrap();
+        assert_eq!(so_far.to_string(), "Visits are 200 so far this month");
+
+        let excluded = FigRunRate::new(
+            metric.clone(),
+            &month,
+            report_date,
+            PartialPolicy::Exclude,
+            &points,
+        )
+        .unwrap();
+        assert_eq!(excluded.to_string(), "Visits are 0 in December 2021");
+
+        let mut varied = points;
+        varied[9] = Datapoint::new(30.0, metric.clone(), report_date);
+        let varied = FigRunRate::new(
+            metric,
+            &month,
+            report_date,
+            PartialPolicy::Extrapolate,
+            &varied,
+        )
+        .unwrap()
+        .with_interval(IntervalStyle::Words);
+        assert_eq!(
+            varied.to_string(),
+            "Visits are on track for roughly 650 this month, likely between 630 and 670"
+        );
+    }
+
+    #[test]
+    fn date_styles_in_prose() {
+        let week = TimeSpan::new(
+            &NaiveDate::from_ymd_opt(2022, 2, 4).unwrap(),
+            TimeFrequency::Weekly,
+        )
+        .unwrap();
+        assert_eq!(
+            describe_period(&week, DateStyle::default()),
+            "the week of 31 Jan"
+        );
+        assert_eq!(
+            describe_period(&week, DateStyle::Ordinal),
+            "the week of Jan 31st"
+        );
+        let day = TimeSpan::new(&week.end(), TimeFrequency::Daily).unwrap();
+        assert_eq!(describe_period(&day, DateStyle::Long), "6 February 2022");
+        let month = TimeSpan::new(&week.end(), TimeFrequency::Monthly).unwrap();
+        assert_eq!(describe_period(&month, DateStyle::Iso), "2022-02");
+    }
+
+    #[test]
+    fn units_follow_amounts() {
+        let when = NaiveDate::from_ymd_opt(2022, 1, 10).unwrap();
+        let visits = purrs().with_unit("visits");
+        assert_eq!(
+            FigDiff::new(visits.clone(), when, 100.0, 142.0).to_string(),
+            "Purrs were up 42 visits"
+        );
+        assert_eq!(
+            FigDiff::new(visits.clone(), when, 100.0, 99.0).to_string(),
+            "Purrs were down 1 visit"
+        );
+        assert_eq!(
+            FigApprox::new(visits, when, 1234.0, Approximation::default()).to_string(),
+            "Purrs were about 1.2 thousand visits"
+        );
+
+        let rate = purrs().with_unit("%");
+        assert_eq!(
+            FigDiff::new(rate, when, 40.0, 42.5).to_string(),
+            "Purrs were up 2.5%"
+        );
+    }
+
+    #[test]
+    fn normalised_by_metric() {
+        let metric = purrs().with_unit("purrs");
+        let cats = Metric::new(
+            String::from("cat_count"),
+            None,
+            String::from("There were {} cats"),
+            TimeFrequency::Weekly,
+        );
+        let week = TimeSpan::new(
+            &NaiveDate::from_ymd_opt(2022, 1, 3).unwrap(),
+            TimeFrequency::Weekly,
+        )
+        .unwrap();
+        let points = weekly_points(&metric, &[42.0]);
+        let cat_points = weekly_points(&cats, &[4.0]);
+
+        let figure =
+            FigRatio::per(metric.clone(), cats.clone(), &week, &points, &cat_points).unwrap();
+        assert_eq!(figure.to_string(), "Purrs were 10.50 purrs per cat_count");
+
+        let figure = FigRatio::per(metric, cats.with_unit("cats"), &week, &points, &cat_points);
+        assert_eq!(
+            figure.unwrap().to_string(),
+            "Purrs were 10.50 purrs per cat"
+        );
+    }
+
+    #[test]
+    fn data_freshness() {
+        let metric = purrs();
+        let points = weekly_points(&metric, &[10.0, 12.0]);
+
+        let report_date = NaiveDate::from_ymd_opt(2022, 1, 19).unwrap();
+        let fresh = FigFreshness::new(metric.clone(), report_date, &points).unwrap();
+        assert_eq!(fresh.days(), Some(9));
+        assert!(!fresh.is_stale());
+        assert_eq!(fresh.to_string(), "Purrs were last recorded 9 days ago");
+
+        let report_date = NaiveDate::from_ymd_opt(2022, 1, 24).unwrap();
+        assert!(FigFreshness::new(metric.clone(), report_date, &points)
+            .unwrap()
+            .is_stale());
+
+        let diff = FigDiff::new(metric, report_date, 10.0, 12.0);
+        let warned = FigStaleWarning::new(diff, &points).unwrap();
+        assert_eq!(
+            warned.to_string(),
+            "Purrs were up 2 (data last recorded 10 Jan 2022)"
+        );
+        assert_eq!(warned.render_in(RenderContext::Raw), "2");
+    }
+
+    #[test]
+    fn goal_progress() {
+        let metric = purrs();
+        let points = weekly_points(&metric, &[100.0, 240.0, 340.0]);
+        let report_date = NaiveDate::from_ymd_opt(2022, 8, 7).unwrap();
+        let year = TimeSpan::new(&report_date, TimeFrequency::Yearly).unwrap();
+
+        assert!(FigProgress::new(metric.clone(), report_date, &points, &[])
+            .unwrap()
+            .is_none());
+
+        let targets = vec![Target::new(1000.0, metric.clone(), &year)];
+        let figure = FigProgress::new(metric, report_date, &points, &targets)
+            .unwrap()
+            .unwrap();
+        assert_eq!(figure.complete(), 0.68);
+        assert_eq!(
+            figure.to_string(),
+            "Purrs were 68% of the yearly target with 40% of the year remaining"
+        );
+    }
+
+    #[test]
+    fn distribution_summary() {
+        let metric = purrs();
+        let points = weekly_points(&metric, &[90.0, 156.0, 100.0, 110.0, 120.0]);
+        let when = points.last().unwrap().when();
+
+        let figure = FigDistribution::new(metric.clone(), when, 5, &points).unwrap();
+        assert_eq!(figure.summary(), [90.0, 100.0, 110.0, 120.0, 156.0]);
+        assert_eq!(
+            figure.to_string(),
+            "Purrs were 90 to 156 over the last 5 weeks, with a median of 110 and half between 100 and 120"
+        );
+
+        let timed = metric.with_unit("ms");
+        let figure = FigDistribution::new(timed, when, 5, &points).unwrap();
+        assert_eq!(
+            figure.to_string(),
+            "Purrs were 90 ms to 156 ms over the last 5 weeks, with a median of 110 ms and half between 100 ms and 120 ms"
+        );
+    }
+
+    #[test]
+    fn currency_amounts() {
+        let euros = Currency::new("EUR", "€", SymbolPosition::After).with_separators('.', ',');
+        let metric = Metric::new(
+            String::from("revenue"),
+            None,
+            String::from("Revenue was {}"),
+            TimeFrequency::Weekly,
+        )
+        .with_currency(euros);
+        let when = NaiveDate::from_ymd_opt(2022, 1, 10).unwrap();
+
+        let figure = FigDiff::new(metric, when, 10_000.0, 11_203.5);
+        assert_eq!(figure.to_string(), "Revenue was up 1.203,50 €");
+    }
+
+    #[test]
+    fn custom_vocabulary() {
+        let metric = Metric::new(
+            String::from("cat_purrs"),
+            None,
+            String::from("Purrs {} this week"),
+            TimeFrequency::Weekly,
+        )
+        .with_vocabulary(Vocabulary::new("rose", "fell"));
+        let when = NaiveDate::from_ymd_opt(2022, 1, 10).unwrap();
+        assert_eq!(
+            FigDiff::new(metric.clone(), when, 10.0, 8.0).to_string(),
+            "Purrs fell 2 this week"
+        );
+        assert_eq!(
+            FigChange::new(metric, when, 10.0, 12.0).to_string(),
+            "Purrs rose 20.0% this week"
+        );
+    }
+
+    #[test]
+    fn direction_indicators() {
+        let metric = purrs();
+        let when = NaiveDate::from_ymd_opt(2022, 1, 10).unwrap();
+        let up = FigIndicator::new(
+            FigDiff::new(metric.clone(), when, 10.0, 12.0),
+            Indicators::Shapes,
+        );
+        assert_eq!(up.to_string(), "▲ Purrs were up 2");
+
+        let indicators = Indicators::parse("indicators:charts").unwrap();
+        let down = FigIndicator::new(FigDiff::new(metric.clone(), when, 12.0, 10.0), indicators);
+        assert_eq!(down.to_string(), "📉 Purrs were down 2");
+
+        let flat = FigIndicator::new(
+            FigApprox::new(metric, when, 5.0, Approximation::default()),
+            indicators,
+        );
+        assert_eq!(flat.to_string(), "➖ Purrs were 5");
+    }
+}
@@ -0,0 +1,248 @@
+use std::{collections::HashMap, error, fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+/// Arithmetic over other metrics' values, such as `revenue / visits`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum Formula {
+    Number(f64),
+    Metric(String),
+    Negate(Box<Formula>),
+    Binary(Box<Formula>, Operator, Box<Formula>),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+}
+
+/// A formula which couldn't be parsed, with the reason why
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormulaError(String);
+
+impl fmt::Display for FormulaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid formula: {}", self.0)
+    }
+}
+
+impl error::Error for FormulaError {}
+
+impl Formula {
+    /// Names of the metrics the formula reads, in order of first use
+    pub fn metrics(&self) -> Vec<&str> {
+        let mut names = Vec::new();
+        self.collect_metrics(&mut names);
+        names
+    }
+
+    fn collect_metrics<'a>(&'a self, names: &mut Vec<&'a str>) {
+        match self {
+            Formula::Number(_) => (),
+            Formula::Metric(name) => {
+                if !names.contains(&name.as_str()) {
+                    names.push(name)
+                }
+            }
+            Formula::Negate(inner) => inner.collect_metrics(names),
+            Formula::Binary(left, _, right) => {
+                left.collect_metrics(names);
+                right.collect_metrics(names);
+            }
+        }
+    }
+
+    /// Evaluates the formula given each metric's value, or None when a metric is missing
+    /// or the formula divides by zero
+    pub fn evaluate(&self, values: &HashMap<String, f64>) -> Option<f64> {
+        match self {
+            Formula::Number(value) => Some(*value),
+            Formula::Metric(name) => values.get(name).copied(),
+            Formula::Negate(inner) => inner.evaluate(values).map(|value| -value),
+            Formula::Binary(left, operator, right) => {
+                let (left, right) = (left.evaluate(values)?, right.evaluate(values)?);
+                match operator {
+                    Operator::Add => Some(left + right),
+                    Operator::Subtract => Some(left - right),
+                    Operator::Multiply => Some(left * right),
+                    Operator::Divide => (right != 0.0).then(|| left / right),
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for Formula {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Formula::Number(value) => write!(f, "{}", value),
+            Formula::Metric(name) => write!(f, "{}", name),
+            Formula::Negate(inner) => write!(f, "-{}", inner),
+            Formula::Binary(left, operator, right) => {
+                let symbol = match operator {
+                    Operator::Add => "+",
+                    Operator::Subtract => "-",
+                    Operator::Multiply => "*",
+                    Operator::Divide => "/",
+                };
+                write!(f, "({} {} {})", left, symbol, right)
+            }
+        }
+    }
+}
+
+impl FromStr for Formula {
+    type Err = FormulaError;
+
+    fn from_str(text: &str) -> Result<Formula, FormulaError> {
+        let mut parser = Parser {
+            tokens: tokenize(text)?,
+            position: 0,
+        };
+        let formula = parser.expression()?;
+        match parser.tokens.get(parser.position) {
+            None => Ok(formula),
+            Some(token) => Err(FormulaError(format!("unexpected `{}`", token))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Name(String),
+    Symbol(char),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Number(value) => write!(f, "{}", value),
+            Token::Name(name) => write!(f, "{}", name),
+            Token::Symbol(symbol) => write!(f, "{}", symbol),
+        }
+    }
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, FormulaError> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_digit() || c == '.' {
+            let mut number = String::new();
+            while let Some(&c) = chars.peek().filter(|c| c.is_ascii_digit() || **c == '.') {
+                number.push(c);
+                chars.next();
+            }
+            let value = number
+                .parse()
+                .map_err(|_| FormulaError(format!("bad number `{}`", number)))?;
+            tokens.push(Token::Number(value));
+        } else if c.is_alphabetic() || c == '_' {
+            let mut name = String::new();
+            while let Some(&c) = chars.peek().filter(|c| c.is_alphanumeric() || **c == '_') {
+                name.push(c);
+                chars.next();
+            }
+            tokens.push(Token::Name(name));
+        } else if "+-*/()".contains(c) {
+            tokens.push(Token::Symbol(c));
+            chars.next();
+        } else {
+            return Err(FormulaError(format!("unexpected `{}`", c)));
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn next_symbol(&mut self, symbols: &str) -> Option<char> {
+        match self.tokens.get(self.position) {
+            Some(Token::Symbol(c)) if symbols.contains(*c) => {
+                self.position += 1;
+                Some(*c)
+            }
+            _ => None,
+        }
+    }
+
+    fn expression(&mut self) -> Result<Formula, FormulaError> {
+        let mut formula = self.term()?;
+        while let Some(symbol) = self.next_symbol("+-") {
+            let operator = match symbol {
+                '+' => Operator::Add,
+                _ => Operator::Subtract,
+            };
+            formula = Formula::Binary(Box::new(formula), operator, Box::new(self.term()?));
+        }
+        Ok(formula)
+    }
+
+    fn term(&mut self) -> Result<Formula, FormulaError> {
+        let mut formula = self.factor()?;
+        while let Some(symbol) = self.next_symbol("*/") {
+            let operator = match symbol {
+                '*' => Operator::Multiply,
+                _ => Operator::Divide,
+            };
+            formula = Formula::Binary(Box::new(formula), operator, Box::new(self.factor()?));
+        }
+        Ok(formula)
+    }
+
+    fn factor(&mut self) -> Result<Formula, FormulaError> {
+        if self.next_symbol("-").is_some() {
+            return Ok(Formula::Negate(Box::new(self.factor()?)));
+        }
+        if self.next_symbol("(").is_some() {
+            let formula = self.expression()?;
+            return match self.next_symbol(")") {
+                Some(_) => Ok(formula),
+                None => Err(FormulaError(String::from("missing `)`"))),
+            };
+        }
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        match token {
+            Some(Token::Number(value)) => Ok(Formula::Number(value)),
+            Some(Token::Name(name)) => Ok(Formula::Metric(name)),
+            Some(token) => Err(FormulaError(format!("unexpected `{}`", token))),
+            None => Err(FormulaError(String::from("unexpected end of formula"))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn parse_and_evaluate() {
+        let formula: Formula = "(revenue - costs) / visits * 100".parse().unwrap();
+        assert_eq!(formula.metrics(), vec!["revenue", "costs", "visits"]);
+        assert_eq!(formula.to_string(), "(((revenue - costs) / visits) * 100)");
+
+        let mut values = HashMap::new();
+        values.insert(String::from("revenue"), 500.0);
+        values.insert(String::from("costs"), 300.0);
+        assert_eq!(formula.evaluate(&values), None);
+        values.insert(String::from("visits"), 400.0);
+        assert_eq!(formula.evaluate(&values), Some(50.0));
+
+        assert_eq!(formula.to_string().parse(), Ok(formula));
+        assert!("revenue /".parse::<Formula>().is_err());
+        assert!("(revenue".parse::<Formula>().is_err());
+        assert!("revenue $ 2".parse::<Formula>().is_err());
+    }
+}
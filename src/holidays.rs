@@ -0,0 +1,96 @@
+use std::collections::BTreeSet;
+
+use chrono::{Datelike, NaiveDate, Weekday};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+
+use crate::{TimeSpan, DATABASE_FILE};
+
+/// Dates which are not business days in addition to weekends
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct HolidayCalendar {
+    holidays: BTreeSet<NaiveDate>,
+}
+
+impl HolidayCalendar {
+    pub fn new(holidays: impl IntoIterator<Item = NaiveDate>) -> HolidayCalendar {
+        HolidayCalendar {
+            holidays: holidays.into_iter().collect(),
+        }
+    }
+
+    pub fn add(&mut self, date: NaiveDate) {
+        self.holidays.insert(date);
+    }
+
+    pub fn is_holiday(&self, date: &NaiveDate) -> bool {
+        self.holidays.contains(date)
+    }
+
+    /// Weekdays which are not configured as holidays
+    pub fn is_business_day(&self, date: &NaiveDate) -> bool {
+        !matches!(date.weekday(), Weekday::Sat | Weekday::Sun) && !self.is_holiday(date)
+    }
+
+    /// Counts the business days within span
+    pub fn business_days(&self, span: &TimeSpan) -> u32 {
+        span.start()
+            .iter_days()
+            .take_while(|date| *date <= span.end())
+            .filter(|date| self.is_business_day(date))
+            .count() as u32
+    }
+
+    /// Reads the holiday list saved in sqlite3
+    pub fn read() -> rusqlite::Result<HolidayCalendar> {
+        let conn = Connection::open(DATABASE_FILE)?;
+
+        let mut stmt = conn.prepare("SELECT naive_date FROM holiday")?;
+
+        let holidays: Result<BTreeSet<_>, _> = stmt.query_map([], |row| row.get(0))?.collect();
+
+        Ok(HolidayCalendar {
+            holidays: holidays?,
+        })
+    }
+
+    /// Inserts any holidays not already saved into sqlite3 database
+    pub fn write(&self) -> rusqlite::Result<()> {
+        let conn = Connection::open(DATABASE_FILE)?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS holiday (naive_date TEXT PRIMARY KEY)",
+            [],
+        )?;
+
+        for date in &self.holidays {
+            conn.execute(
+                "INSERT OR IGNORE INTO holiday (naive_date) VALUES (?1)",
+                params![date],
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::TimeFrequency;
+
+    #[test]
+    fn business_days_exclude_holidays() {
+        let date = NaiveDate::from_ymd_opt(2021, 12, 20).unwrap();
+        let week = TimeSpan::new(&date, TimeFrequency::Weekly).unwrap();
+
+        let mut calendar = HolidayCalendar::default();
+        assert_eq!(calendar.business_days(&week), 5);
+
+        calendar.add(NaiveDate::from_ymd_opt(2021, 12, 24).unwrap());
+        calendar.add(NaiveDate::from_ymd_opt(2021, 12, 25).unwrap());
+        assert_eq!(calendar.business_days(&week), 4);
+        assert_eq!(week.business_days(&calendar), 4);
+    }
+}
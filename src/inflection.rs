@@ -0,0 +1,97 @@
+/// Reads a plural unit in the singular for use after "per" or 1, e.g. visits to visit
+pub(crate) fn singular(unit: &str) -> &str {
+    match unit.strip_suffix('s') {
+        Some(stem) if stem.len() > 1 && !stem.ends_with('s') => stem,
+        _ => unit,
+    }
+}
+
+/// The plural of a singular noun, e.g. day to days, country to countries
+pub(crate) fn plural(noun: &str) -> String {
+    let consonant_y = noun
+        .strip_suffix('y')
+        .filter(|stem| !stem.ends_with(['a', 'e', 'i', 'o', 'u']));
+    match consonant_y {
+        Some(stem) => format!("{}ies", stem),
+        None if noun.ends_with(['s', 'x', 'z']) || noun.ends_with("ch") || noun.ends_with("sh") => {
+            format!("{}es", noun)
+        }
+        None => format!("{}s", noun),
+    }
+}
+
+/// Writes count of a singular noun, e.g. "1 week", "3 weeks"
+pub(crate) fn count(count: i64, noun: &str) -> String {
+    match count {
+        1 => format!("1 {}", noun),
+        _ => format!("{} {}", count, plural(noun)),
+    }
+}
+
+/// The most recent periods of a singular noun, e.g. "the last week", "the last 3 weeks"
+pub(crate) fn last(periods: u32, noun: &str) -> String {
+    match periods {
+        1 => format!("the last {}", noun),
+        _ => format!("the last {}", count(i64::from(periods), noun)),
+    }
+}
+
+/// The indefinite article for word, "an" before a vowel sound, e.g. an 80th, a 1st
+pub(crate) fn article(word: &str) -> &'static str {
+    let lower = word.to_lowercase();
+    let digits: String = lower.chars().take_while(|c| c.is_ascii_digit()).collect();
+    let vowel_sound = if digits.is_empty() {
+        lower.starts_with(['a', 'e', 'i', 'o'])
+            || lower.starts_with("hour")
+            || lower.starts_with("honest")
+            || (lower.starts_with('u') && !lower.starts_with("uni") && !lower.starts_with("use"))
+    } else {
+        // Numbers said starting "eight", "eleven" or "eighteen", e.g. 8, 11, 18,000
+        digits.starts_with('8')
+            || (digits.len() % 3 == 2 && (digits.starts_with("11") || digits.starts_with("18")))
+    };
+    if vowel_sound {
+        "an"
+    } else {
+        "a"
+    }
+}
+
+/// Puts the indefinite article before phrase, e.g. "an 11th percentile"
+pub(crate) fn with_article(phrase: &str) -> String {
+    format!("{} {}", article(phrase), phrase)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn nouns_agree() {
+        assert_eq!(plural("week"), "weeks");
+        assert_eq!(plural("country"), "countries");
+        assert_eq!(plural("day"), "days");
+        assert_eq!(plural("batch"), "batches");
+        assert_eq!(count(1, "month"), "1 month");
+        assert_eq!(count(14, "month"), "14 months");
+        assert_eq!(last(1, "quarter"), "the last quarter");
+        assert_eq!(last(6, "week"), "the last 6 weeks");
+        assert_eq!(singular("visits"), "visit");
+        assert_eq!(singular("ms"), "ms");
+        assert_eq!(singular("kg"), "kg");
+    }
+
+    #[test]
+    fn articles() {
+        assert_eq!(with_article("80th percentile"), "an 80th percentile");
+        assert_eq!(with_article("11th percentile"), "an 11th percentile");
+        assert_eq!(with_article("1st percentile"), "a 1st percentile");
+        assert_eq!(with_article("100th"), "a 100th");
+        assert_eq!(with_article("18,000 loss"), "an 18,000 loss");
+        assert_eq!(with_article("loss"), "a loss");
+        assert_eq!(with_article("increase"), "an increase");
+        assert_eq!(with_article("hour"), "an hour");
+        assert_eq!(with_article("unit"), "a unit");
+    }
+}
@@ -1,7 +1,11 @@
 use core::fmt;
-use std::{collections::HashMap, fmt::Display};
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+};
 
-use chrono::NaiveDate;
+use chrono::{NaiveDate, Weekday};
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 
@@ -9,14 +13,77 @@ use rusqlite::{params, Connection, Error, ToSql};
 
 const DATABASE_FILE: &str = "ignore/data.db";
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+mod commands;
+mod currency;
+pub mod dates;
+mod figures;
+mod formula;
+mod holidays;
+mod inflection;
+mod phrasing;
+mod render;
+mod span;
+mod spelling;
+pub mod stats;
+mod table;
+mod template;
+pub use commands::{
+    describe_template_error, CommandError, CommandFn, CommandRegistry, CommandTemplate,
+};
+pub use currency::{Currency, SymbolPosition};
+pub use dates::DateError;
+pub use figures::{
+    Approximation, Extreme, FigApprox, FigBestPeriod, FigCagr, FigCumulative, FigDescribe, FigDiff,
+    FigDistribution, FigExtreme, FigForecast, FigFreshness, FigIndex, FigIndicator, FigMilestone,
+    FigMovingAvg, FigPercentile, FigProgress, FigRank, FigRatio, FigRunRate, FigSeasonal,
+    FigSincePeak, FigSpark, FigStaleWarning, FigStreak, FigSummary, FigVolatility, FigVsBudget,
+    FigVsTarget, FigWeightedAvg, IntervalStyle, Thresholds, Trend,
+};
+pub use formula::{Formula, FormulaError, Operator};
+pub use holidays::HolidayCalendar;
+pub use phrasing::PhraseChoice;
+pub use render::{
+    DateStyle, Indicators, Precision, RenderContext, Rounding, Scientific, Steady, Vocabulary,
+};
+pub use span::{Comparison, PartialPolicy, SpanFormat, TimeSpan};
+pub use spelling::{English, NumberWords};
+pub use table::{
+    Axis, Cell, CellFn, SortOrder, Summary, Table, TableFormat, TableSpec, Tables, TotalPolicy,
+};
+pub use template::{install_report_helpers, register_report, register_report_file, strip_comments};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TimeFrequency {
     Yearly,
+    Quarterly,
     Monthly,
     Weekly,
     Daily,
 }
 
+impl TimeFrequency {
+    /// Conventional length of one period in days, ignoring calendar variation
+    pub fn nominal_days(&self) -> f64 {
+        match self {
+            TimeFrequency::Yearly => 365.0,
+            TimeFrequency::Quarterly => 91.0,
+            TimeFrequency::Monthly => 30.0,
+            TimeFrequency::Weekly => 7.0,
+            TimeFrequency::Daily => 1.0,
+        }
+    }
+
+    pub(crate) fn noun(&self) -> &'static str {
+        match self {
+            TimeFrequency::Yearly => "year",
+            TimeFrequency::Quarterly => "quarter",
+            TimeFrequency::Monthly => "month",
+            TimeFrequency::Weekly => "week",
+            TimeFrequency::Daily => "day",
+        }
+    }
+}
+
 impl ToSql for TimeFrequency {
     fn to_sql(&self) -> rusqlite::Result<rusqlite::types::ToSqlOutput<'_>> {
         Ok(format!("{:#?}", self).into())
@@ -26,7 +93,7 @@ impl ToSql for TimeFrequency {
 pub trait Figure {
     /// Inserts data into description by replacing the characters {} in the description
     /// Panics if {} not present in description
-    fn format(&self, description: &String, data: String) -> String {
+    fn format(&self, description: &str, data: String) -> String {
         let insert_position = description
             .find("{}")
             .expect("Couldn't find place to insert data");
@@ -38,16 +105,237 @@ pub trait Figure {
         )
     }
 
+    /// Inserts data into the print text of the figure's metric
+    fn render(&self, data: String) -> String {
+        self.format(self.metric_info().print_text(), data)
+    }
+
+    /// The figure's main value, unformatted and in the metric's units, with proportions
+    /// such as changes as fractions rather than percentages
+    fn raw_value(&self) -> Option<f64> {
+        None
+    }
+
+    /// The earlier and current values a comparison figure, such as a change, was
+    /// calculated from
+    fn compared(&self) -> Option<(f64, f64)> {
+        None
+    }
+
+    /// Whether a comparison figure's current value is above, below or equal to the earlier
+    /// one, None for other figures
+    fn direction(&self) -> Option<Ordering> {
+        self.compared()
+            .map(|(previous, current)| current.total_cmp(&previous))
+    }
+
+    /// The figure as a JSON object of its metric, date, text and values. Comparison figures
+    /// also give the previous value and the direction of the change
+    fn to_json(&self) -> serde_json::Value
+    where
+        Self: Display,
+    {
+        let mut json = serde_json::json!({
+            "metric": self.metric_info().name(),
+            "when": self.when().to_string(),
+            "text": self.to_string(),
+            "value": self.raw_value(),
+        });
+        if let (Some((previous, current)), Some(direction)) = (self.compared(), self.direction()) {
+            let direction = match direction {
+                Ordering::Greater => "up",
+                Ordering::Less => "down",
+                Ordering::Equal => "unchanged",
+            };
+            json["value"] = current.into();
+            json["previous"] = previous.into();
+            json["change"] = self.raw_value().into();
+            json["direction"] = direction.into();
+        }
+        json
+    }
+
+    /// Named parts of the figure which a template can place with {name}: the metric, the
+    /// date, the value and, for comparison figures, the previous and current values
+    fn fields(&self) -> Vec<(&'static str, String)> {
+        let metric = self.metric_info();
+        let mut fields = vec![
+            ("metric", metric.name().to_string()),
+            ("when", self.when().to_string()),
+        ];
+        if let Some(value) = self.raw_value() {
+            fields.push(("value", metric.format_amount(value)));
+        }
+        if let Some((previous, current)) = self.compared() {
+            fields.push(("previous", metric.format_amount(previous)));
+            fields.push(("current", metric.format_amount(current)));
+        }
+        fields
+    }
+
+    /// Writes the figure with template, e.g. "{direction} {pct} compared with {prev_span}",
+    /// in place of its usual data. Placeholders which aren't fields are left as written,
+    /// and `{{` and `}}` write literal braces
+    fn templated(&self, template: &str) -> String {
+        self.render(fill_fields(template, &self.fields()))
+    }
+
+    /// Class the figure's data is marked up with in contexts which style figures
+    fn markup_class(&self) -> &str {
+        "figure"
+    }
+
+    /// The figure's data in words followed by its exact figure in brackets, e.g. "up by a
+    /// quarter (25.0%)", None when it can't be put into words
+    fn combined(&self) -> Option<String> {
+        None
+    }
+
+    /// Writes the figure out for context, marking up the data its Display inserts into
+    /// the metric's print text. Raw writes just the raw value when the figure has one.
+    /// Combined writes the combined data when there is one
+    fn render_in(&self, context: RenderContext) -> String
+    where
+        Self: Display,
+    {
+        if let (RenderContext::Raw, Some(value)) = (context, self.raw_value()) {
+            return value.to_string();
+        }
+        let text = match context {
+            RenderContext::Combined => self.combined().map(|data| self.render(data)),
+            _ => None,
+        };
+        context.figure(
+            self.metric_info().print_text(),
+            &text.unwrap_or_else(|| self.to_string()),
+            self.markup_class(),
+        )
+    }
+
     fn metric_info(&self) -> &Metric;
     fn when(&self) -> &NaiveDate;
 }
 
-#[derive(Clone, Serialize, Deserialize)]
+/// Which way a metric should move, e.g. lower is better for support tickets
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum GoodDirection {
+    HigherIsBetter,
+    LowerIsBetter,
+}
+
+impl GoodDirection {
+    /// Parses `higher_is_better` or `lower_is_better`
+    pub fn parse(spec: &str) -> Option<GoodDirection> {
+        match spec {
+            "higher_is_better" => Some(GoodDirection::HigherIsBetter),
+            "lower_is_better" => Some(GoodDirection::LowerIsBetter),
+            _ => None,
+        }
+    }
+
+    /// Whether change moves the metric the good way
+    pub fn is_improvement(&self, change: f64) -> bool {
+        match self {
+            GoodDirection::HigherIsBetter => change > 0.0,
+            GoodDirection::LowerIsBetter => change < 0.0,
+        }
+    }
+}
+
+impl Display for GoodDirection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GoodDirection::HigherIsBetter => write!(f, "higher_is_better"),
+            GoodDirection::LowerIsBetter => write!(f, "lower_is_better"),
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
 pub struct Metric {
     name: String,
     description: Option<String>,
     print_text: String,
     frequency: TimeFrequency,
+    #[serde(default = "monday")]
+    week_start: Weekday,
+    #[serde(default)]
+    formula: Option<Formula>,
+    #[serde(default)]
+    unit: Option<String>,
+    #[serde(default)]
+    currency: Option<Currency>,
+    #[serde(default)]
+    precision: Option<Precision>,
+    #[serde(default)]
+    vocabulary: Option<Vocabulary>,
+    #[serde(default)]
+    direction: Option<GoodDirection>,
+    #[serde(default)]
+    abbreviate: bool,
+    #[serde(default)]
+    sign_words: Option<Vocabulary>,
+    #[serde(default)]
+    steady: Option<Steady>,
+    #[serde(default)]
+    date_style: Option<DateStyle>,
+    #[serde(default)]
+    rounding: Option<Rounding>,
+    #[serde(default)]
+    scientific: Option<Scientific>,
+    #[serde(default)]
+    spell_limit: Option<u32>,
+}
+
+/// Weeks start on Monday unless a metric says otherwise
+fn monday() -> Weekday {
+    Weekday::Mon
+}
+
+/// The metric table's columns after name, description, print_text and frequency, which
+/// were added later and so may be missing from older databases
+const METRIC_COLUMNS: [(&str, &str); 14] = [
+    ("week_start", "TEXT"),
+    ("formula", "TEXT"),
+    ("unit", "TEXT"),
+    ("currency", "TEXT"),
+    ("display_precision", "TEXT"),
+    ("vocabulary", "TEXT"),
+    ("good_direction", "TEXT"),
+    ("abbreviate", "INTEGER"),
+    ("sign_words", "TEXT"),
+    ("steady", "TEXT"),
+    ("date_style", "TEXT"),
+    ("rounding", "TEXT"),
+    ("scientific", "TEXT"),
+    ("spell_limit", "INTEGER"),
+];
+
+/// Creates the metric table, or adds any of METRIC_COLUMNS a table from an older
+/// version of this crate lacks
+fn migrate_metric_table(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        r#"CREATE TABLE IF NOT EXISTS metric (
+        name TEXT PRIMARY KEY, 
+        description TEXT, 
+        print_text TEXT, 
+        frequency TEXT)"#,
+        [],
+    )?;
+    let mut stmt = conn.prepare("PRAGMA table_info(metric)")?;
+    let existing: Vec<String> = stmt
+        .query_map([], |row| row.get(1))?
+        .collect::<Result<_, _>>()?;
+    for (column, kind) in METRIC_COLUMNS {
+        if !existing.iter().any(|name| name == column) {
+            conn.execute(
+                &format!("ALTER TABLE metric ADD COLUMN {} {}", column, kind),
+                [],
+            )?;
+        }
+    }
+    Ok(())
 }
 
 impl Metric {
@@ -62,32 +350,383 @@ impl Metric {
             description,
             print_text,
             frequency,
+            week_start: Weekday::Mon,
+            formula: None,
+            unit: None,
+            currency: None,
+            precision: None,
+            vocabulary: None,
+            direction: None,
+            abbreviate: false,
+            sign_words: None,
+            steady: None,
+            date_style: None,
+            rounding: None,
+            scientific: None,
+            spell_limit: None,
+        }
+    }
+
+    /// Sets the day this metric's weekly collection periods begin on, Monday by default
+    pub fn with_week_start(mut self, week_start: Weekday) -> Metric {
+        self.week_start = week_start;
+        self
+    }
+
+    /// Makes this a derived metric, whose datapoints are calculated from other metrics
+    /// when read rather than stored
+    pub fn with_formula(mut self, formula: Formula) -> Metric {
+        self.formula = Some(formula);
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    /// Sentence the metric's figures are inserted into, at the characters {}
+    pub fn print_text(&self) -> &str {
+        &self.print_text
+    }
+
+    pub fn frequency(&self) -> TimeFrequency {
+        self.frequency
+    }
+
+    pub fn week_start(&self) -> Weekday {
+        self.week_start
+    }
+
+    /// Sets the unit values of this metric are counted in, written as it reads after a
+    /// number, e.g. "visits" or "ms"
+    pub fn with_unit(mut self, unit: &str) -> Metric {
+        self.unit = Some(unit.to_string());
+        self
+    }
+
+    pub fn unit(&self) -> Option<&str> {
+        self.unit.as_deref()
+    }
+
+    /// Makes this a monetary metric, whose amounts are written in currency
+    pub fn with_currency(mut self, currency: Currency) -> Metric {
+        self.currency = Some(currency);
+        self
+    }
+
+    pub fn currency(&self) -> Option<&Currency> {
+        self.currency.as_ref()
+    }
+
+    /// Sets the decimal places or significant figures this metric's figures are written
+    /// with, in place of each figure's default
+    pub fn with_precision(mut self, precision: Precision) -> Metric {
+        self.precision = Some(precision);
+        self
+    }
+
+    pub fn precision(&self) -> Option<Precision> {
+        self.precision
+    }
+
+    /// Sets the words this metric's changes are described with, up and down by default
+    pub fn with_vocabulary(mut self, vocabulary: Vocabulary) -> Metric {
+        self.vocabulary = Some(vocabulary);
+        self
+    }
+
+    /// Declares which way this metric should move, so changes are described and styled
+    /// as improvements or deteriorations
+    pub fn with_direction(mut self, direction: GoodDirection) -> Metric {
+        self.direction = Some(direction);
+        self
+    }
+
+    pub fn direction(&self) -> Option<GoodDirection> {
+        self.direction
+    }
+
+    /// Writes large amounts abbreviated, e.g. 1.2k, 3.4M, 1.1B. Leave off for metrics
+    /// where exact counts matter
+    pub fn with_abbreviation(mut self) -> Metric {
+        self.abbreviate = true;
+        self
+    }
+
+    pub fn abbreviates(&self) -> bool {
+        self.abbreviate
+    }
+
+    /// Sets the names of positive and negative amounts of this metric, e.g. profit/loss,
+    /// used when a change crosses zero
+    pub fn with_sign_words(mut self, sign_words: Vocabulary) -> Metric {
+        self.sign_words = Some(sign_words);
+        self
+    }
+
+    /// Sets how changes too small to matter are described, "unchanged" for changes
+    /// which would be written as 0.0% by default
+    pub fn with_steady(mut self, steady: Steady) -> Metric {
+        self.steady = Some(steady);
+        self
+    }
+
+    pub fn steady(&self) -> Steady {
+        self.steady.clone().unwrap_or_default()
+    }
+
+    /// Whether change, a proportion, is small enough to be described with the steady word:
+    /// within the metric's steady threshold, or else when format_percent would write it
+    /// as zero at the metric's precision and rounding
+    pub fn is_steady(&self, change: f64) -> bool {
+        match &self.steady {
+            Some(steady) => steady.holds(change),
+            None => {
+                let percent = 100.0 * change.abs();
+                let decimals = self
+                    .precision
+                    .map_or(1, |precision| precision.decimals(percent));
+                self.rounding().round(percent, decimals) == 0.0
+            }
+        }
+    }
+
+    /// Sets how dates in this metric's figures are written, e.g. "4 February 2022"
+    pub fn with_date_style(mut self, date_style: DateStyle) -> Metric {
+        self.date_style = Some(date_style);
+        self
+    }
+
+    pub fn date_style(&self) -> DateStyle {
+        self.date_style.unwrap_or_default()
+    }
+
+    /// Sets how this metric's values are rounded when written, half up by default
+    pub fn with_rounding(mut self, rounding: Rounding) -> Metric {
+        self.rounding = Some(rounding);
+        self
+    }
+
+    pub fn rounding(&self) -> Rounding {
+        self.rounding.unwrap_or_default()
+    }
+
+    /// Sets the bounds outside which this metric's values are written in scientific
+    /// notation, under a millionth or from a quadrillion by default
+    pub fn with_scientific(mut self, scientific: Scientific) -> Metric {
+        self.scientific = Some(scientific);
+        self
+    }
+
+    pub fn scientific(&self) -> Scientific {
+        self.scientific.unwrap_or_default()
+    }
+
+    /// Writes whole numbers and percentages up to limit in words, e.g. "twenty-five per
+    /// cent", for editorial styles which require it
+    pub fn with_spelled_numbers(mut self, limit: u32) -> Metric {
+        self.spell_limit = Some(limit);
+        self
+    }
+
+    /// The English words for value when the metric spells out numbers and value is whole
+    /// and within the limit
+    pub fn spelled(&self, value: f64) -> Option<String> {
+        let limit = self.spell_limit?;
+        (value.abs() <= f64::from(limit))
+            .then(|| English.spell(value))
+            .flatten()
+    }
+
+    /// Writes percent, already multiplied by 100, like format_fixed followed by %, or in
+    /// words when the metric spells out numbers
+    pub fn format_percent(&self, percent: f64, decimals: usize) -> String {
+        let decimals = self
+            .precision
+            .map_or(decimals, |precision| precision.decimals(percent));
+        match self.spelled(self.rounding().round(percent, decimals)) {
+            Some(words) => format!("{} {}", words, English.percent()),
+            None => format!("{}%", self.format_fixed(percent, decimals)),
+        }
+    }
+
+    /// Writes value, such as a percentage or an average, to the metric's precision or
+    /// else decimals decimal places, rounded the metric's way
+    pub fn format_fixed(&self, value: f64, decimals: usize) -> String {
+        if self.scientific().applies(value) {
+            return self.scientific().format(value);
+        }
+        match self.precision {
+            Some(precision) => {
+                let decimals = precision.decimals(value);
+                let value = self.rounding().round(precision.round(value), decimals);
+                figures::with_separators(value, decimals)
+            }
+            None => format!("{:.*}", decimals, self.rounding().round(value, decimals)),
+        }
+    }
+
+    /// Writes amount with its sign, e.g. "a loss of 3,000" when the metric has sign words
+    /// or else "-3,000"
+    pub fn signed_amount(&self, amount: f64) -> String {
+        match &self.sign_words {
+            Some(words) => format!(
+                "{} of {}",
+                inflection::with_article(words.word(amount)),
+                self.format_amount(amount.abs())
+            ),
+            None => self.format_amount(amount),
+        }
+    }
+
+    /// The word describing a change of this metric, e.g. "up" when change is positive
+    pub fn direction_word(&self, change: f64) -> &str {
+        match &self.vocabulary {
+            Some(vocabulary) => vocabulary.word(change),
+            None if change > 0.0 => "up",
+            None => "down",
+        }
+    }
+
+    /// Writes number, already formatted, followed by this metric's unit, e.g. 42 visits,
+    /// 1 visit, 120 ms or 5%
+    pub fn in_units(&self, number: &str) -> String {
+        match self.unit.as_deref() {
+            None => number.to_string(),
+            Some("%") if number.ends_with(|c: char| c.is_ascii_digit()) => format!("{}%", number),
+            Some("%") => format!("{} {}", number, English.percent()),
+            Some(unit) if number == "1" || number == "one" => {
+                format!("{} {}", number, inflection::singular(unit))
+            }
+            Some(unit) => format!("{} {}", number, unit),
+        }
+    }
+
+    /// Writes an amount of this metric, in its currency when it has one, to the metric's
+    /// precision or else only showing decimals when the amount isn't whole, followed by its
+    /// unit
+    pub fn format_amount(&self, amount: f64) -> String {
+        self.in_units(&self.format_number(amount))
+    }
+
+    /// Writes amount in this metric's currency and precision, without its unit
+    pub fn format_number(&self, amount: f64) -> String {
+        if let Some(words) = self.spelled(amount) {
+            return words;
+        }
+        if self.currency.is_none() && self.scientific().applies(amount) {
+            return self.scientific().format(amount);
+        }
+        let (amount, suffix) = if self.abbreviate {
+            figures::abbreviated(amount)
+        } else {
+            (amount, "")
+        };
+        let decimals = match (self.precision, &self.currency) {
+            (Some(precision), _) => precision.decimals(amount),
+            _ if amount.fract() == 0.0 => 0,
+            (None, Some(_)) if suffix.is_empty() => 2,
+            (None, _) => 1,
+        };
+        let amount = self
+            .precision
+            .map_or(amount, |precision| precision.round(amount));
+        let amount = self.rounding().round(amount, decimals);
+        match &self.currency {
+            Some(currency) => currency.format_with_suffix(amount, decimals, suffix),
+            None => format!("{}{}", figures::with_separators(amount, decimals), suffix),
         }
     }
 
+    pub fn formula(&self) -> Option<&Formula> {
+        self.formula.as_ref()
+    }
+
+    /// The period of this metric's frequency which contains date
+    pub fn span(&self, date: &NaiveDate) -> Result<TimeSpan, DateError> {
+        TimeSpan::anchored(date, self.frequency, self.week_start)
+    }
+
+    /// The last periods periods of this metric's frequency, ending with the one containing date
+    pub fn trailing(&self, periods: u32, date: &NaiveDate) -> Result<TimeSpan, DateError> {
+        TimeSpan::trailing(periods, self.frequency, date, self.week_start)
+    }
+
     /// Reads all Metrics saved in sqlite3
     pub fn read() -> rusqlite::Result<HashMap<String, Metric>> {
         let conn = Connection::open(DATABASE_FILE)?;
+        migrate_metric_table(&conn)?;
+        Metric::read_from(&conn)
+    }
 
-        let mut stmt =
-            conn.prepare("SELECT name, description, print_text, frequency FROM metric")?;
+    fn read_from(conn: &Connection) -> rusqlite::Result<HashMap<String, Metric>> {
+        let mut stmt = conn.prepare(
+            "SELECT name, description, print_text, frequency, week_start, formula, unit, currency, display_precision, vocabulary, good_direction, abbreviate, sign_words, steady, date_style, rounding, scientific, spell_limit FROM metric",
+        )?;
 
         let metric_iter = stmt.query_map([], |row| {
             let freq = match row.get::<_, String>(3)?.as_str() {
                 "Yearly" => TimeFrequency::Yearly,
+                "Quarterly" => TimeFrequency::Quarterly,
                 "Monthly" => TimeFrequency::Monthly,
                 "Weekly" => TimeFrequency::Weekly,
                 "Daily" => TimeFrequency::Daily,
                 _ => return Err(Error::InvalidQuery),
             };
-            Ok(Metric::new(row.get(0)?, row.get(1)?, row.get(2)?, freq))
+            let week_start = match row.get::<_, Option<String>>(4)? {
+                Some(day) => day.parse().map_err(|_| Error::InvalidQuery)?,
+                None => Weekday::Mon,
+            };
+            let mut metric = Metric::new(row.get(0)?, row.get(1)?, row.get(2)?, freq)
+                .with_week_start(week_start);
+            if let Some(formula) = row.get::<_, Option<String>>(5)? {
+                metric = metric.with_formula(formula.parse().map_err(|_| Error::InvalidQuery)?);
+            }
+            metric.unit = row.get(6)?;
+            if let Some(currency) = row.get::<_, Option<String>>(7)? {
+                metric.currency =
+                    Some(serde_json::from_str(&currency).map_err(|_| Error::InvalidQuery)?);
+            }
+            if let Some(precision) = row.get::<_, Option<String>>(8)? {
+                metric.precision = Some(Precision::parse(&precision).ok_or(Error::InvalidQuery)?);
+            }
+            if let Some(vocabulary) = row.get::<_, Option<String>>(9)? {
+                metric.vocabulary =
+                    Some(Vocabulary::parse(&vocabulary).ok_or(Error::InvalidQuery)?);
+            }
+            if let Some(direction) = row.get::<_, Option<String>>(10)? {
+                metric.direction =
+                    Some(GoodDirection::parse(&direction).ok_or(Error::InvalidQuery)?);
+            }
+            metric.abbreviate = row.get::<_, Option<bool>>(11)?.unwrap_or(false);
+            if let Some(sign_words) = row.get::<_, Option<String>>(12)? {
+                metric.sign_words =
+                    Some(Vocabulary::parse(&sign_words).ok_or(Error::InvalidQuery)?);
+            }
+            if let Some(steady) = row.get::<_, Option<String>>(13)? {
+                metric.steady = Some(Steady::parse(&steady).ok_or(Error::InvalidQuery)?);
+            }
+            if let Some(date_style) = row.get::<_, Option<String>>(14)? {
+                metric.date_style = Some(DateStyle::parse(&date_style).ok_or(Error::InvalidQuery)?);
+            }
+            if let Some(rounding) = row.get::<_, Option<String>>(15)? {
+                metric.rounding = Some(Rounding::parse(&rounding).ok_or(Error::InvalidQuery)?);
+            }
+            if let Some(scientific) = row.get::<_, Option<String>>(16)? {
+                metric.scientific =
+                    Some(Scientific::parse(&scientific).ok_or(Error::InvalidQuery)?);
+            }
+            metric.spell_limit = row.get(17)?;
+            Ok(metric)
         })?;
 
         let mut found: HashMap<String, Metric> = HashMap::new();
-        for metric in metric_iter {
-            if let Ok(f) = metric {
-                found.insert(f.name.clone(), f);
-            }
+        for f in metric_iter.flatten() {
+            found.insert(f.name.clone(), f);
         }
         Ok(found)
     }
@@ -95,19 +734,36 @@ impl Metric {
     /// Inserts current metric into sqlite3 database
     pub fn write(&self) -> rusqlite::Result<()> {
         let conn = Connection::open(DATABASE_FILE)?;
+        migrate_metric_table(&conn)?;
 
         conn.execute(
-            r#"CREATE TABLE IF NOT EXISTS metric (
-            name TEXT PRIMARY KEY, 
-            description TEXT, 
-            print_text TEXT, 
-            frequency TEXT)"#,
-            [],
-        )?;
-
-        conn.execute(
-            "INSERT INTO metric (name, description, print_text, frequency) VALUES (?1, ?2, ?3, ?4)",
-            params![self.name, self.description, self.print_text, self.frequency],
+            "INSERT INTO metric (name, description, print_text, frequency, week_start, formula, unit, currency, display_precision, vocabulary, good_direction, abbreviate, sign_words, steady, date_style, rounding, scientific, spell_limit) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)",
+            params![
+                self.name,
+                self.description,
+                self.print_text,
+                self.frequency,
+                self.week_start.to_string(),
+                self.formula.as_ref().map(|formula| formula.to_string()),
+                self.unit,
+                self.currency
+                    .as_ref()
+                    .map(|currency| serde_json::to_string(currency).unwrap_or_default()),
+                self.precision.map(|precision| precision.to_string()),
+                self.vocabulary
+                    .as_ref()
+                    .map(|vocabulary| vocabulary.to_string()),
+                self.direction.map(|direction| direction.to_string()),
+                self.abbreviate,
+                self.sign_words
+                    .as_ref()
+                    .map(|sign_words| sign_words.to_string()),
+                self.steady.as_ref().map(|steady| steady.to_string()),
+                self.date_style.map(|date_style| date_style.to_string()),
+                self.rounding.map(|rounding| rounding.to_string()),
+                self.scientific.map(|scientific| scientific.to_string()),
+                self.spell_limit
+            ],
         )?;
 
         Ok(())
@@ -118,6 +774,9 @@ impl Metric {
 pub struct FigChange {
     old: f64,
     new: f64,
+    significance: Option<f64>,
+    #[serde(default)]
+    comparison: Option<Comparison>,
     metric: Metric,
     when: NaiveDate,
 }
@@ -130,6 +789,78 @@ impl Figure for FigChange {
     fn when(&self) -> &NaiveDate {
         &self.when
     }
+
+    fn compared(&self) -> Option<(f64, f64)> {
+        Some((self.old, self.new))
+    }
+
+    fn raw_value(&self) -> Option<f64> {
+        (!self.crosses_zero()).then(|| self.diff())
+    }
+
+    /// Adds direction (e.g. up, or the steady word), pct, span and prev_span to the default
+    /// fields
+    fn fields(&self) -> Vec<(&'static str, String)> {
+        let direction = if self.is_steady() {
+            self.metric.steady().word().to_string()
+        } else {
+            self.metric.direction_word(self.movement()).to_string()
+        };
+        let pct = self.metric.format_percent(100.0 * self.diff().abs(), 1);
+        let mut fields = vec![
+            ("metric", self.metric.name().to_string()),
+            ("when", self.when.to_string()),
+            ("direction", direction),
+            ("pct", pct),
+            ("previous", self.metric.format_amount(self.old)),
+            ("current", self.metric.format_amount(self.new)),
+        ];
+        if let Ok(span) = self.metric.span(&self.when) {
+            fields.push((
+                "span",
+                figures::describe_period(&span, self.metric.date_style()),
+            ));
+            if let Ok(prev) = span.compare_to(self.comparison.unwrap_or(Comparison::Previous)) {
+                fields.push((
+                    "prev_span",
+                    figures::describe_period(&prev, self.metric.date_style()),
+                ));
+            }
+        }
+        fields
+    }
+
+    fn combined(&self) -> Option<String> {
+        if self.is_steady() || self.crosses_zero() {
+            return None;
+        }
+        let diff = self.diff();
+        Some(format!(
+            "{} by {} ({})",
+            self.metric.direction_word(diff),
+            figures::fraction_words(diff.abs())?,
+            self.metric.format_percent(100.0 * diff.abs(), 1)
+        ))
+    }
+
+    fn markup_class(&self) -> &str {
+        if self.is_steady() {
+            return "delta steady";
+        }
+        let diff = self.movement();
+        let improvement = self
+            .metric
+            .direction
+            .map(|direction| direction.is_improvement(diff));
+        match (diff > 0.0, improvement) {
+            (true, None) => "delta up",
+            (true, Some(true)) => "delta up good",
+            (true, Some(false)) => "delta up bad",
+            (false, None) => "delta down",
+            (false, Some(true)) => "delta down good",
+            (false, Some(false)) => "delta down bad",
+        }
+    }
 }
 
 impl FigChange {
@@ -137,26 +868,95 @@ impl FigChange {
         FigChange {
             old,
             new,
+            significance: None,
+            comparison: None,
             metric,
             when,
         }
     }
 
+    /// Says which earlier span the change is from, e.g. "up 25.0% year on year". The
+    /// previous span is left unsaid
+    pub fn with_comparison(mut self, comparison: Comparison) -> FigChange {
+        self.comparison = Some(comparison);
+        self
+    }
+
+    /// Tests the change against history, the metric's earlier period-on-period proportional
+    /// changes, so that rendering says whether it is out of the ordinary. Left untested when
+    /// history has fewer than two distinct values
+    pub fn with_significance(mut self, history: &[f64]) -> FigChange {
+        self.significance = stats::z_score(self.diff(), history);
+        self
+    }
+
+    /// Whether the change is more than two standard deviations from the historical mean,
+    /// None when untested
+    pub fn is_unusual(&self) -> Option<bool> {
+        self.significance.map(|z| z.abs() > 2.0)
+    }
+
+    /// Whether the metric changed sign, e.g. from a loss to a profit, which leaves the
+    /// proportional change meaningless
+    pub fn crosses_zero(&self) -> bool {
+        self.old * self.new < 0.0
+    }
+
     fn diff(&self) -> f64 {
         (self.new - self.old) / self.old
     }
 
+    /// Whether the change is small enough to be described as steady by the metric
+    pub fn is_steady(&self) -> bool {
+        !self.crosses_zero() && self.metric.is_steady(self.diff())
+    }
+
+    /// The proportional change, or the absolute one when the sign changed so that it
+    /// still points the right way
+    fn movement(&self) -> f64 {
+        if self.crosses_zero() {
+            self.new - self.old
+        } else {
+            self.diff()
+        }
+    }
+
     fn diff_format(&self) -> String {
-        let diff = self.diff();
+        let diff = self.movement();
         let mut output = String::new();
-        output.push_str({
-            if diff > 0.0 {
-                "up"
-            } else {
-                "down"
+        if self.crosses_zero() {
+            output.push_str(&format!(
+                "swung from {} to {}",
+                self.metric.signed_amount(self.old),
+                self.metric.signed_amount(self.new)
+            ));
+        } else if self.is_steady() {
+            output.push_str(self.metric.steady().word());
+        } else {
+            output.push_str(self.metric.direction_word(diff));
+            output.push(' ');
+            output.push_str(&self.metric.format_percent(100.0 * diff.abs(), 1));
+        }
+        if self.comparison == Some(Comparison::YearOnYear) {
+            output.push_str(" year on year");
+        }
+        match self.metric.direction {
+            _ if self.is_steady() => (),
+            Some(direction) if direction.is_improvement(diff) => {
+                output.push_str(", an improvement")
             }
-        });
-        output.push_str(&format!(" {:.1}%", (100.0 * diff.abs())));
+            Some(_) if diff != 0.0 => output.push_str(", a deterioration"),
+            _ => (),
+        }
+        match self.is_unusual() {
+            Some(true) if diff > 0.0 => output.push_str(", a statistically unusual jump"),
+            Some(true) => output.push_str(", a statistically unusual drop"),
+            Some(false) => output.push_str(&format!(
+                ", within normal {0}-to-{0} variation",
+                self.metric.frequency.noun()
+            )),
+            None => (),
+        }
         output
     }
 }
@@ -171,6 +971,110 @@ impl Display for FigChange {
     }
 }
 
+/// How the number of periods in a span is counted when averaging
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum Normalization {
+    /// Uses the actual calendar length of each period, e.g. 28 to 31 day months
+    Exact,
+    /// Treats every period as its nominal length, e.g. 30 day months
+    Nominal,
+    /// Counts only the weekdays which aren't holidays in the calendar, and longer periods
+    /// as that many business days over the five in each week of their nominal length
+    BusinessDays(HolidayCalendar),
+}
+
+/// A total over a span expressed as an average per period of a (usually shorter) frequency
+#[derive(Serialize, Deserialize)]
+pub struct FigAvgFreq {
+    total: f64,
+    span: TimeSpan,
+    per: TimeFrequency,
+    normalization: Normalization,
+    metric: Metric,
+    when: NaiveDate,
+}
+
+impl Figure for FigAvgFreq {
+    fn metric_info(&self) -> &Metric {
+        &self.metric
+    }
+
+    fn when(&self) -> &NaiveDate {
+        &self.when
+    }
+
+    fn raw_value(&self) -> Option<f64> {
+        self.average().ok()
+    }
+}
+
+impl FigAvgFreq {
+    pub fn new(
+        metric: Metric,
+        span: TimeSpan,
+        total: f64,
+        per: TimeFrequency,
+        normalization: Normalization,
+    ) -> FigAvgFreq {
+        FigAvgFreq {
+            total,
+            span,
+            per,
+            normalization,
+            metric,
+            when: span.end(),
+        }
+    }
+
+    pub fn normalization(&self) -> &Normalization {
+        &self.normalization
+    }
+
+    /// Number of periods of the averaging frequency the total is divided by
+    pub fn periods(&self) -> Result<f64, DateError> {
+        match &self.normalization {
+            Normalization::Exact => self.span.count(self.per),
+            Normalization::Nominal => Ok(self.span.days() as f64 / self.per.nominal_days()),
+            Normalization::BusinessDays(calendar) => match self.span.business_days(calendar) {
+                0 => Err(DateError::EmptySpan),
+                days => Ok(match self.per {
+                    TimeFrequency::Daily => days as f64,
+                    _ => days as f64 / (self.per.nominal_days() * 5.0 / 7.0),
+                }),
+            },
+        }
+    }
+
+    /// What the average is per, e.g. day or business day
+    fn per_noun(&self) -> String {
+        match self.normalization {
+            Normalization::BusinessDays(_) => format!("business {}", self.per.noun()),
+            _ => self.per.noun().to_string(),
+        }
+    }
+
+    pub fn average(&self) -> Result<f64, DateError> {
+        Ok(self.total / self.periods()?)
+    }
+}
+
+impl Display for FigAvgFreq {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let average = match self.average() {
+            Ok(average) => self.metric.in_units(&self.metric.format_fixed(average, 2)),
+            Err(_) => String::from("n/a"),
+        };
+        write!(
+            f,
+            "{}",
+            self.format(
+                &self.metric.print_text,
+                format!("{} per {}", average, self.per_noun())
+            )
+        )
+    }
+}
+
 pub struct Datapoint {
     value: f64,
     metric: Metric,
@@ -207,8 +1111,24 @@ impl Datapoint {
         Ok(())
     }
 
+    /// Reads the metric's datapoints saved in sqlite3, or calculates them if the metric is
+    /// derived from a formula
     pub fn read(metric: Metric) -> rusqlite::Result<Vec<Datapoint>> {
         let conn = Connection::open(DATABASE_FILE)?;
+        migrate_metric_table(&conn)?;
+        Datapoint::read_from(&conn, &metric, &mut Vec::new())
+    }
+
+    /// Reads or derives the metric's datapoints, where deriving holds the names of the
+    /// derived metrics already being calculated
+    fn read_from(
+        conn: &Connection,
+        metric: &Metric,
+        deriving: &mut Vec<String>,
+    ) -> rusqlite::Result<Vec<Datapoint>> {
+        if let Some(formula) = &metric.formula {
+            return Datapoint::derive(conn, metric, formula, deriving);
+        }
 
         let mut stmt = conn.prepare("SELECT naive_date, val FROM data WHERE metric_name = ?1")?;
 
@@ -225,19 +1145,354 @@ impl Datapoint {
         points
     }
 
-    pub fn value(&self) -> f64 {
-        self.value
-    }
+    /// Evaluates formula on every date all of its metrics have a datapoint for, deriving
+    /// any of those metrics which are themselves derived
+    fn derive(
+        conn: &Connection,
+        metric: &Metric,
+        formula: &Formula,
+        deriving: &mut Vec<String>,
+    ) -> rusqlite::Result<Vec<Datapoint>> {
+        if deriving.contains(&metric.name) {
+            return Err(Error::InvalidParameterName(format!(
+                "{} is derived from itself",
+                metric.name
+            )));
+        }
+        deriving.push(metric.name.clone());
+
+        let metrics = Metric::read_from(conn)?;
+        let mut values: BTreeMap<NaiveDate, HashMap<String, f64>> = BTreeMap::new();
+        for name in formula.metrics() {
+            let source = metrics.get(name).cloned().unwrap_or_else(|| {
+                Metric::new(name.to_string(), None, String::new(), metric.frequency)
+            });
+            for point in Datapoint::read_from(conn, &source, deriving)? {
+                values
+                    .entry(point.when)
+                    .or_default()
+                    .insert(name.to_string(), point.value);
+            }
+        }
+        deriving.pop();
+
+        Ok(values
+            .into_iter()
+            .filter_map(|(when, values)| {
+                Some(Datapoint {
+                    value: formula.evaluate(&values)?,
+                    metric: metric.clone(),
+                    when,
+                })
+            })
+            .collect())
+    }
+
+    pub fn value(&self) -> f64 {
+        self.value
+    }
 
     pub fn when(&self) -> NaiveDate {
         self.when
     }
 }
 
-pub trait Component {}
+/// The value a metric is expected to reach over a span
+pub struct Target {
+    value: f64,
+    metric: Metric,
+    start: NaiveDate,
+    end: NaiveDate,
+}
+
+impl Target {
+    pub fn new(value: f64, metric: Metric, span: &TimeSpan) -> Target {
+        Target {
+            value,
+            metric,
+            start: span.start(),
+            end: span.end(),
+        }
+    }
+
+    pub fn write(&self) -> rusqlite::Result<()> {
+        let conn = Connection::open(DATABASE_FILE)?;
+
+        conn.execute(
+            r#"CREATE TABLE IF NOT EXISTS target (
+            metric_name TEXT NOT NULL, 
+            start_date TEXT NOT NULL, 
+            end_date TEXT NOT NULL, 
+            val REAL, 
+            PRIMARY KEY (metric_name, start_date, end_date), 
+            FOREIGN KEY(metric_name) REFERENCES metric(name))"#,
+            [],
+        )?;
+
+        conn.execute(
+            "INSERT INTO target (metric_name, start_date, end_date, val) VALUES (?1, ?2, ?3, ?4)",
+            params![self.metric.name, self.start, self.end, self.value],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn read(metric: Metric) -> rusqlite::Result<Vec<Target>> {
+        let conn = Connection::open(DATABASE_FILE)?;
+
+        let mut stmt =
+            conn.prepare("SELECT start_date, end_date, val FROM target WHERE metric_name = ?1")?;
+
+        let targets: Result<Vec<_>, _> = stmt
+            .query_map(params![metric.name], |row| {
+                Ok(Target {
+                    value: row.get(2)?,
+                    metric: metric.clone(),
+                    start: row.get(0)?,
+                    end: row.get(1)?,
+                })
+            })?
+            .collect();
+
+        targets
+    }
+
+    /// The target set for exactly span, if any
+    pub fn find<'a>(targets: &'a [Target], span: &TimeSpan) -> Option<&'a Target> {
+        targets
+            .iter()
+            .find(|target| target.start == span.start() && target.end == span.end())
+    }
+
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+
+    pub fn metric(&self) -> &Metric {
+        &self.metric
+    }
+}
+
+/// The amount budgeted for a metric over a span, such as a full year's spend
+pub struct Budget {
+    value: f64,
+    metric: Metric,
+    start: NaiveDate,
+    end: NaiveDate,
+}
+
+impl Budget {
+    pub fn new(value: f64, metric: Metric, span: &TimeSpan) -> Budget {
+        Budget {
+            value,
+            metric,
+            start: span.start(),
+            end: span.end(),
+        }
+    }
+
+    pub fn write(&self) -> rusqlite::Result<()> {
+        let conn = Connection::open(DATABASE_FILE)?;
+
+        conn.execute(
+            r#"CREATE TABLE IF NOT EXISTS budget (
+            metric_name TEXT NOT NULL, 
+            start_date TEXT NOT NULL, 
+            end_date TEXT NOT NULL, 
+            val REAL, 
+            PRIMARY KEY (metric_name, start_date, end_date), 
+            FOREIGN KEY(metric_name) REFERENCES metric(name))"#,
+            [],
+        )?;
+
+        conn.execute(
+            "INSERT INTO budget (metric_name, start_date, end_date, val) VALUES (?1, ?2, ?3, ?4)",
+            params![self.metric.name, self.start, self.end, self.value],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn read(metric: Metric) -> rusqlite::Result<Vec<Budget>> {
+        let conn = Connection::open(DATABASE_FILE)?;
+
+        let mut stmt =
+            conn.prepare("SELECT start_date, end_date, val FROM budget WHERE metric_name = ?1")?;
+
+        let budgets: Result<Vec<_>, _> = stmt
+            .query_map(params![metric.name], |row| {
+                Ok(Budget {
+                    value: row.get(2)?,
+                    metric: metric.clone(),
+                    start: row.get(0)?,
+                    end: row.get(1)?,
+                })
+            })?
+            .collect();
+
+        budgets
+    }
+
+    /// The budget for span: one set for exactly span if there is one, otherwise the share
+    /// by days of a budget covering the whole of span, such as a full-year budget
+    pub fn for_span(budgets: &[Budget], span: &TimeSpan) -> Option<f64> {
+        if let Some(budget) = budgets
+            .iter()
+            .find(|budget| budget.start == span.start() && budget.end == span.end())
+        {
+            return Some(budget.value);
+        }
+        budgets
+            .iter()
+            .find(|budget| budget.start <= span.start() && span.end() <= budget.end)
+            .map(|budget| {
+                let days = (budget.end - budget.start).num_days() + 1;
+                budget.value * span.days() as f64 / days as f64
+            })
+    }
+
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+}
+
+/// A named collection of metrics which are compared with each other, such as all channels
+pub struct MetricGroup {
+    name: String,
+    members: Vec<String>,
+}
+
+impl MetricGroup {
+    pub fn new(name: String, members: Vec<String>) -> MetricGroup {
+        MetricGroup { name, members }
+    }
+
+    pub fn write(&self) -> rusqlite::Result<()> {
+        let conn = Connection::open(DATABASE_FILE)?;
+
+        conn.execute(
+            r#"CREATE TABLE IF NOT EXISTS metric_group (
+            group_name TEXT NOT NULL, 
+            metric_name TEXT NOT NULL, 
+            PRIMARY KEY (group_name, metric_name), 
+            FOREIGN KEY(metric_name) REFERENCES metric(name))"#,
+            [],
+        )?;
+
+        for member in &self.members {
+            conn.execute(
+                "INSERT OR IGNORE INTO metric_group (group_name, metric_name) VALUES (?1, ?2)",
+                params![self.name, member],
+            )?;
+        }
+
+        Ok(())
+    }
+
+    pub fn read(name: &str) -> rusqlite::Result<MetricGroup> {
+        let conn = Connection::open(DATABASE_FILE)?;
+
+        let mut stmt =
+            conn.prepare("SELECT metric_name FROM metric_group WHERE group_name = ?1")?;
+
+        let members: Result<Vec<String>, _> =
+            stmt.query_map(params![name], |row| row.get(0))?.collect();
+
+        Ok(MetricGroup::new(name.to_string(), members?))
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn members(&self) -> &[String] {
+        &self.members
+    }
+}
+
+/// Named metrics with the weight each contributes to a composite, such as a blended score
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MetricWeights {
+    name: String,
+    weights: Vec<(String, f64)>,
+}
+
+impl MetricWeights {
+    pub fn new(name: String, weights: Vec<(String, f64)>) -> MetricWeights {
+        MetricWeights { name, weights }
+    }
+
+    pub fn write(&self) -> rusqlite::Result<()> {
+        let conn = Connection::open(DATABASE_FILE)?;
+
+        conn.execute(
+            r#"CREATE TABLE IF NOT EXISTS metric_weight (
+            group_name TEXT NOT NULL, 
+            metric_name TEXT NOT NULL, 
+            weight REAL NOT NULL, 
+            PRIMARY KEY (group_name, metric_name), 
+            FOREIGN KEY(metric_name) REFERENCES metric(name))"#,
+            [],
+        )?;
+
+        for (member, weight) in &self.weights {
+            conn.execute(
+                "INSERT OR REPLACE INTO metric_weight (group_name, metric_name, weight) VALUES (?1, ?2, ?3)",
+                params![self.name, member, weight],
+            )?;
+        }
+
+        Ok(())
+    }
+
+    pub fn read(name: &str) -> rusqlite::Result<MetricWeights> {
+        let conn = Connection::open(DATABASE_FILE)?;
+
+        let mut stmt =
+            conn.prepare("SELECT metric_name, weight FROM metric_weight WHERE group_name = ?1")?;
+
+        let weights: Result<Vec<(String, f64)>, _> = stmt
+            .query_map(params![name], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect();
+
+        Ok(MetricWeights::new(name.to_string(), weights?))
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn weights(&self) -> &[(String, f64)] {
+        &self.weights
+    }
+}
+
+pub trait Component {
+    /// Writes the component out for context
+    fn rendered(&self, context: RenderContext) -> String;
+
+    /// The component's figure as JSON, None for plain text
+    fn figure_json(&self) -> Option<serde_json::Value> {
+        None
+    }
+}
+
+impl Component for String {
+    fn rendered(&self, context: RenderContext) -> String {
+        context.escape(self)
+    }
+}
+
+/// Every figure, including those defined outside this crate, can appear in a statement
+impl<F: Figure + Display> Component for F {
+    fn rendered(&self, context: RenderContext) -> String {
+        self.render_in(context)
+    }
 
-impl Component for String {}
-impl Component for FigChange {}
+    fn figure_json(&self) -> Option<serde_json::Value> {
+        Some(self.to_json())
+    }
+}
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Statement<C: Component> {
@@ -254,12 +1509,93 @@ impl<C: Component + Display> fmt::Display for Statement<C> {
     }
 }
 
+impl<C: Component> Statement<C> {
+    /// Writes each component out for context, separated by spaces
+    pub fn render(&self, context: RenderContext) -> String {
+        self.contents
+            .iter()
+            .map(|component| component.rendered(context))
+            .join(" ")
+    }
+
+    /// The statement's text alongside each of its figures as JSON
+    pub fn to_json(&self) -> serde_json::Value {
+        let figures: Vec<_> = self
+            .contents
+            .iter()
+            .filter_map(|component| component.figure_json())
+            .collect();
+        serde_json::json!({
+            "text": self.render(RenderContext::Words),
+            "figures": figures,
+        })
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Paragraph<C: Component> {
     pub contents: Vec<Statement<C>>,
     pub name: String,
 }
 
+impl<C: Component> Paragraph<C> {
+    /// Writes the paragraph out for context, its name as a heading followed by each
+    /// statement on its own line
+    pub fn render(&self, context: RenderContext) -> String {
+        std::iter::once(context.heading(&self.name))
+            .chain(
+                self.contents
+                    .iter()
+                    .map(|statement| statement.render(context)),
+            )
+            .join("\n")
+    }
+
+    /// The whole paragraph as JSON, giving each statement's text and figures
+    pub fn to_json(&self) -> serde_json::Value {
+        let statements: Vec<_> = self.contents.iter().map(Statement::to_json).collect();
+        serde_json::json!({
+            "name": self.name,
+            "statements": statements,
+        })
+    }
+}
+
+/// Replaces each `{name}` in template with the field of that name, leaving placeholders
+/// which aren't fields as written. Doubled braces are escapes for single ones
+fn fill_fields(template: &str, fields: &[(&str, String)]) -> String {
+    let mut output = String::new();
+    let mut rest = template;
+    while let Some(index) = rest.find(['{', '}']) {
+        output.push_str(&rest[..index]);
+        rest = &rest[index..];
+        if rest.starts_with("{{") || rest.starts_with("}}") {
+            output.push_str(&rest[..1]);
+            rest = &rest[2..];
+            continue;
+        }
+        let field = rest[1..].find('}').and_then(|end| {
+            let name = &rest[1..end + 1];
+            fields
+                .iter()
+                .find(|(field, _)| *field == name)
+                .map(|(_, value)| (value, end + 2))
+        });
+        match field {
+            Some((value, length)) => {
+                output.push_str(value);
+                rest = &rest[length..];
+            }
+            None => {
+                output.push_str(&rest[..1]);
+                rest = &rest[1..];
+            }
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -289,4 +1625,533 @@ mod tests {
             String::from("Website users were down 5% ")
         );
     }
+
+    #[test]
+    fn average_per_frequency() {
+        let metric = Metric::new(
+            String::from("users"),
+            None,
+            String::from("Users averaged {}"),
+            TimeFrequency::Quarterly,
+        );
+        let quarter = TimeSpan::new(
+            &NaiveDate::from_ymd_opt(2022, 2, 4).unwrap(),
+            TimeFrequency::Quarterly,
+        )
+        .unwrap();
+
+        let exact = FigAvgFreq::new(
+            metric.clone(),
+            quarter,
+            900.0,
+            TimeFrequency::Daily,
+            Normalization::Exact,
+        );
+        assert_eq!(exact.average(), Ok(10.0));
+        assert_eq!(exact.to_string(), "Users averaged 10.00 per day");
+
+        let whole = FigAvgFreq::new(
+            metric.clone().with_precision(Precision::Decimals(0)),
+            quarter,
+            900.0,
+            TimeFrequency::Daily,
+            Normalization::Exact,
+        );
+        assert_eq!(whole.to_string(), "Users averaged 10 per day");
+
+        let nominal = FigAvgFreq::new(
+            metric,
+            quarter,
+            900.0,
+            TimeFrequency::Monthly,
+            Normalization::Nominal,
+        );
+        assert_eq!(nominal.periods(), Ok(3.0));
+    }
+
+    #[test]
+    fn average_per_business_day() {
+        let metric = Metric::new(
+            String::from("sales"),
+            None,
+            String::from("Sales averaged {}"),
+            TimeFrequency::Weekly,
+        );
+        let week = TimeSpan::new(
+            &NaiveDate::from_ymd_opt(2022, 4, 13).unwrap(),
+            TimeFrequency::Weekly,
+        )
+        .unwrap();
+        let easter = HolidayCalendar::new([
+            NaiveDate::from_ymd_opt(2022, 4, 15).unwrap(),
+            NaiveDate::from_ymd_opt(2022, 4, 18).unwrap(),
+        ]);
+
+        let average = FigAvgFreq::new(
+            metric.clone(),
+            week,
+            120.0,
+            TimeFrequency::Daily,
+            Normalization::BusinessDays(easter.clone()),
+        );
+        assert_eq!(average.periods(), Ok(4.0));
+        assert_eq!(average.to_string(), "Sales averaged 30.00 per business day");
+
+        let weekly = FigAvgFreq::new(
+            metric.clone(),
+            week,
+            120.0,
+            TimeFrequency::Weekly,
+            Normalization::BusinessDays(easter.clone()),
+        );
+        assert_eq!(weekly.periods(), Ok(0.8));
+        assert_eq!(
+            weekly.to_string(),
+            "Sales averaged 150.00 per business week"
+        );
+
+        let weekend = TimeSpan::new(
+            &NaiveDate::from_ymd_opt(2022, 4, 16).unwrap(),
+            TimeFrequency::Daily,
+        )
+        .unwrap();
+        let closed = FigAvgFreq::new(
+            metric,
+            weekend,
+            120.0,
+            TimeFrequency::Daily,
+            Normalization::BusinessDays(HolidayCalendar::default()),
+        );
+        assert_eq!(closed.raw_value(), None);
+        assert_eq!(closed.to_string(), "Sales averaged n/a per business day");
+    }
+
+    #[test]
+    fn custom_figures_join_statements() {
+        struct FigCats {
+            cats: u32,
+            metric: Metric,
+            when: NaiveDate,
+        }
+
+        impl Figure for FigCats {
+            fn metric_info(&self) -> &Metric {
+                &self.metric
+            }
+
+            fn when(&self) -> &NaiveDate {
+                &self.when
+            }
+        }
+
+        impl Display for FigCats {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{}", self.render(format!("{} cats", self.cats)))
+            }
+        }
+
+        let metric = Metric::new(
+            String::from("cats"),
+            None,
+            String::from("Purrs came from {} this week"),
+            TimeFrequency::Weekly,
+        );
+        let statement = Statement {
+            contents: vec![FigCats {
+                cats: 3,
+                metric,
+                when: NaiveDate::from_ymd_opt(2022, 1, 10).unwrap(),
+            }],
+        };
+        assert_eq!(statement.to_string(), "Purrs came from 3 cats this week");
+    }
+
+    #[test]
+    fn change_significance() {
+        let metric = Metric::new(
+            String::from("users"),
+            None,
+            String::from("Website users were {}"),
+            TimeFrequency::Weekly,
+        );
+        let when = NaiveDate::from_ymd_opt(2022, 2, 4).unwrap();
+        let history = [0.02, -0.03, 0.05, 0.01, -0.02, 0.03];
+
+        let normal = FigChange::new(metric.clone(), when, 100.0, 104.0).with_significance(&history);
+        assert_eq!(normal.is_unusual(), Some(false));
+        assert_eq!(
+            normal.to_string(),
+            "Website users were up 4.0%, within normal week-to-week variation"
+        );
+
+        let unusual =
+            FigChange::new(metric.clone(), when, 100.0, 140.0).with_significance(&history);
+        assert_eq!(
+            unusual.to_string(),
+            "Website users were up 40.0%, a statistically unusual jump"
+        );
+
+        let untested = FigChange::new(metric.clone(), when, 100.0, 95.0).with_significance(&[]);
+        assert_eq!(untested.to_string(), "Website users were down 5.0%");
+
+        let whole = FigChange::new(
+            metric.with_precision(Precision::Significant(2)),
+            when,
+            100.0,
+            125.0,
+        );
+        assert_eq!(whole.to_string(), "Website users were up 25%");
+    }
+
+    #[test]
+    fn render_statement_as_html() {
+        let metric = Metric::new(
+            String::from("users"),
+            None,
+            String::from("Website users were {}"),
+            TimeFrequency::Weekly,
+        );
+        let when = NaiveDate::from_ymd_opt(2022, 2, 4).unwrap();
+        let statement = Statement {
+            contents: vec![FigChange::new(metric, when, 100.0, 125.0)],
+        };
+        assert_eq!(
+            statement.render(RenderContext::Html),
+            "Website users were <span class=\"delta up\">up 25.0%</span>"
+        );
+        assert_eq!(
+            statement.render(RenderContext::Words),
+            statement.to_string()
+        );
+        assert_eq!(statement.render(RenderContext::Raw), "0.25");
+    }
+
+    #[test]
+    fn paragraph_as_json() {
+        let metric = Metric::new(
+            String::from("users"),
+            None,
+            String::from("Website users were {}"),
+            TimeFrequency::Weekly,
+        );
+        let when = NaiveDate::from_ymd_opt(2022, 2, 4).unwrap();
+        let paragraph = Paragraph {
+            name: String::from("Top highlights"),
+            contents: vec![Statement {
+                contents: vec![FigChange::new(metric, when, 100.0, 125.0)],
+            }],
+        };
+        assert_eq!(
+            paragraph.to_json(),
+            serde_json::json!({
+                "name": "Top highlights",
+                "statements": [{
+                    "text": "Website users were up 25.0%",
+                    "figures": [{
+                        "metric": "users",
+                        "when": "2022-02-04",
+                        "text": "Website users were up 25.0%",
+                        "value": 125.0,
+                        "previous": 100.0,
+                        "change": 0.25,
+                        "direction": "up",
+                    }],
+                }],
+            })
+        );
+        assert_eq!(
+            paragraph.render(RenderContext::Ansi),
+            "\x1b[1mTop highlights\x1b[0m\nWebsite users were \x1b[32mup 25.0%\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn abbreviated_amounts() {
+        let metric = Metric::new(
+            String::from("views"),
+            None,
+            String::from("Views were {}"),
+            TimeFrequency::Weekly,
+        );
+        assert_eq!(metric.format_amount(3_400_000.0), "3,400,000");
+
+        let abbreviated = metric.with_abbreviation();
+        assert!(abbreviated.abbreviates());
+        assert_eq!(abbreviated.format_amount(1_240.0), "1.2k");
+        assert_eq!(abbreviated.format_amount(3_400_000.0), "3.4M");
+        assert_eq!(abbreviated.format_amount(1_100_000_000.0), "1.1B");
+        assert_eq!(abbreviated.format_amount(999_960.0), "1.0M");
+        assert_eq!(abbreviated.format_amount(640.0), "640");
+        assert_eq!(
+            abbreviated
+                .with_currency(Currency::new("USD", "$", SymbolPosition::Before))
+                .format_amount(-2_500_000.0),
+            "-$2.5M"
+        );
+    }
+
+    #[test]
+    fn sign_crossing_change() {
+        let metric = Metric::new(
+            String::from("net_profit"),
+            None,
+            String::from("Net profit {}"),
+            TimeFrequency::Monthly,
+        );
+        let when = NaiveDate::from_ymd_opt(2022, 2, 28).unwrap();
+
+        let swing = FigChange::new(metric.clone(), when, -3000.0, 1200.0);
+        assert!(swing.crosses_zero());
+        assert_eq!(swing.to_string(), "Net profit swung from -3,000 to 1,200");
+        assert_eq!(swing.markup_class(), "delta up");
+        assert_eq!(swing.raw_value(), None);
+
+        let metric = metric
+            .with_abbreviation()
+            .with_sign_words(Vocabulary::parse("profit/loss").unwrap());
+        let swing = FigChange::new(metric.clone(), when, -3000.0, 1200.0);
+        assert_eq!(
+            swing.to_string(),
+            "Net profit swung from a loss of 3k to a profit of 1.2k"
+        );
+        assert!(!FigChange::new(metric, when, 3000.0, 1200.0).crosses_zero());
+    }
+
+    #[test]
+    fn escaped_template_braces() {
+        let fields = [
+            ("pct", String::from("25.0%")),
+            ("span", String::from("{week}")),
+        ];
+        assert_eq!(
+            fill_fields("{pct} {{pct}} {span} {unknown} }}{{", &fields),
+            "25.0% {pct} {week} {unknown} }{"
+        );
+    }
+
+    #[test]
+    fn older_metric_tables_migrate() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE metric (name TEXT PRIMARY KEY, description TEXT, print_text TEXT, frequency TEXT)",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO metric VALUES ('users', NULL, 'Website users were {}', 'Weekly')",
+            [],
+        )
+        .unwrap();
+        migrate_metric_table(&conn).unwrap();
+        migrate_metric_table(&conn).unwrap();
+        let metrics = Metric::read_from(&conn).unwrap();
+        assert_eq!(metrics["users"].week_start(), Weekday::Mon);
+        assert_eq!(metrics["users"].frequency(), TimeFrequency::Weekly);
+
+        let old_json = r#"{"name":"users","description":null,"print_text":"Users were {}","frequency":"Weekly"}"#;
+        let metric: Metric = serde_json::from_str(old_json).unwrap();
+        assert_eq!(metric.week_start(), Weekday::Mon);
+    }
+
+    #[test]
+    fn derived_metrics_nest() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate_metric_table(&conn).unwrap();
+        conn.execute(
+            "CREATE TABLE data (metric_name TEXT, naive_date TEXT, val REAL)",
+            [],
+        )
+        .unwrap();
+        for (name, formula) in [
+            ("revenue", None),
+            ("visits", None),
+            ("per_visit", Some("revenue / visits")),
+            ("per_hundred", Some("per_visit * 100")),
+            ("echo", Some("loop + 1")),
+            ("loop", Some("echo * 2")),
+        ] {
+            conn.execute(
+                "INSERT INTO metric (name, print_text, frequency, formula) VALUES (?1, '{}', 'Weekly', ?2)",
+                params![name, formula],
+            )
+            .unwrap();
+        }
+        let when = NaiveDate::from_ymd_opt(2022, 1, 10).unwrap();
+        for (name, value) in [("revenue", 50.0), ("visits", 200.0)] {
+            conn.execute(
+                "INSERT INTO data VALUES (?1, ?2, ?3)",
+                params![name, when, value],
+            )
+            .unwrap();
+        }
+
+        let metrics = Metric::read_from(&conn).unwrap();
+        let points = Datapoint::read_from(&conn, &metrics["per_hundred"], &mut Vec::new()).unwrap();
+        assert_eq!(points.len(), 1);
+        assert_eq!(points[0].value(), 25.0);
+        assert_eq!(points[0].when(), when);
+
+        assert!(Datapoint::read_from(&conn, &metrics["echo"], &mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn steady_change() {
+        let metric = Metric::new(
+            String::from("users"),
+            None,
+            String::from("Website users were {}"),
+            TimeFrequency::Weekly,
+        );
+        let when = NaiveDate::from_ymd_opt(2022, 2, 4).unwrap();
+
+        let same = FigChange::new(metric.clone(), when, 2000.0, 2000.0);
+        assert_eq!(same.to_string(), "Website users were unchanged");
+        assert_eq!(same.markup_class(), "delta steady");
+        assert_eq!(
+            FigChange::new(metric.clone(), when, 2000.0, 2000.9).to_string(),
+            "Website users were unchanged"
+        );
+        assert_eq!(
+            FigChange::new(metric.clone(), when, 2000.0, 2001.0).to_string(),
+            "Website users were up 0.1%"
+        );
+        let precise = metric.clone().with_precision(Precision::Decimals(2));
+        assert_eq!(
+            FigChange::new(precise, when, 2000.0, 2000.8).to_string(),
+            "Website users were up 0.04%"
+        );
+
+        let metric = metric.with_steady(Steady::new("broadly steady", 0.01));
+        assert_eq!(
+            FigChange::new(metric.clone(), when, 2000.0, 2015.0).to_string(),
+            "Website users were broadly steady"
+        );
+        assert_eq!(
+            FigChange::new(metric, when, 2000.0, 2100.0).to_string(),
+            "Website users were up 5.0%"
+        );
+    }
+
+    #[test]
+    fn rounding_reconciles() {
+        let metric = Metric::new(
+            String::from("revenue"),
+            None,
+            String::from("Revenue was {}"),
+            TimeFrequency::Weekly,
+        );
+        let when = NaiveDate::from_ymd_opt(2022, 2, 4).unwrap();
+        assert_eq!(metric.format_amount(2.25), "2.3");
+        assert_eq!(
+            FigChange::new(metric.clone(), when, 400.0, 409.0).to_string(),
+            "Revenue was up 2.3%"
+        );
+
+        let bankers = metric.clone().with_rounding(Rounding::HalfEven);
+        assert_eq!(bankers.format_amount(2.25), "2.2");
+        assert_eq!(
+            FigChange::new(bankers, when, 400.0, 409.0).to_string(),
+            "Revenue was up 2.2%"
+        );
+
+        let floor = metric.with_rounding(Rounding::Floor);
+        assert_eq!(floor.format_fixed(10.999, 2), "10.99");
+    }
+
+    #[test]
+    fn scientific_fallback() {
+        let metric = Metric::new(
+            String::from("particles"),
+            None,
+            String::from("Particles were {}"),
+            TimeFrequency::Weekly,
+        )
+        .with_unit("kg");
+        assert_eq!(metric.format_amount(0.00000032), "3.2 × 10⁻⁷ kg");
+        assert_eq!(metric.format_amount(1250.0), "1,250 kg");
+
+        let metric = metric.with_scientific(Scientific::new(0.01, 1e6));
+        assert_eq!(metric.format_amount(12_500_000.0), "1.25 × 10⁷ kg");
+        assert_eq!(metric.format_fixed(0.004, 2), "4 × 10⁻³");
+    }
+
+    #[test]
+    fn spelled_out_numbers() {
+        let metric = Metric::new(
+            String::from("users"),
+            None,
+            String::from("Website users were {}"),
+            TimeFrequency::Weekly,
+        )
+        .with_spelled_numbers(100);
+        let when = NaiveDate::from_ymd_opt(2022, 2, 4).unwrap();
+        assert_eq!(
+            FigChange::new(metric.clone(), when, 100.0, 125.0).to_string(),
+            "Website users were up twenty-five per cent"
+        );
+        assert_eq!(
+            FigChange::new(metric.clone(), when, 100.0, 112.5).to_string(),
+            "Website users were up 12.5%"
+        );
+        assert_eq!(metric.format_amount(7.0), "seven");
+        assert_eq!(metric.format_amount(250.0), "250");
+        assert_eq!(
+            metric.clone().with_unit("visits").format_amount(1.0),
+            "one visit"
+        );
+        assert_eq!(metric.with_unit("%").format_amount(40.0), "forty per cent");
+    }
+
+    #[test]
+    fn combined_words_and_numbers() {
+        let metric = Metric::new(
+            String::from("users"),
+            None,
+            String::from("Website users were {}"),
+            TimeFrequency::Weekly,
+        );
+        let when = NaiveDate::from_ymd_opt(2022, 2, 4).unwrap();
+        let quarter = FigChange::new(metric.clone(), when, 100.0, 125.0);
+        assert_eq!(
+            quarter.render_in(RenderContext::Combined),
+            "Website users were up by a quarter (25.0%)"
+        );
+        assert_eq!(
+            FigChange::new(metric.clone(), when, 300.0, 201.0).render_in(RenderContext::Combined),
+            "Website users were down by about a third (33.0%)"
+        );
+        assert_eq!(
+            FigChange::new(metric, when, 100.0, 142.0).render_in(RenderContext::Combined),
+            "Website users were up 42.0%"
+        );
+    }
+
+    #[test]
+    fn lower_is_better() {
+        let metric = Metric::new(
+            String::from("tickets"),
+            None,
+            String::from("Support tickets were {}"),
+            TimeFrequency::Weekly,
+        )
+        .with_direction(GoodDirection::parse("lower_is_better").unwrap());
+        let when = NaiveDate::from_ymd_opt(2022, 2, 4).unwrap();
+
+        let fewer = FigChange::new(metric.clone(), when, 100.0, 80.0);
+        assert_eq!(
+            fewer.to_string(),
+            "Support tickets were down 20.0%, an improvement"
+        );
+        assert_eq!(
+            fewer.render_in(RenderContext::Html),
+            "Support tickets were <span class=\"delta down good\">down 20.0%, an improvement</span>"
+        );
+
+        let more = FigChange::new(metric, when, 100.0, 110.0);
+        assert_eq!(more.markup_class(), "delta up bad");
+        assert_eq!(
+            more.to_string(),
+            "Support tickets were up 10.0%, a deterioration"
+        );
+    }
 }
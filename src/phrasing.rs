@@ -0,0 +1,95 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use serde::{Deserialize, Serialize};
+
+/// How one of several phrasings is picked each time a figure is written
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PhraseChoice {
+    /// Each phrasing in turn
+    #[default]
+    Rotate,
+    /// A phrasing picked from the seed and the figure, so that a report reads the same
+    /// each time it is written
+    Seeded(u64),
+}
+
+/// Alternative templates phrasing one kind of figure, so that long reports don't repeat
+/// the same sentence shape
+#[derive(Debug, Default)]
+pub(crate) struct Phrasings {
+    templates: Vec<String>,
+    next: AtomicUsize,
+}
+
+impl Phrasings {
+    pub(crate) fn new(template: &str) -> Phrasings {
+        Phrasings {
+            templates: vec![template.to_string()],
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    pub(crate) fn add(&mut self, template: &str) {
+        if !self.templates.iter().any(|existing| existing == template) {
+            self.templates.push(template.to_string());
+        }
+    }
+
+    /// Picks a template for the figure identified by key, e.g. its JSON
+    pub(crate) fn choose(&self, choice: PhraseChoice, key: &str) -> Option<&str> {
+        if self.templates.is_empty() {
+            return None;
+        }
+        let index = match choice {
+            PhraseChoice::Rotate => self.next.fetch_add(1, Ordering::Relaxed),
+            PhraseChoice::Seeded(seed) => fnv1a(seed, key) as usize,
+        };
+        Some(&self.templates[index % self.templates.len()])
+    }
+}
+
+impl Clone for Phrasings {
+    fn clone(&self) -> Phrasings {
+        Phrasings {
+            templates: self.templates.clone(),
+            next: AtomicUsize::new(self.next.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+/// FNV-1a hash of text starting from seed, stable across builds unlike std's hashers
+fn fnv1a(seed: u64, text: &str) -> u64 {
+    const PRIME: u64 = 0x100000001b3;
+    text.bytes().fold(0xcbf29ce484222325 ^ seed, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn phrasings_vary() {
+        let mut phrasings = Phrasings::new("{direction} {pct}");
+        phrasings.add("{direction} by {pct}");
+        phrasings.add("{direction} {pct}");
+        let rotated: Vec<_> = (0..3)
+            .map(|_| phrasings.choose(PhraseChoice::Rotate, "").unwrap())
+            .collect();
+        assert_eq!(
+            rotated,
+            vec![
+                "{direction} {pct}",
+                "{direction} by {pct}",
+                "{direction} {pct}"
+            ]
+        );
+
+        let seeded = PhraseChoice::Seeded(7);
+        let first = phrasings.choose(seeded, "users 2022-02-04");
+        assert_eq!(phrasings.choose(seeded, "users 2022-02-04"), first);
+        assert!(Phrasings::default().choose(seeded, "").is_none());
+    }
+}
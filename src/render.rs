@@ -0,0 +1,641 @@
+use std::{cmp::Ordering, fmt};
+
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::figures::{ordinal, round_significant, with_separators};
+
+/// The kind of document figures are written out for
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RenderContext {
+    /// Plain prose, the same as a figure's Display
+    #[default]
+    Words,
+    /// Prose with each figure's data wrapped in a classed span so it can be styled, e.g.
+    /// `<span class="delta up">up 25.0%</span>`
+    Html,
+    /// Prose for pdflatex with special characters escaped and each figure's data in bold
+    Latex,
+    /// Unformatted, machine-readable values without units, percentages or separators.
+    /// Figures without a single value and plain text are written as words
+    Raw,
+    /// Prose with figures described in words where they can be and the exact figure
+    /// following in brackets, e.g. "up by a quarter (25.0%)"
+    Combined,
+    /// Prose for a terminal, with rising and improving figures in green, falling and
+    /// worsening ones in red, and other figures and headings in bold
+    Ansi,
+}
+
+const ANSI_BOLD: &str = "\x1b[1m";
+const ANSI_GREEN: &str = "\x1b[32m";
+const ANSI_RED: &str = "\x1b[31m";
+const ANSI_RESET: &str = "\x1b[0m";
+
+impl RenderContext {
+    /// Parses `words`, `html`, `latex`, `raw`, `combined` or `ansi`
+    pub fn parse(spec: &str) -> Option<RenderContext> {
+        match spec {
+            "words" => Some(RenderContext::Words),
+            "html" => Some(RenderContext::Html),
+            "latex" => Some(RenderContext::Latex),
+            "raw" => Some(RenderContext::Raw),
+            "combined" => Some(RenderContext::Combined),
+            "ansi" => Some(RenderContext::Ansi),
+            _ => None,
+        }
+    }
+
+    /// Escapes text so that it appears as written
+    pub fn escape(&self, text: &str) -> String {
+        match self {
+            RenderContext::Words
+            | RenderContext::Raw
+            | RenderContext::Ansi
+            | RenderContext::Combined => text.to_string(),
+            RenderContext::Html => {
+                let mut escaped = String::with_capacity(text.len());
+                for c in text.chars() {
+                    match c {
+                        '&' => escaped.push_str("&amp;"),
+                        '<' => escaped.push_str("&lt;"),
+                        '>' => escaped.push_str("&gt;"),
+                        '"' => escaped.push_str("&quot;"),
+                        '\'' => escaped.push_str("&#39;"),
+                        _ => escaped.push(c),
+                    }
+                }
+                escaped
+            }
+            RenderContext::Latex => {
+                let mut escaped = String::with_capacity(text.len());
+                for c in text.chars() {
+                    match c {
+                        '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+                            escaped.push('\\');
+                            escaped.push(c);
+                        }
+                        '\\' => escaped.push_str("\\textbackslash{}"),
+                        '~' => escaped.push_str("\\textasciitilde{}"),
+                        '^' => escaped.push_str("\\textasciicircum{}"),
+                        _ => escaped.push(c),
+                    }
+                }
+                escaped
+            }
+        }
+    }
+
+    /// Marks data up as a figure's value, with class describing it
+    pub fn value(&self, data: &str, class: &str) -> String {
+        match self {
+            RenderContext::Words | RenderContext::Raw | RenderContext::Combined => data.to_string(),
+            RenderContext::Html => {
+                format!("<span class=\"{}\">{}</span>", class, self.escape(data))
+            }
+            RenderContext::Latex => format!("\\textbf{{{}}}", self.escape(data)),
+            RenderContext::Ansi => {
+                let classes: Vec<&str> = class.split_whitespace().collect();
+                let style = if classes.contains(&"good") {
+                    ANSI_GREEN
+                } else if classes.contains(&"bad") {
+                    ANSI_RED
+                } else if classes.contains(&"up") {
+                    ANSI_GREEN
+                } else if classes.contains(&"down") {
+                    ANSI_RED
+                } else {
+                    ANSI_BOLD
+                };
+                format!("{}{}{}", style, data, ANSI_RESET)
+            }
+        }
+    }
+
+    /// Marks text up as a heading
+    pub fn heading(&self, text: &str) -> String {
+        match self {
+            RenderContext::Words | RenderContext::Raw | RenderContext::Combined => text.to_string(),
+            RenderContext::Html => format!("<h2>{}</h2>", self.escape(text)),
+            RenderContext::Latex => format!("\\section*{{{}}}", self.escape(text)),
+            RenderContext::Ansi => format!("{}{}{}", ANSI_BOLD, text, ANSI_RESET),
+        }
+    }
+
+    /// Renders text, a figure's words which put its data into print_text in place of {},
+    /// marking up the data with class. Text which doesn't follow print_text is escaped
+    /// as a whole
+    pub(crate) fn figure(&self, print_text: &str, text: &str, class: &str) -> String {
+        match split_figure(print_text, text) {
+            Some((before, data, after)) => format!(
+                "{}{}{}",
+                self.escape(&before),
+                self.value(data, class),
+                self.escape(after)
+            ),
+            None => self.escape(text),
+        }
+    }
+}
+
+/// Splits text, a figure's words which put its data into print_text in place of {}, into
+/// the words before the data, the data and the words after. None when text doesn't follow
+/// print_text
+pub(crate) fn split_figure<'a>(
+    print_text: &'a str,
+    text: &'a str,
+) -> Option<(String, &'a str, &'a str)> {
+    let (before, after) = print_text.split_once("{}")?;
+    let before = format!("{} ", before.strip_suffix(' ').unwrap_or(before));
+    let data = text.strip_prefix(&before)?.strip_suffix(after)?;
+    Some((before, data, after))
+}
+
+/// How many digits figures are written with
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Precision {
+    /// A fixed number of decimal places
+    Decimals(usize),
+    /// A number of significant figures, so that very small and very large values both
+    /// show a sensible number of digits
+    Significant(u32),
+}
+
+impl Precision {
+    /// Parses a precision written as `decimals:<n>` or `sigfigs:<n>`
+    pub fn parse(spec: &str) -> Option<Precision> {
+        let (kind, digits) = spec.split_once(':')?;
+        match kind {
+            "decimals" => Some(Precision::Decimals(digits.parse().ok()?)),
+            "sigfigs" => match digits.parse().ok()? {
+                0 => None,
+                digits => Some(Precision::Significant(digits)),
+            },
+            _ => None,
+        }
+    }
+
+    /// Rounds value to the precision
+    pub fn round(&self, value: f64) -> f64 {
+        match self {
+            Precision::Decimals(_) => value,
+            Precision::Significant(digits) => round_significant(value, *digits),
+        }
+    }
+
+    /// Number of decimal places value is written with
+    pub fn decimals(&self, value: f64) -> usize {
+        match self {
+            Precision::Decimals(decimals) => *decimals,
+            Precision::Significant(_) if value == 0.0 => 0,
+            Precision::Significant(digits) => {
+                let magnitude = value.abs().log10().floor() as i32;
+                (*digits as i32 - 1 - magnitude).max(0) as usize
+            }
+        }
+    }
+
+    /// Writes value to the precision with separators between thousands
+    pub fn format(&self, value: f64) -> String {
+        with_separators(self.round(value), self.decimals(value))
+    }
+}
+
+impl fmt::Display for Precision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Precision::Decimals(decimals) => write!(f, "decimals:{}", decimals),
+            Precision::Significant(digits) => write!(f, "sigfigs:{}", digits),
+        }
+    }
+}
+
+/// How values are rounded to the digits they are written with, so that figures
+/// reconcile with the systems they came from
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Rounding {
+    /// Halves round away from zero, e.g. 2.25 to 2.3
+    #[default]
+    HalfUp,
+    /// Banker's rounding, halves round to the even digit, e.g. 2.25 to 2.2
+    HalfEven,
+    /// Always rounds down, e.g. 2.29 to 2.2
+    Floor,
+}
+
+impl Rounding {
+    /// Parses a mode written as `half_up`, `half_even` or `floor`
+    pub fn parse(spec: &str) -> Option<Rounding> {
+        match spec {
+            "half_up" => Some(Rounding::HalfUp),
+            "half_even" => Some(Rounding::HalfEven),
+            "floor" => Some(Rounding::Floor),
+            _ => None,
+        }
+    }
+
+    /// Rounds value to decimals decimal places. Values are taken as written in decimal,
+    /// so 2.675, which is slightly less in binary, is a half
+    pub fn round(&self, value: f64, decimals: usize) -> f64 {
+        let factor = 10f64.powi(decimals as i32);
+        let mut scaled = value * factor;
+        if scaled.abs() < 1e9 {
+            scaled = (scaled * 1e6).round() / 1e6;
+        }
+        let rounded = match self {
+            Rounding::HalfUp => scaled.round(),
+            Rounding::HalfEven => scaled.round_ties_even(),
+            Rounding::Floor => scaled.floor(),
+        };
+        rounded / factor
+    }
+}
+
+impl fmt::Display for Rounding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rounding::HalfUp => write!(f, "half_up"),
+            Rounding::HalfEven => write!(f, "half_even"),
+            Rounding::Floor => write!(f, "floor"),
+        }
+    }
+}
+
+/// Bounds outside which values are written in scientific notation, e.g. 3.2 × 10⁻⁷,
+/// rather than as long strings of digits
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Scientific {
+    small: f64,
+    large: f64,
+}
+
+impl Scientific {
+    /// Values smaller in size than small, other than zero, or at least as large as large
+    /// are written in scientific notation
+    pub fn new(small: f64, large: f64) -> Scientific {
+        Scientific {
+            small: small.abs(),
+            large: large.abs(),
+        }
+    }
+
+    /// Parses bounds written as `<small>:<large>`, e.g. `1e-6:1e12`
+    pub fn parse(spec: &str) -> Option<Scientific> {
+        let (small, large) = spec.split_once(':')?;
+        let small: f64 = small.trim().parse().ok()?;
+        let large: f64 = large.trim().parse().ok()?;
+        (small.is_finite() && large.is_finite() && small.abs() < large.abs())
+            .then(|| Scientific::new(small, large))
+    }
+
+    /// Whether value is outside the bounds
+    pub fn applies(&self, value: f64) -> bool {
+        value.is_finite() && value != 0.0 && (value.abs() < self.small || value.abs() >= self.large)
+    }
+
+    /// Writes value in scientific notation with three significant figures
+    pub fn format(&self, value: f64) -> String {
+        let mut exponent = value.abs().log10().floor() as i32;
+        let mut mantissa = round_significant(value / 10f64.powi(exponent), 3);
+        if mantissa.abs() >= 10.0 {
+            mantissa /= 10.0;
+            exponent += 1;
+        }
+        const SUPERSCRIPTS: [char; 10] = ['⁰', '¹', '²', '³', '⁴', '⁵', '⁶', '⁷', '⁸', '⁹'];
+        let mut power: String = exponent
+            .unsigned_abs()
+            .to_string()
+            .chars()
+            .map(|digit| SUPERSCRIPTS[digit.to_digit(10).unwrap_or(0) as usize])
+            .collect();
+        if exponent < 0 {
+            power.insert(0, '⁻');
+        }
+        format!("{} × 10{}", mantissa, power)
+    }
+}
+
+/// Writes values under a millionth or from a quadrillion in scientific notation
+impl Default for Scientific {
+    fn default() -> Scientific {
+        Scientific::new(1e-6, 1e15)
+    }
+}
+
+impl fmt::Display for Scientific {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:e}:{:e}", self.small, self.large)
+    }
+}
+
+/// Words a metric's changes are described with, e.g. rose/fell or improved/declined
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Vocabulary {
+    up: String,
+    down: String,
+}
+
+impl Vocabulary {
+    pub fn new(up: &str, down: &str) -> Vocabulary {
+        Vocabulary {
+            up: up.to_string(),
+            down: down.to_string(),
+        }
+    }
+
+    /// Parses a pair of words written as `<up>/<down>`, e.g. `rose/fell`
+    pub fn parse(spec: &str) -> Option<Vocabulary> {
+        let (up, down) = spec.split_once('/')?;
+        let (up, down) = (up.trim(), down.trim());
+        (!up.is_empty() && !down.is_empty()).then(|| Vocabulary::new(up, down))
+    }
+
+    /// The word for a change, up when it is positive and down otherwise
+    pub fn word(&self, change: f64) -> &str {
+        if change > 0.0 {
+            &self.up
+        } else {
+            &self.down
+        }
+    }
+}
+
+impl Default for Vocabulary {
+    fn default() -> Vocabulary {
+        Vocabulary::new("up", "down")
+    }
+}
+
+impl fmt::Display for Vocabulary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.up, self.down)
+    }
+}
+
+/// How a change too small to matter is described, e.g. "unchanged" or "broadly steady"
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Steady {
+    word: String,
+    threshold: f64,
+}
+
+impl Steady {
+    /// Changes whose size is no more than threshold, a proportion, are described as word
+    pub fn new(word: &str, threshold: f64) -> Steady {
+        Steady {
+            word: word.to_string(),
+            threshold: threshold.abs(),
+        }
+    }
+
+    /// Parses a word and threshold written as `<word>:<threshold>`, e.g. `flat:0.005`
+    pub fn parse(spec: &str) -> Option<Steady> {
+        let (word, threshold) = spec.rsplit_once(':')?;
+        let word = word.trim();
+        let threshold: f64 = threshold.trim().parse().ok()?;
+        (!word.is_empty() && threshold.is_finite()).then(|| Steady::new(word, threshold))
+    }
+
+    pub fn word(&self) -> &str {
+        &self.word
+    }
+
+    /// Whether change is small enough to be described as steady
+    pub fn holds(&self, change: f64) -> bool {
+        change.abs() <= self.threshold
+    }
+}
+
+/// "unchanged" for changes of up to 0.05%. Metrics without a steady threshold of their own
+/// only use its word, and are unchanged when their change would be written as 0%
+impl Default for Steady {
+    fn default() -> Steady {
+        Steady::new("unchanged", 0.0005)
+    }
+}
+
+impl fmt::Display for Steady {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.word, self.threshold)
+    }
+}
+
+/// How dates are written in prose, to match house style
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DateStyle {
+    /// e.g. 4 Feb 2022
+    #[default]
+    Abbreviated,
+    /// e.g. 4 February 2022
+    Long,
+    /// e.g. Feb 4th, without the year
+    Ordinal,
+    /// ISO 8601, e.g. 2022-02-04
+    Iso,
+}
+
+impl DateStyle {
+    /// Parses a style written as `abbreviated`, `long`, `ordinal` or `iso`
+    pub fn parse(spec: &str) -> Option<DateStyle> {
+        match spec {
+            "abbreviated" => Some(DateStyle::Abbreviated),
+            "long" => Some(DateStyle::Long),
+            "ordinal" => Some(DateStyle::Ordinal),
+            "iso" => Some(DateStyle::Iso),
+            _ => None,
+        }
+    }
+
+    /// Writes date in full
+    pub fn date(&self, date: &NaiveDate) -> String {
+        match self {
+            DateStyle::Abbreviated => date.format("%-d %b %Y").to_string(),
+            DateStyle::Long => date.format("%-d %B %Y").to_string(),
+            DateStyle::Ordinal => self.day(date),
+            DateStyle::Iso => date.format("%F").to_string(),
+        }
+    }
+
+    /// Writes date without its year where the style allows, for when the year is clear
+    pub fn day(&self, date: &NaiveDate) -> String {
+        match self {
+            DateStyle::Abbreviated => date.format("%-d %b").to_string(),
+            DateStyle::Long => date.format("%-d %B").to_string(),
+            DateStyle::Ordinal => format!("{} {}", date.format("%b"), ordinal(date.day())),
+            DateStyle::Iso => self.date(date),
+        }
+    }
+}
+
+impl fmt::Display for DateStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DateStyle::Abbreviated => write!(f, "abbreviated"),
+            DateStyle::Long => write!(f, "long"),
+            DateStyle::Ordinal => write!(f, "ordinal"),
+            DateStyle::Iso => write!(f, "iso"),
+        }
+    }
+}
+
+/// Symbols put before figures to show their direction at a glance, e.g. in Slack
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Indicators {
+    /// ▲, ▼ and ●
+    Shapes,
+    /// 📈, 📉 and ➖
+    Charts,
+}
+
+impl Indicators {
+    /// Parses indicators written as `indicators:shapes` or `indicators:charts`
+    pub fn parse(spec: &str) -> Option<Indicators> {
+        match spec {
+            "indicators:shapes" => Some(Indicators::Shapes),
+            "indicators:charts" => Some(Indicators::Charts),
+            _ => None,
+        }
+    }
+
+    /// The symbol for a direction, the neutral one when there is none
+    pub fn symbol(&self, direction: Option<Ordering>) -> &'static str {
+        match (self, direction) {
+            (Indicators::Shapes, Some(Ordering::Greater)) => "▲",
+            (Indicators::Shapes, Some(Ordering::Less)) => "▼",
+            (Indicators::Shapes, _) => "●",
+            (Indicators::Charts, Some(Ordering::Greater)) => "📈",
+            (Indicators::Charts, Some(Ordering::Less)) => "📉",
+            (Indicators::Charts, _) => "➖",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn html_marks_up_figure_data() {
+        let html = RenderContext::Html;
+        assert_eq!(
+            html.figure(
+                "Sales & returns were {} today",
+                "Sales & returns were up 5% today",
+                "delta up"
+            ),
+            "Sales &amp; returns were <span class=\"delta up\">up 5%</span> today"
+        );
+        assert_eq!(
+            html.figure("Purrs were {}", "Purrs <3", "figure"),
+            "Purrs &lt;3"
+        );
+        assert_eq!(
+            RenderContext::Words.figure("Purrs were {}", "Purrs were up 5%", "figure"),
+            "Purrs were up 5%"
+        );
+    }
+
+    #[test]
+    fn latex_escapes_special_characters() {
+        let latex = RenderContext::Latex;
+        assert_eq!(
+            latex.escape("R&D costs_2022 were 5% of $1m ~ {a}^b \\"),
+            "R\\&D costs\\_2022 were 5\\% of \\$1m \\textasciitilde{} \\{a\\}\\textasciicircum{}b \\textbackslash{}"
+        );
+        assert_eq!(
+            latex.figure("Purrs were {}", "Purrs were up 5%", "delta up"),
+            "Purrs were \\textbf{up 5\\%}"
+        );
+    }
+
+    #[test]
+    fn ansi_colours_deltas() {
+        let ansi = RenderContext::Ansi;
+        assert_eq!(
+            ansi.figure("Purrs were {}", "Purrs were up 5%", "delta up"),
+            "Purrs were \x1b[32mup 5%\x1b[0m"
+        );
+        assert_eq!(ansi.value("up 5%", "delta up bad"), "\x1b[31mup 5%\x1b[0m");
+        assert_eq!(ansi.value("42", "figure"), "\x1b[1m42\x1b[0m");
+        assert_eq!(
+            ansi.heading("Top highlights"),
+            "\x1b[1mTop highlights\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn steady_changes() {
+        let steady = Steady::parse("broadly steady: 0.01").unwrap();
+        assert_eq!(steady.word(), "broadly steady");
+        assert!(steady.holds(-0.008));
+        assert!(!steady.holds(0.02));
+        assert_eq!(Steady::parse(&steady.to_string()), Some(steady));
+        assert_eq!(Steady::parse("flat"), None);
+        assert!(Steady::default().holds(0.0004));
+    }
+
+    #[test]
+    fn date_styles() {
+        let date = NaiveDate::from_ymd_opt(2022, 2, 4).unwrap();
+        assert_eq!(DateStyle::default().date(&date), "4 Feb 2022");
+        assert_eq!(DateStyle::Long.date(&date), "4 February 2022");
+        assert_eq!(DateStyle::Ordinal.date(&date), "Feb 4th");
+        assert_eq!(DateStyle::Iso.day(&date), "2022-02-04");
+        assert_eq!(
+            DateStyle::parse(&DateStyle::Long.to_string()),
+            Some(DateStyle::Long)
+        );
+        assert_eq!(DateStyle::parse("fancy"), None);
+    }
+
+    #[test]
+    fn significant_figures() {
+        let precision = Precision::parse("sigfigs:3").unwrap();
+        assert_eq!(precision.format(0.000123456), "0.000123");
+        assert_eq!(precision.format(1234567.0), "1,230,000");
+        assert_eq!(precision.format(42.0), "42.0");
+        assert_eq!(Precision::parse("decimals:2"), Some(Precision::Decimals(2)));
+        assert_eq!(Precision::parse("sigfigs:0"), None);
+        assert_eq!(Precision::parse(&precision.to_string()), Some(precision));
+    }
+
+    #[test]
+    fn rounding_modes() {
+        assert_eq!(Rounding::HalfUp.round(2.25, 1), 2.3);
+        assert_eq!(Rounding::HalfEven.round(2.25, 1), 2.2);
+        assert_eq!(Rounding::HalfEven.round(2.35, 1), 2.4);
+        assert_eq!(Rounding::Floor.round(2.29, 1), 2.2);
+        assert_eq!(Rounding::HalfUp.round(2.675, 2), 2.68);
+        assert_eq!(Rounding::HalfUp.round(-2.5, 0), -3.0);
+        assert_eq!(
+            Rounding::parse(&Rounding::Floor.to_string()),
+            Some(Rounding::Floor)
+        );
+    }
+
+    #[test]
+    fn scientific_notation() {
+        let scientific = Scientific::default();
+        assert!(!scientific.applies(0.0));
+        assert!(!scientific.applies(1234.5));
+        assert_eq!(scientific.format(0.00000032), "3.2 × 10⁻⁷");
+        assert_eq!(scientific.format(-6.02214e23), "-6.02 × 10²³");
+        assert_eq!(scientific.format(9.999e17), "1 × 10¹⁸");
+
+        let bounds = Scientific::parse("1e-3:1e9").unwrap();
+        assert!(bounds.applies(2.5e9));
+        assert_eq!(Scientific::parse(&bounds.to_string()), Some(bounds));
+        assert_eq!(Scientific::parse("1e9:1e-3"), None);
+    }
+
+    #[test]
+    fn vocabulary_pairs() {
+        let vocabulary = Vocabulary::parse("rose / fell").unwrap();
+        assert_eq!(vocabulary.word(0.2), "rose");
+        assert_eq!(vocabulary.word(-0.2), "fell");
+        assert_eq!(Vocabulary::parse(&vocabulary.to_string()), Some(vocabulary));
+        assert_eq!(Vocabulary::parse("rose"), None);
+        assert_eq!(Vocabulary::default().word(0.0), "down");
+    }
+}
@@ -0,0 +1,640 @@
+use std::fmt;
+
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    dates::{self, MonthEnd},
+    DateError, HolidayCalendar, TimeFrequency,
+};
+
+/// One or more whole periods of a frequency, inclusive of both start and end dates
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeSpan {
+    start: NaiveDate,
+    end: NaiveDate,
+    frequency: TimeFrequency,
+    periods: u32,
+    to_date: bool,
+    week_start: Weekday,
+}
+
+impl TimeSpan {
+    /// The single period of frequency which contains date, with weeks starting on Monday
+    pub fn new(date: &NaiveDate, frequency: TimeFrequency) -> Result<TimeSpan, DateError> {
+        TimeSpan::trailing(1, frequency, date, Weekday::Mon)
+    }
+
+    /// The single period of frequency which contains date, with weeks starting on week_start
+    pub fn anchored(
+        date: &NaiveDate,
+        frequency: TimeFrequency,
+        week_start: Weekday,
+    ) -> Result<TimeSpan, DateError> {
+        TimeSpan::trailing(1, frequency, date, week_start)
+    }
+
+    /// The rolling window of periods ending with the period which contains date, with
+    /// weeks starting on week_start
+    pub fn trailing(
+        periods: u32,
+        frequency: TimeFrequency,
+        date: &NaiveDate,
+        week_start: Weekday,
+    ) -> Result<TimeSpan, DateError> {
+        if periods == 0 {
+            return Err(DateError::EmptySpan);
+        }
+        let last = period_start(date, frequency, week_start)?;
+        Ok(TimeSpan {
+            start: step(&last, frequency, 1 - periods as i32)?,
+            end: period_end(&last, frequency)?,
+            frequency,
+            periods,
+            to_date: false,
+            week_start,
+        })
+    }
+
+    /// The period of frequency which contains date, cut short at date, with weeks starting
+    /// on week_start
+    pub fn to_date(
+        date: &NaiveDate,
+        frequency: TimeFrequency,
+        week_start: Weekday,
+    ) -> Result<TimeSpan, DateError> {
+        Ok(TimeSpan {
+            start: period_start(date, frequency, week_start)?,
+            end: *date,
+            frequency,
+            periods: 1,
+            to_date: true,
+            week_start,
+        })
+    }
+
+    /// Parses a period-to-date span written as `wtd`, `mtd`, `qtd` or `ytd`
+    pub fn parse_to_date(
+        spec: &str,
+        date: &NaiveDate,
+        week_start: Weekday,
+    ) -> Result<TimeSpan, DateError> {
+        let frequency = match spec {
+            "wtd" => TimeFrequency::Weekly,
+            "mtd" => TimeFrequency::Monthly,
+            "qtd" => TimeFrequency::Quarterly,
+            "ytd" => TimeFrequency::Yearly,
+            _ => return Err(DateError::Parse(spec.to_string())),
+        };
+        TimeSpan::to_date(date, frequency, week_start)
+    }
+
+    /// Parses a rolling window written as `trailing:<n><y|q|m|w|d>`, e.g. `trailing:12w`
+    pub fn parse_trailing(
+        spec: &str,
+        date: &NaiveDate,
+        week_start: Weekday,
+    ) -> Result<TimeSpan, DateError> {
+        let invalid = || DateError::Parse(spec.to_string());
+        let window = spec.strip_prefix("trailing:").ok_or_else(invalid)?;
+        let unit = window.chars().last().ok_or_else(invalid)?;
+        let periods = window[..window.len() - unit.len_utf8()]
+            .parse()
+            .map_err(|_| invalid())?;
+        let frequency = match unit {
+            'y' => TimeFrequency::Yearly,
+            'q' => TimeFrequency::Quarterly,
+            'm' => TimeFrequency::Monthly,
+            'w' => TimeFrequency::Weekly,
+            'd' => TimeFrequency::Daily,
+            _ => return Err(invalid()),
+        };
+        TimeSpan::trailing(periods, frequency, date, week_start)
+    }
+
+    pub fn start(&self) -> NaiveDate {
+        self.start
+    }
+
+    pub fn end(&self) -> NaiveDate {
+        self.end
+    }
+
+    pub fn frequency(&self) -> TimeFrequency {
+        self.frequency
+    }
+
+    pub fn periods(&self) -> u32 {
+        self.periods
+    }
+
+    /// The day weekly periods within the span begin on
+    pub fn week_start(&self) -> Weekday {
+        self.week_start
+    }
+
+    /// Whether the span stops part way through its last period
+    pub fn is_to_date(&self) -> bool {
+        self.to_date
+    }
+
+    /// Exact number of calendar days in the span, counting both ends
+    pub fn days(&self) -> i64 {
+        (self.end - self.start).num_days() + 1
+    }
+
+    /// Number of periods of frequency the span covers, counting partial periods as
+    /// the fraction of their actual calendar days which fall within the span
+    pub fn count(&self, frequency: TimeFrequency) -> Result<f64, DateError> {
+        let mut period = TimeSpan::anchored(&self.start, frequency, self.week_start)?;
+        let mut count = 0.0;
+        while period.start <= self.end {
+            let overlap = (period.end.min(self.end) - period.start.max(self.start)).num_days() + 1;
+            count += overlap as f64 / period.days() as f64;
+            period = period.next()?;
+        }
+        Ok(count)
+    }
+
+    pub fn contains(&self, date: &NaiveDate) -> bool {
+        self.start <= *date && *date <= self.end
+    }
+
+    pub fn intersects(&self, other: &TimeSpan) -> bool {
+        self.start <= other.end && other.start <= self.end
+    }
+
+    /// Moves the span by count spans of the same length, backwards when count is negative
+    /// Period-to-date spans are trimmed to the same number of elapsed days into their last period
+    pub fn shift(&self, count: i32) -> Result<TimeSpan, DateError> {
+        self.offset(count * self.periods as i32)
+    }
+
+    /// Whether every day of the span is on or before report_date
+    pub fn is_complete(&self, report_date: &NaiveDate) -> bool {
+        self.end <= *report_date
+    }
+
+    /// The span to read data over when reporting on report_date under policy, along with
+    /// the factor values read over it should be scaled by
+    pub fn with_policy(
+        &self,
+        policy: PartialPolicy,
+        report_date: &NaiveDate,
+    ) -> Result<(TimeSpan, f64), DateError> {
+        if self.is_complete(report_date) {
+            return Ok((*self, 1.0));
+        }
+        if policy == PartialPolicy::Exclude {
+            let mut span = self.prev()?;
+            while !span.is_complete(report_date) {
+                span = span.prev()?;
+            }
+            return Ok((span, 1.0));
+        }
+        if *report_date < self.start {
+            return Err(DateError::EmptySpan);
+        }
+        let partial = TimeSpan {
+            end: *report_date,
+            to_date: true,
+            ..*self
+        };
+        match policy {
+            PartialPolicy::Extrapolate => Ok((partial, self.days() as f64 / partial.days() as f64)),
+            _ => Ok((partial, 1.0)),
+        }
+    }
+
+    /// The span this one is compared against under comparison
+    pub fn compare_to(&self, comparison: Comparison) -> Result<TimeSpan, DateError> {
+        match comparison {
+            Comparison::Previous => self.prev(),
+            Comparison::YearOnYear => self.years_earlier(1),
+        }
+    }
+
+    /// The equivalent span a number of years earlier, in whole weeks for weekly and daily
+    /// spans so that weekdays line up
+    pub fn years_earlier(&self, years: u32) -> Result<TimeSpan, DateError> {
+        self.offset(-self.frequency.periods_per_year() * years as i32)
+    }
+
+    /// Moves the span by a number of periods of its frequency
+    fn offset(&self, periods: i32) -> Result<TimeSpan, DateError> {
+        let start = step(&self.start, self.frequency, periods)?;
+        let last = step(&start, self.frequency, self.periods as i32 - 1)?;
+        let mut end = period_end(&last, self.frequency)?;
+        if self.to_date {
+            let elapsed = self.end - period_start(&self.end, self.frequency, self.week_start)?;
+            end = end.min(
+                last.checked_add_signed(elapsed)
+                    .ok_or(DateError::OutOfRange)?,
+            );
+        }
+        Ok(TimeSpan {
+            start,
+            end,
+            ..*self
+        })
+    }
+
+    /// The span of the same length immediately before this one
+    pub fn prev(&self) -> Result<TimeSpan, DateError> {
+        self.shift(-1)
+    }
+
+    /// The span of the same length immediately after this one
+    pub fn next(&self) -> Result<TimeSpan, DateError> {
+        self.shift(1)
+    }
+
+    /// Extends the span forward, period by period, until it covers date
+    pub fn through(&self, date: &NaiveDate) -> Result<TimeSpan, DateError> {
+        let mut span = *self;
+        while span.end < *date {
+            span.periods += 1;
+            span.end = period_end(
+                &step(&span.start, span.frequency, span.periods as i32 - 1)?,
+                span.frequency,
+            )?;
+        }
+        Ok(span)
+    }
+
+    /// The smallest span of whole periods of frequency which covers this span
+    pub fn expand(&self, frequency: TimeFrequency) -> Result<TimeSpan, DateError> {
+        let start = period_start(&self.start, frequency, self.week_start)?;
+        let last = period_start(&self.end, frequency, self.week_start)?;
+        let mut periods = 1;
+        while step(&start, frequency, periods as i32)? <= last {
+            periods += 1;
+        }
+        Ok(TimeSpan {
+            start,
+            end: period_end(&last, frequency)?,
+            frequency,
+            periods,
+            to_date: false,
+            week_start: self.week_start,
+        })
+    }
+
+    /// Renders the span in the given format
+    pub fn display(&self, format: SpanFormat) -> String {
+        match format {
+            SpanFormat::DateRange if self.start == self.end => {
+                self.start.format("%-d %b %Y").to_string()
+            }
+            SpanFormat::DateRange => format!(
+                "{} to {}",
+                self.start.format("%-d %b %Y"),
+                self.end.format("%-d %b %Y")
+            ),
+            SpanFormat::IsoWeek => {
+                let (first, last) = (self.start.iso_week(), self.end.iso_week());
+                if first == last {
+                    format!("W{:02} {}", first.week(), first.year())
+                } else {
+                    format!(
+                        "W{:02} {} to W{:02} {}",
+                        first.week(),
+                        first.year(),
+                        last.week(),
+                        last.year()
+                    )
+                }
+            }
+        }
+    }
+
+    /// Length of the span in business days, excluding weekends and the calendar's holidays
+    pub fn business_days(&self, calendar: &HolidayCalendar) -> u32 {
+        calendar.business_days(self)
+    }
+}
+
+impl fmt::Display for TimeSpan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.display(SpanFormat::DateRange))
+    }
+}
+
+/// How a span is written out in headers and narrative
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SpanFormat {
+    /// First and last dates, e.g. "31 Jan 2022 to 6 Feb 2022"
+    #[default]
+    DateRange,
+    /// ISO 8601 week numbers, e.g. "W05 2022"
+    IsoWeek,
+}
+
+/// How a span which hasn't finished by the report date is treated
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartialPolicy {
+    /// Falls back to the most recent complete span
+    Exclude,
+    /// Uses the span up to the report date as is
+    Include,
+    /// Uses the span up to the report date, scaled up to the full span's length
+    Extrapolate,
+}
+
+impl PartialPolicy {
+    /// Parses a policy written as `exclude_partial`, `include_partial` or `extrapolate`
+    pub fn parse(spec: &str) -> Result<PartialPolicy, DateError> {
+        match spec {
+            "exclude_partial" => Ok(PartialPolicy::Exclude),
+            "include_partial" => Ok(PartialPolicy::Include),
+            "extrapolate" => Ok(PartialPolicy::Extrapolate),
+            _ => Err(DateError::Parse(spec.to_string())),
+        }
+    }
+}
+
+/// Which earlier span a figure's current span is compared with
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    /// The span of the same length immediately before
+    Previous,
+    /// The equivalent span one year earlier, 52 weeks earlier for weekly and daily spans so
+    /// that weekdays line up
+    YearOnYear,
+}
+
+impl Comparison {
+    /// Parses a comparison written as `compare:prev` or `compare:yoy`, or as just `prev` or
+    /// `yoy`
+    pub fn parse(spec: &str) -> Result<Comparison, DateError> {
+        match spec.strip_prefix("compare:").unwrap_or(spec) {
+            "prev" => Ok(Comparison::Previous),
+            "yoy" => Ok(Comparison::YearOnYear),
+            _ => Err(DateError::Parse(spec.to_string())),
+        }
+    }
+}
+
+impl TimeFrequency {
+    fn periods_per_year(&self) -> i32 {
+        match self {
+            TimeFrequency::Yearly => 1,
+            TimeFrequency::Quarterly => 4,
+            TimeFrequency::Monthly => 12,
+            TimeFrequency::Weekly => 52,
+            TimeFrequency::Daily => 364,
+        }
+    }
+}
+
+/// First day of the period of frequency which contains date
+fn period_start(
+    date: &NaiveDate,
+    frequency: TimeFrequency,
+    week_start: Weekday,
+) -> Result<NaiveDate, DateError> {
+    match frequency {
+        TimeFrequency::Yearly => date.with_ordinal(1),
+        TimeFrequency::Quarterly => {
+            NaiveDate::from_ymd_opt(date.year(), date.month0() / 3 * 3 + 1, 1)
+        }
+        TimeFrequency::Monthly => date.with_day(1),
+        TimeFrequency::Weekly => {
+            let offset = date.weekday().days_since(week_start);
+            date.checked_sub_signed(Duration::days(offset as i64))
+        }
+        TimeFrequency::Daily => Some(*date),
+    }
+    .ok_or(DateError::OutOfRange)
+}
+
+/// Last day of the period of frequency which starts on start
+fn period_end(start: &NaiveDate, frequency: TimeFrequency) -> Result<NaiveDate, DateError> {
+    step(start, frequency, 1)?
+        .pred_opt()
+        .ok_or(DateError::OutOfRange)
+}
+
+/// Moves a period start forward (or backward when negative) by count periods
+fn step(start: &NaiveDate, frequency: TimeFrequency, count: i32) -> Result<NaiveDate, DateError> {
+    match frequency {
+        TimeFrequency::Yearly => dates::add_years(start, count, MonthEnd::Clamp),
+        TimeFrequency::Quarterly => dates::add_quarters(start, count, MonthEnd::Clamp),
+        TimeFrequency::Monthly => dates::add_months(start, count, MonthEnd::Clamp),
+        TimeFrequency::Weekly => start
+            .checked_add_signed(Duration::weeks(count as i64))
+            .ok_or(DateError::OutOfRange),
+        TimeFrequency::Daily => start
+            .checked_add_signed(Duration::days(count as i64))
+            .ok_or(DateError::OutOfRange),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn trailing_spans() {
+        let date = NaiveDate::from_ymd_opt(2022, 2, 4).unwrap();
+
+        let month = TimeSpan::new(&date, TimeFrequency::Monthly).unwrap();
+        assert_eq!(month.start(), NaiveDate::from_ymd_opt(2022, 2, 1).unwrap());
+        assert_eq!(month.end(), NaiveDate::from_ymd_opt(2022, 2, 28).unwrap());
+
+        let weeks = TimeSpan::parse_trailing("trailing:12w", &date, Weekday::Mon).unwrap();
+        assert_eq!(weeks.periods(), 12);
+        assert_eq!(
+            weeks.start(),
+            NaiveDate::from_ymd_opt(2021, 11, 15).unwrap()
+        );
+        assert_eq!(weeks.end(), NaiveDate::from_ymd_opt(2022, 2, 6).unwrap());
+
+        let months = TimeSpan::trailing(3, TimeFrequency::Monthly, &date, Weekday::Mon).unwrap();
+        assert_eq!(
+            months.start(),
+            NaiveDate::from_ymd_opt(2021, 12, 1).unwrap()
+        );
+        assert!(months.contains(&date));
+
+        assert_eq!(
+            TimeSpan::parse_trailing("trailing:0w", &date, Weekday::Mon),
+            Err(DateError::EmptySpan)
+        );
+        assert!(TimeSpan::parse_trailing("trailing:12x", &date, Weekday::Mon).is_err());
+        assert_eq!(
+            TimeSpan::new(&NaiveDate::MAX, TimeFrequency::Yearly),
+            Err(DateError::OutOfRange)
+        );
+    }
+
+    #[test]
+    fn span_arithmetic() {
+        let date = NaiveDate::from_ymd_opt(2022, 2, 4).unwrap();
+        let months = TimeSpan::trailing(2, TimeFrequency::Monthly, &date, Weekday::Mon).unwrap();
+
+        let prev = months.prev().unwrap();
+        assert_eq!(prev.start(), NaiveDate::from_ymd_opt(2021, 11, 1).unwrap());
+        assert_eq!(prev.end(), NaiveDate::from_ymd_opt(2021, 12, 31).unwrap());
+        assert_eq!(prev.next(), Ok(months));
+        assert_eq!(months.shift(-6), prev.shift(-5));
+        assert!(!months.intersects(&prev));
+        assert!(months.intersects(&TimeSpan::new(&date, TimeFrequency::Weekly).unwrap()));
+
+        let week = TimeSpan::new(
+            &NaiveDate::from_ymd_opt(2022, 1, 31).unwrap(),
+            TimeFrequency::Weekly,
+        )
+        .unwrap();
+        let expanded = week.expand(TimeFrequency::Monthly).unwrap();
+        assert_eq!(expanded.periods(), 2);
+        assert_eq!(expanded, months);
+    }
+
+    #[test]
+    fn period_to_date_spans() {
+        let date = NaiveDate::from_ymd_opt(2022, 5, 17).unwrap();
+
+        let ytd = TimeSpan::parse_to_date("ytd", &date, Weekday::Mon).unwrap();
+        assert_eq!(ytd.start(), NaiveDate::from_ymd_opt(2022, 1, 1).unwrap());
+        assert_eq!(ytd.end(), date);
+        let last_year = ytd.prev().unwrap();
+        assert_eq!(
+            last_year.start(),
+            NaiveDate::from_ymd_opt(2021, 1, 1).unwrap()
+        );
+        assert_eq!(
+            last_year.end(),
+            NaiveDate::from_ymd_opt(2021, 5, 17).unwrap()
+        );
+
+        let qtd = TimeSpan::parse_to_date("qtd", &date, Weekday::Mon).unwrap();
+        assert_eq!(qtd.start(), NaiveDate::from_ymd_opt(2022, 4, 1).unwrap());
+        let prev = qtd.prev().unwrap();
+        assert_eq!(prev.start(), NaiveDate::from_ymd_opt(2022, 1, 1).unwrap());
+        assert_eq!(prev.end(), NaiveDate::from_ymd_opt(2022, 2, 16).unwrap());
+
+        let mtd = TimeSpan::to_date(
+            &NaiveDate::from_ymd_opt(2022, 3, 31).unwrap(),
+            TimeFrequency::Monthly,
+            Weekday::Mon,
+        )
+        .unwrap();
+        assert_eq!(
+            mtd.prev().unwrap().end(),
+            NaiveDate::from_ymd_opt(2022, 2, 28).unwrap()
+        );
+    }
+
+    #[test]
+    fn anchored_weeks() {
+        let date = NaiveDate::from_ymd_opt(2022, 2, 4).unwrap();
+
+        let week = TimeSpan::anchored(&date, TimeFrequency::Weekly, Weekday::Wed).unwrap();
+        assert_eq!(week.start(), NaiveDate::from_ymd_opt(2022, 2, 2).unwrap());
+        assert_eq!(week.end(), NaiveDate::from_ymd_opt(2022, 2, 8).unwrap());
+        assert_eq!(
+            week.prev().unwrap().start(),
+            NaiveDate::from_ymd_opt(2022, 1, 26).unwrap()
+        );
+
+        let week = TimeSpan::anchored(&date, TimeFrequency::Weekly, Weekday::Sun).unwrap();
+        assert_eq!(week.start(), NaiveDate::from_ymd_opt(2022, 1, 30).unwrap());
+
+        let wtd = TimeSpan::parse_to_date("wtd", &date, Weekday::Sun).unwrap();
+        assert_eq!(wtd.start(), NaiveDate::from_ymd_opt(2022, 1, 30).unwrap());
+        assert_eq!(wtd.end(), date);
+
+        let weeks = TimeSpan::parse_trailing("trailing:2w", &date, Weekday::Wed).unwrap();
+        assert_eq!(weeks.start(), NaiveDate::from_ymd_opt(2022, 1, 26).unwrap());
+        assert_eq!(weeks.end(), NaiveDate::from_ymd_opt(2022, 2, 8).unwrap());
+    }
+
+    #[test]
+    fn exact_period_counts() {
+        let leap_feb = TimeSpan::new(
+            &NaiveDate::from_ymd_opt(2024, 2, 10).unwrap(),
+            TimeFrequency::Monthly,
+        )
+        .unwrap();
+        assert_eq!(leap_feb.days(), 29);
+        assert_eq!(leap_feb.count(TimeFrequency::Monthly), Ok(1.0));
+
+        let quarter = leap_feb.expand(TimeFrequency::Quarterly).unwrap();
+        assert_eq!(quarter.days(), 91);
+        assert_eq!(quarter.count(TimeFrequency::Monthly), Ok(3.0));
+        assert_eq!(quarter.count(TimeFrequency::Daily), Ok(91.0));
+        assert_eq!(leap_feb.count(TimeFrequency::Quarterly), Ok(29.0 / 91.0));
+    }
+
+    #[test]
+    fn year_on_year_comparison() {
+        let date = NaiveDate::from_ymd_opt(2022, 2, 4).unwrap();
+        let yoy = Comparison::parse("compare:yoy").unwrap();
+
+        let months = TimeSpan::trailing(3, TimeFrequency::Monthly, &date, Weekday::Mon).unwrap();
+        let last_year = months.compare_to(yoy).unwrap();
+        assert_eq!(
+            last_year.start(),
+            NaiveDate::from_ymd_opt(2020, 12, 1).unwrap()
+        );
+        assert_eq!(
+            last_year.end(),
+            NaiveDate::from_ymd_opt(2021, 2, 28).unwrap()
+        );
+        assert_eq!(months.compare_to(Comparison::Previous), months.prev());
+        assert_eq!(Comparison::parse("yoy"), Ok(yoy));
+        assert!(Comparison::parse("compare:mom").is_err());
+
+        let week = TimeSpan::new(&date, TimeFrequency::Weekly).unwrap();
+        let last_year = week.compare_to(yoy).unwrap();
+        assert_eq!(
+            last_year.start(),
+            NaiveDate::from_ymd_opt(2021, 2, 1).unwrap()
+        );
+        assert_eq!(last_year.start().weekday(), week.start().weekday());
+    }
+
+    #[test]
+    fn partial_period_policies() {
+        let report_date = NaiveDate::from_ymd_opt(2022, 2, 7).unwrap();
+        let month = TimeSpan::new(&report_date, TimeFrequency::Monthly).unwrap();
+        assert!(!month.is_complete(&report_date));
+
+        let (span, factor) = month
+            .with_policy(
+                PartialPolicy::parse("exclude_partial").unwrap(),
+                &report_date,
+            )
+            .unwrap();
+        assert_eq!(span, month.prev().unwrap());
+        assert_eq!(factor, 1.0);
+
+        let (span, factor) = month
+            .with_policy(PartialPolicy::Include, &report_date)
+            .unwrap();
+        assert_eq!(span.end(), report_date);
+        assert_eq!(factor, 1.0);
+
+        let (span, factor) = month
+            .with_policy(PartialPolicy::Extrapolate, &report_date)
+            .unwrap();
+        assert_eq!(span.days(), 7);
+        assert_eq!(factor, 4.0);
+    }
+
+    #[test]
+    fn span_display() {
+        let date = NaiveDate::from_ymd_opt(2022, 2, 4).unwrap();
+        let week = TimeSpan::new(&date, TimeFrequency::Weekly).unwrap();
+        assert_eq!(week.to_string(), "31 Jan 2022 to 6 Feb 2022");
+        assert_eq!(week.display(SpanFormat::IsoWeek), "W05 2022");
+
+        let weeks = TimeSpan::trailing(6, TimeFrequency::Weekly, &date, Weekday::Mon).unwrap();
+        assert_eq!(weeks.display(SpanFormat::IsoWeek), "W52 2021 to W05 2022");
+
+        let day = TimeSpan::new(&date, TimeFrequency::Daily).unwrap();
+        assert_eq!(day.to_string(), "4 Feb 2022");
+    }
+}
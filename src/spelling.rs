@@ -0,0 +1,124 @@
+/// Writes numbers as words, for editorial styles which spell out small numbers.
+/// Implement it to spell numbers in another language
+pub trait NumberWords {
+    /// The words for n, e.g. "twenty-five"
+    fn cardinal(&self, n: u64) -> String;
+
+    /// The word put before a negative number, e.g. "minus"
+    fn minus(&self) -> &str;
+
+    /// The words put after a percentage, e.g. "per cent"
+    fn percent(&self) -> &str;
+
+    /// The words for value when it is whole, None otherwise
+    fn spell(&self, value: f64) -> Option<String> {
+        if value.fract() != 0.0 || !value.is_finite() || value.abs() >= u64::MAX as f64 {
+            return None;
+        }
+        let words = self.cardinal(value.abs() as u64);
+        Some(if value < 0.0 {
+            format!("{} {}", self.minus(), words)
+        } else {
+            words
+        })
+    }
+}
+
+/// English number words, e.g. "one hundred and five", "twenty-five per cent"
+#[derive(Debug, Clone, Copy, Default)]
+pub struct English;
+
+impl NumberWords for English {
+    fn cardinal(&self, n: u64) -> String {
+        const UNITS: [&str; 20] = [
+            "zero",
+            "one",
+            "two",
+            "three",
+            "four",
+            "five",
+            "six",
+            "seven",
+            "eight",
+            "nine",
+            "ten",
+            "eleven",
+            "twelve",
+            "thirteen",
+            "fourteen",
+            "fifteen",
+            "sixteen",
+            "seventeen",
+            "eighteen",
+            "nineteen",
+        ];
+        const TENS: [&str; 10] = [
+            "", "", "twenty", "thirty", "forty", "fifty", "sixty", "seventy", "eighty", "ninety",
+        ];
+        const SCALES: [(u64, &str); 4] = [
+            (1_000_000_000_000, "trillion"),
+            (1_000_000_000, "billion"),
+            (1_000_000, "million"),
+            (1_000, "thousand"),
+        ];
+        match n {
+            0..=19 => UNITS[n as usize].to_string(),
+            20..=99 => match n % 10 {
+                0 => TENS[n as usize / 10].to_string(),
+                units => format!("{}-{}", TENS[n as usize / 10], UNITS[units as usize]),
+            },
+            100..=999 => match n % 100 {
+                0 => format!("{} hundred", UNITS[n as usize / 100]),
+                rest => format!(
+                    "{} hundred and {}",
+                    UNITS[n as usize / 100],
+                    self.cardinal(rest)
+                ),
+            },
+            _ => {
+                let (scale, name) = SCALES
+                    .iter()
+                    .find(|(scale, _)| n >= *scale)
+                    .copied()
+                    .unwrap_or(SCALES[3]);
+                let rest = n % scale;
+                let head = format!("{} {}", self.cardinal(n / scale), name);
+                match rest {
+                    0 => head,
+                    1..=99 => format!("{} and {}", head, self.cardinal(rest)),
+                    _ => format!("{} {}", head, self.cardinal(rest)),
+                }
+            }
+        }
+    }
+
+    fn minus(&self) -> &str {
+        "minus"
+    }
+
+    fn percent(&self) -> &str {
+        "per cent"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn english_words() {
+        assert_eq!(English.cardinal(7), "seven");
+        assert_eq!(English.cardinal(25), "twenty-five");
+        assert_eq!(English.cardinal(40), "forty");
+        assert_eq!(English.cardinal(105), "one hundred and five");
+        assert_eq!(English.cardinal(2_000), "two thousand");
+        assert_eq!(English.cardinal(2_042), "two thousand and forty-two");
+        assert_eq!(
+            English.cardinal(1_250_300),
+            "one million two hundred and fifty thousand three hundred"
+        );
+        assert_eq!(English.spell(-3.0), Some(String::from("minus three")));
+        assert_eq!(English.spell(2.5), None);
+    }
+}
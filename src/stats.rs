@@ -0,0 +1,115 @@
+/// The value below which p percent of values fall, interpolating linearly between
+/// the closest ranks. Returns None when values is empty or p is outside 0 to 100
+pub fn percentile(values: &[f64], p: f64) -> Option<f64> {
+    if values.is_empty() || !(0.0..=100.0).contains(&p) {
+        return None;
+    }
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let rank = p / 100.0 * (sorted.len() - 1) as f64;
+    let (lower, upper) = (rank.floor() as usize, rank.ceil() as usize);
+    Some(sorted[lower] + (sorted[upper] - sorted[lower]) * (rank - lower as f64))
+}
+
+pub fn median(values: &[f64]) -> Option<f64> {
+    percentile(values, 50.0)
+}
+
+pub fn mean(values: &[f64]) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    Some(values.iter().sum::<f64>() / values.len() as f64)
+}
+
+/// Sample standard deviation, which needs at least two values
+pub fn std_dev(values: &[f64]) -> Option<f64> {
+    if values.len() < 2 {
+        return None;
+    }
+    let mean = mean(values)?;
+    let squares: f64 = values.iter().map(|value| (value - mean).powi(2)).sum();
+    Some((squares / (values.len() - 1) as f64).sqrt())
+}
+
+/// Standard deviation as a proportion of the mean
+pub fn coefficient_of_variation(values: &[f64]) -> Option<f64> {
+    let mean = mean(values)?;
+    if mean == 0.0 {
+        return None;
+    }
+    Some(std_dev(values)? / mean.abs())
+}
+
+/// How many standard deviations value lies from the mean of history, which needs at least
+/// two values that aren't all equal
+pub fn z_score(value: f64, history: &[f64]) -> Option<f64> {
+    let std_dev = std_dev(history)?;
+    if std_dev == 0.0 {
+        return None;
+    }
+    Some((value - mean(history)?) / std_dev)
+}
+
+/// Least squares line through values taken at x = 0, 1, 2, ..., as (slope, intercept).
+/// Needs at least two values
+pub fn linear_fit(values: &[f64]) -> Option<(f64, f64)> {
+    if values.len() < 2 {
+        return None;
+    }
+    let n = values.len() as f64;
+    let mean_x = (n - 1.0) / 2.0;
+    let mean_y = mean(values)?;
+    let (mut covariance, mut variance) = (0.0, 0.0);
+    for (x, y) in values.iter().enumerate() {
+        covariance += (x as f64 - mean_x) * (y - mean_y);
+        variance += (x as f64 - mean_x).powi(2);
+    }
+    let slope = covariance / variance;
+    Some((slope, mean_y - slope * mean_x))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn percentiles() {
+        let values = [156.0, 90.0, 110.0, 120.0, 100.0];
+        assert_eq!(median(&values), Some(110.0));
+        assert_eq!(percentile(&values, 90.0), Some(141.6));
+        assert_eq!(percentile(&values, 0.0), Some(90.0));
+        assert_eq!(percentile(&values, 101.0), None);
+        assert_eq!(median(&[1.0, 2.0]), Some(1.5));
+        assert_eq!(median(&[]), None);
+    }
+
+    #[test]
+    fn spread() {
+        let values = [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0];
+        assert_eq!(mean(&values), Some(5.0));
+        assert_eq!(std_dev(&values), Some((32.0f64 / 7.0).sqrt()));
+        assert_eq!(
+            coefficient_of_variation(&values),
+            Some((32.0f64 / 7.0).sqrt() / 5.0)
+        );
+        assert_eq!(std_dev(&[1.0]), None);
+    }
+
+    #[test]
+    fn line_of_best_fit() {
+        assert_eq!(linear_fit(&[1.0, 3.0, 5.0]), Some((2.0, 1.0)));
+        assert_eq!(linear_fit(&[4.0, 4.0]), Some((0.0, 4.0)));
+        assert_eq!(linear_fit(&[4.0]), None);
+    }
+
+    #[test]
+    fn z_scores() {
+        let history = [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0];
+        let z = z_score(9.0, &history).unwrap();
+        assert!((z - 1.87).abs() < 0.01);
+        assert_eq!(z_score(1.0, &[3.0, 3.0]), None);
+        assert_eq!(z_score(1.0, &[3.0]), None);
+    }
+}
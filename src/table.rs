@@ -0,0 +1,3238 @@
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap},
+    fmt,
+    ops::Range,
+    sync::{Arc, Mutex},
+};
+
+use chrono::NaiveDate;
+use handlebars::{
+    to_json, BlockContext, Context, Handlebars, Helper, HelperDef, HelperResult, JsonValue, Output,
+    RenderError, Renderable, ScopedJson,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    figures::{describe_period, format_value, total_within},
+    inflection,
+    render::split_figure,
+    CommandError, Comparison, Datapoint, DateError, DateStyle, FigAvgFreq, FigChange, FigDiff,
+    FigSpark, FigVsTarget, Figure, HolidayCalendar, Metric, MetricGroup, Normalization,
+    PartialPolicy, Precision, RenderContext, Target, TimeFrequency, TimeSpan,
+};
+
+/// One value in a table: the data a figure puts into its metric's print text, with its raw
+/// value and the class it is marked up with
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Cell {
+    data: String,
+    value: Option<f64>,
+    class: String,
+    combined: Option<String>,
+    context: Option<RenderContext>,
+    current: Option<(String, f64)>,
+    previous: Option<f64>,
+    additive: bool,
+    link: Option<String>,
+    #[serde(skip)]
+    metric: Option<Metric>,
+}
+
+impl Cell {
+    pub fn new(data: &str, value: Option<f64>) -> Cell {
+        Cell {
+            data: data.to_string(),
+            value,
+            class: String::from("figure"),
+            combined: None,
+            context: None,
+            current: None,
+            previous: None,
+            additive: true,
+            link: None,
+            metric: None,
+        }
+    }
+
+    /// The cell for figure, holding just the data its Display inserts into the metric's
+    /// print text, or the whole text when it doesn't follow the print text
+    pub fn figure<F: Figure + fmt::Display>(figure: &F) -> Cell {
+        let print_text = figure.metric_info().print_text();
+        let data = |text: String| match split_figure(print_text, &text) {
+            Some((_, data, _)) => data.to_string(),
+            None => text,
+        };
+        Cell {
+            data: data(figure.to_string()),
+            value: figure.raw_value(),
+            class: figure.markup_class().to_string(),
+            combined: figure.combined(),
+            context: None,
+            current: figure
+                .compared()
+                .map(|(_, current)| (figure.metric_info().format_amount(current), current)),
+            previous: figure.compared().map(|(previous, _)| previous),
+            additive: true,
+            link: None,
+            metric: Some(figure.metric_info().clone()),
+        }
+    }
+
+    /// A cell standing in for a figure which couldn't be worked out, e.g. "—"
+    pub fn missing(placeholder: &str) -> Cell {
+        Cell {
+            data: placeholder.to_string(),
+            value: None,
+            class: String::from("missing"),
+            combined: None,
+            context: None,
+            current: None,
+            previous: None,
+            additive: true,
+            link: None,
+            metric: None,
+        }
+    }
+
+    pub fn data(&self) -> &str {
+        &self.data
+    }
+
+    pub fn value(&self) -> Option<f64> {
+        self.value
+    }
+
+    pub fn class(&self) -> &str {
+        &self.class
+    }
+
+    /// The current period's value a comparison figure, such as a change, was worked out
+    /// from, as a cell of its own
+    pub fn current(&self) -> Option<Cell> {
+        self.current
+            .as_ref()
+            .map(|(data, value)| Cell::new(data, Some(*value)))
+    }
+
+    /// Marks the cell as one which can't be summed, such as a percentage change, so that
+    /// grand totals follow their policy for it
+    pub fn not_additive(mut self) -> Cell {
+        self.additive = false;
+        self
+    }
+
+    pub fn is_additive(&self) -> bool {
+        self.additive
+    }
+
+    /// Links the cell to url, e.g. a dashboard for its metric, in Markdown, HTML and
+    /// reStructuredText tables
+    pub fn with_link(mut self, url: &str) -> Cell {
+        self.link = Some(url.to_string());
+        self
+    }
+
+    pub fn link(&self) -> Option<&str> {
+        self.link.as_deref()
+    }
+
+    /// Writes summaries of the cell, such as a total row, the way metric writes amounts
+    pub fn with_metric(mut self, metric: &Metric) -> Cell {
+        self.metric = Some(metric.clone());
+        self
+    }
+
+    /// Writes the cell out for context regardless of the table's context, e.g. Raw for a
+    /// column of numbers in a table of words
+    pub fn in_context(mut self, context: RenderContext) -> Cell {
+        self.context = Some(context);
+        self
+    }
+
+    /// The cell's text for its own context, or else context, before it is marked up: the
+    /// raw value for Raw, the combined data for Combined and otherwise the data
+    pub fn text(&self, context: RenderContext) -> String {
+        self.text_for(self.context.unwrap_or(context))
+    }
+
+    fn text_for(&self, context: RenderContext) -> String {
+        match (context, self.value) {
+            (RenderContext::Raw, Some(value)) => value.to_string(),
+            (RenderContext::Combined, _) => {
+                self.combined.clone().unwrap_or_else(|| self.data.clone())
+            }
+            _ => self.data.clone(),
+        }
+    }
+
+    /// Writes the cell out for its own context, or else context, as a figure's render_in
+    /// writes its data
+    pub fn render(&self, context: RenderContext) -> String {
+        let context = self.context.unwrap_or(context);
+        context.value(&self.text_for(context), &self.class)
+    }
+}
+
+/// Works out a table cell for a metric over a span from the metric's datapoints
+pub trait CellFn: Send + Sync {
+    fn cell(
+        &self,
+        metric: &Metric,
+        span: &TimeSpan,
+        points: &[Datapoint],
+    ) -> Result<Cell, CommandError>;
+
+    /// Works out the cell against the earlier span comparison picks, for commands which
+    /// compare spans. Others ignore comparison
+    fn compared(
+        &self,
+        metric: &Metric,
+        span: &TimeSpan,
+        points: &[Datapoint],
+        comparison: Comparison,
+    ) -> Result<Cell, CommandError> {
+        let _ = comparison;
+        self.cell(metric, span, points)
+    }
+}
+
+impl<F> CellFn for F
+where
+    F: Fn(&Metric, &TimeSpan, &[Datapoint]) -> Result<Cell, CommandError> + Send + Sync,
+{
+    fn cell(
+        &self,
+        metric: &Metric,
+        span: &TimeSpan,
+        points: &[Datapoint],
+    ) -> Result<Cell, CommandError> {
+        self(metric, span, points)
+    }
+}
+
+/// The change command, a metric's change from the previous span or the one a comparison picks
+struct ChangeCell;
+
+impl CellFn for ChangeCell {
+    fn cell(
+        &self,
+        metric: &Metric,
+        span: &TimeSpan,
+        points: &[Datapoint],
+    ) -> Result<Cell, CommandError> {
+        self.compared(metric, span, points, Comparison::Previous)
+    }
+
+    fn compared(
+        &self,
+        metric: &Metric,
+        span: &TimeSpan,
+        points: &[Datapoint],
+        comparison: Comparison,
+    ) -> Result<Cell, CommandError> {
+        let prev = span.compare_to(comparison).map_err(date_error)?;
+        require_data(metric, &prev, points)?;
+        require_data(metric, span, points)?;
+        let old = total_within(points, &prev);
+        let new = total_within(points, span);
+        let change =
+            FigChange::new(metric.clone(), span.end(), old, new).with_comparison(comparison);
+        Ok(Cell::figure(&change).not_additive())
+    }
+}
+
+fn date_error(error: crate::DateError) -> CommandError {
+    CommandError(error.to_string())
+}
+
+/// Fails unless the metric has a datapoint within span
+fn require_data(
+    metric: &Metric,
+    span: &TimeSpan,
+    points: &[Datapoint],
+) -> Result<(), CommandError> {
+    if points.iter().any(|point| span.contains(&point.when())) {
+        return Ok(());
+    }
+    Err(CommandError(format!(
+        "no {} data for {}",
+        metric.name(),
+        describe_period(span, metric.date_style())
+    )))
+}
+
+/// What a table's rows or its columns are. Of metric, command and frequency, the one which
+/// is neither the rows nor the columns is the same for every cell. Periods stand in for
+/// frequencies, giving each cell its own span rather than the one containing the report date
+#[derive(Clone)]
+pub enum Axis {
+    Metrics(Vec<Metric>),
+    Commands(Vec<String>),
+    Frequencies(Vec<TimeFrequency>),
+    Periods(Vec<TimeSpan>),
+}
+
+impl Axis {
+    /// The last periods of frequency up to the one containing report_date, oldest first,
+    /// e.g. for a table of the last 6 weeks. Weeks start on Monday, which labels them, but
+    /// Tables works out each metric's cells over its own weeks, as many weeks back
+    pub fn last(
+        periods: u32,
+        frequency: TimeFrequency,
+        report_date: &NaiveDate,
+    ) -> Result<Axis, DateError> {
+        let latest = TimeSpan::new(report_date, frequency)?;
+        (0..periods as i32)
+            .rev()
+            .map(|back| latest.shift(-back))
+            .collect::<Result<_, _>>()
+            .map(Axis::Periods)
+    }
+
+    fn labels(&self) -> Vec<String> {
+        match self {
+            Axis::Metrics(metrics) => metrics.iter().map(|m| m.name().to_string()).collect(),
+            Axis::Commands(commands) => commands.clone(),
+            Axis::Frequencies(frequencies) => {
+                frequencies.iter().map(|f| format!("{:?}", f)).collect()
+            }
+            Axis::Periods(periods) => periods.iter().map(period_label).collect(),
+        }
+    }
+
+    /// Sets the part of selection which is the index'th entry of the axis
+    fn select<'a>(&'a self, index: usize, selection: &mut Selection<'a>) {
+        match self {
+            Axis::Metrics(metrics) => selection.metric = Some(&metrics[index]),
+            Axis::Commands(commands) => selection.command = Some(&commands[index]),
+            Axis::Frequencies(frequencies) => selection.frequency = Some(frequencies[index]),
+            Axis::Periods(periods) => selection.period = Some(periods[index]),
+        }
+    }
+
+    fn same_kind(&self, other: &Axis) -> bool {
+        matches!(
+            (self, other),
+            (Axis::Metrics(_), Axis::Metrics(_))
+                | (Axis::Commands(_), Axis::Commands(_))
+                | (
+                    Axis::Frequencies(_) | Axis::Periods(_),
+                    Axis::Frequencies(_) | Axis::Periods(_)
+                )
+        )
+    }
+}
+
+/// Labels a period as a column heading, e.g. "10 Jan" for a week or "January 2022"
+fn period_label(period: &TimeSpan) -> String {
+    let style = DateStyle::default();
+    match period.frequency() {
+        TimeFrequency::Weekly | TimeFrequency::Daily => style.day(&period.start()),
+        _ => describe_period(period, style),
+    }
+}
+
+/// The metric, command and frequency or period of one cell
+#[derive(Clone, Copy, Default)]
+struct Selection<'a> {
+    metric: Option<&'a Metric>,
+    command: Option<&'a str>,
+    frequency: Option<TimeFrequency>,
+    period: Option<TimeSpan>,
+    span: Option<&'a str>,
+    partial: Option<PartialPolicy>,
+    comparison: Option<Comparison>,
+}
+
+/// What a table shows: its rows, its columns and whichever of metric, command and
+/// frequency they leave fixed. The frequency defaults to each metric's own
+#[derive(Clone)]
+pub struct TableSpec {
+    rows: Axis,
+    columns: Axis,
+    metric: Option<Metric>,
+    command: Option<String>,
+    frequency: Option<TimeFrequency>,
+    span: Option<String>,
+    partial: Option<PartialPolicy>,
+    comparison: Option<Comparison>,
+    placeholder: String,
+    contexts: BTreeMap<String, RenderContext>,
+    precisions: BTreeMap<String, Precision>,
+    links: Option<String>,
+}
+
+impl TableSpec {
+    pub fn new(rows: Axis, columns: Axis) -> TableSpec {
+        TableSpec {
+            rows,
+            columns,
+            metric: None,
+            command: None,
+            frequency: None,
+            span: None,
+            partial: None,
+            comparison: None,
+            placeholder: String::from("—"),
+            contexts: BTreeMap::new(),
+            precisions: BTreeMap::new(),
+            links: None,
+        }
+    }
+
+    /// Writes the cells of the column labelled column out for context whatever the
+    /// table's context, e.g. Raw for a column of numbers
+    pub fn with_column_context(mut self, column: &str, context: RenderContext) -> TableSpec {
+        self.contexts.insert(column.to_string(), context);
+        self
+    }
+
+    /// Works out the cells of the column labelled column with the metric's figures
+    /// written to precision
+    pub fn with_column_precision(mut self, column: &str, precision: Precision) -> TableSpec {
+        self.precisions.insert(column.to_string(), precision);
+        self
+    }
+
+    /// Links each cell to a URL from template, in which `{metric}` is replaced with the
+    /// cell's metric and `{start}` and `{end}` with the first and last days of its span,
+    /// e.g. `https://dash.example.com/{metric}?from={start}&to={end}`
+    pub fn with_links(mut self, template: &str) -> TableSpec {
+        self.links = Some(template.to_string());
+        self
+    }
+
+    /// Sets the text of cells whose figure couldn't be worked out, "—" by default
+    pub fn with_placeholder(mut self, placeholder: &str) -> TableSpec {
+        self.placeholder = placeholder.to_string();
+        self
+    }
+
+    pub fn with_metric(mut self, metric: Metric) -> TableSpec {
+        self.metric = Some(metric);
+        self
+    }
+
+    pub fn with_command(mut self, command: &str) -> TableSpec {
+        self.command = Some(command.to_string());
+        self
+    }
+
+    pub fn with_frequency(mut self, frequency: TimeFrequency) -> TableSpec {
+        self.frequency = Some(frequency);
+        self
+    }
+
+    /// Works out every cell over the span spec describes up to the report date in place of
+    /// a frequency, e.g. `trailing:12w` for the last 12 weeks or `mtd` for the month to date
+    pub fn with_span(mut self, spec: &str) -> TableSpec {
+        self.span = Some(spec.to_string());
+        self
+    }
+
+    /// Treats each cell's span which hasn't finished by the report date under policy, rather
+    /// than reading its data so far
+    pub fn with_partial(mut self, policy: PartialPolicy) -> TableSpec {
+        self.partial = Some(policy);
+        self
+    }
+
+    /// Compares each cell's span against the earlier span comparison picks rather than
+    /// the previous one, for commands which compare spans such as change
+    pub fn with_comparison(mut self, comparison: Comparison) -> TableSpec {
+        self.comparison = Some(comparison);
+        self
+    }
+
+    fn fixed(&self) -> Selection<'_> {
+        Selection {
+            metric: self.metric.as_ref(),
+            command: self.command.as_deref(),
+            frequency: self.frequency,
+            period: None,
+            span: self.span.as_deref(),
+            partial: self.partial,
+            comparison: self.comparison,
+        }
+    }
+}
+
+/// How a summary row or column combines the values in each column or row
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Summary {
+    Sum,
+    Average,
+}
+
+impl Summary {
+    /// Parses `sum` or `average`
+    pub fn parse(spec: &str) -> Option<Summary> {
+        match spec {
+            "sum" => Some(Summary::Sum),
+            "average" => Some(Summary::Average),
+            _ => None,
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            Summary::Sum => "Total",
+            Summary::Average => "Average",
+        }
+    }
+
+    /// Combines the values of cells which can be summed, skipping cells without one, and
+    /// leaving out the likes of changes and sparklines. Empty when none have one
+    fn cell<'a>(&self, cells: impl Iterator<Item = &'a Cell>) -> Cell {
+        self.combine(cells.filter(|cell| cell.is_additive()))
+    }
+
+    /// Combines the values of cells, skipping cells without one, written the way their
+    /// metric writes amounts when they all have the same one. Empty when none have one
+    fn combine<'a>(&self, cells: impl Iterator<Item = &'a Cell>) -> Cell {
+        let cells: Vec<&Cell> = cells.filter(|cell| cell.value.is_some()).collect();
+        let values: Vec<f64> = cells.iter().filter_map(|cell| cell.value).collect();
+        if values.is_empty() {
+            return Cell::new("", None);
+        }
+        let total: f64 = values.iter().sum();
+        let value = match self {
+            Summary::Sum => total,
+            Summary::Average => total / values.len() as f64,
+        };
+        let metric = cells[0]
+            .metric
+            .as_ref()
+            .filter(|metric| {
+                cells
+                    .iter()
+                    .all(|cell| cell.metric.as_ref() == Some(metric))
+            })
+            .filter(|_| cells.iter().all(|cell| cell.is_additive()));
+        match metric {
+            Some(metric) => {
+                Cell::new(&metric.format_amount(value), Some(value)).with_metric(metric)
+            }
+            None => Cell::new(&format_value(value), Some(value)),
+        }
+    }
+}
+
+/// What grand totals do with cells which can't be summed, such as percentage changes
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TotalPolicy {
+    /// Works changes out again from the totals of the values they compare, leaving other
+    /// cells which can't be summed empty
+    #[default]
+    Recompute,
+    /// Averages them
+    Average,
+    /// Leaves them empty
+    Blank,
+}
+
+impl TotalPolicy {
+    /// Parses `recompute`, `average` or `blank`
+    pub fn parse(spec: &str) -> Option<TotalPolicy> {
+        match spec {
+            "recompute" => Some(TotalPolicy::Recompute),
+            "average" => Some(TotalPolicy::Average),
+            "blank" => Some(TotalPolicy::Blank),
+            _ => None,
+        }
+    }
+
+    /// Totals cells, summing them when they can all be summed and otherwise following the
+    /// policy. Empty when it can't be worked out
+    fn cell<'a>(&self, cells: impl Iterator<Item = &'a Cell> + Clone) -> Cell {
+        if cells.clone().all(Cell::is_additive) {
+            return Summary::Sum.cell(cells);
+        }
+        match self {
+            TotalPolicy::Recompute => {
+                let compared: Option<Vec<(f64, f64)>> = cells
+                    .filter(|cell| cell.class != "missing")
+                    .map(|cell| Some((cell.previous?, cell.current.as_ref()?.1)))
+                    .collect();
+                match compared.filter(|compared| !compared.is_empty()) {
+                    Some(compared) => {
+                        let old = compared.iter().map(|(old, _)| old).sum();
+                        let new = compared.iter().map(|(_, new)| new).sum();
+                        let metric = Metric::new(
+                            String::from("total"),
+                            None,
+                            String::from("total {}"),
+                            TimeFrequency::Daily,
+                        );
+                        let change = FigChange::new(metric, NaiveDate::default(), old, new);
+                        Cell::figure(&change).not_additive()
+                    }
+                    None => Cell::new("", None),
+                }
+            }
+            TotalPolicy::Average => Summary::Average.combine(cells),
+            TotalPolicy::Blank => Cell::new("", None),
+        }
+    }
+}
+
+/// Which way a table's rows are sorted
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    #[default]
+    Ascending,
+    Descending,
+}
+
+impl SortOrder {
+    /// Parses `ascending` or `descending`
+    pub fn parse(spec: &str) -> Option<SortOrder> {
+        match spec {
+            "ascending" => Some(SortOrder::Ascending),
+            "descending" => Some(SortOrder::Descending),
+            _ => None,
+        }
+    }
+}
+
+/// The markup a table is written in
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TableFormat {
+    #[default]
+    Markdown,
+    Html,
+    Csv,
+    Rst,
+}
+
+impl TableFormat {
+    /// Parses `markdown`, `html`, `csv` or `rst`
+    pub fn parse(spec: &str) -> Option<TableFormat> {
+        match spec {
+            "markdown" => Some(TableFormat::Markdown),
+            "html" => Some(TableFormat::Html),
+            "csv" => Some(TableFormat::Csv),
+            "rst" => Some(TableFormat::Rst),
+            _ => None,
+        }
+    }
+}
+
+/// A table of figures, with a label for each row and column
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Table {
+    caption: Option<String>,
+    columns: Vec<String>,
+    rows: Vec<(String, Vec<Cell>)>,
+    groups: Vec<(String, usize)>,
+    footer: Option<String>,
+    warnings: Vec<String>,
+}
+
+impl Table {
+    /// An empty table with columns labelled columns, to which rows can be pushed
+    pub fn new(columns: &[&str]) -> Table {
+        Table {
+            caption: None,
+            columns: columns.iter().map(|label| label.to_string()).collect(),
+            rows: Vec::new(),
+            groups: Vec::new(),
+            footer: None,
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Adds a row labelled label, failing unless it has a cell for each column
+    pub fn push_row(&mut self, label: &str, cells: Vec<Cell>) -> Result<(), CommandError> {
+        if cells.len() != self.columns.len() {
+            return Err(CommandError(format!(
+                "the {} row has {} cells for {} columns",
+                label,
+                cells.len(),
+                self.columns.len()
+            )));
+        }
+        self.rows.push((label.to_string(), cells));
+        Ok(())
+    }
+
+    /// Stacks tables with the same columns into one, with the rows of each under a group
+    /// header of its label, e.g. metrics grouped by channel
+    pub fn nested(sections: Vec<(String, Table)>) -> Result<Table, CommandError> {
+        let mut sections = sections.into_iter();
+        let (label, mut nested) = sections
+            .next()
+            .ok_or_else(|| CommandError(String::from("a nested table needs a group")))?;
+        nested.groups = vec![(label, nested.rows.len())];
+        for (label, table) in sections {
+            if table.columns != nested.columns {
+                return Err(CommandError(format!(
+                    "the {} group's columns differ from the others'",
+                    label
+                )));
+            }
+            nested.groups.push((label, table.rows.len()));
+            nested.rows.extend(table.rows);
+            nested.warnings.extend(table.warnings);
+        }
+        Ok(nested)
+    }
+
+    pub fn with_caption(mut self, caption: &str) -> Table {
+        self.caption = Some(caption.to_string());
+        self
+    }
+
+    /// Replaces each row and column label which labels has an entry for, e.g. avg_freq
+    /// with "Daily average"
+    pub fn relabelled(mut self, labels: &BTreeMap<String, String>) -> Table {
+        let relabel = |label: &mut String| {
+            if let Some(new) = labels.get(label.as_str()) {
+                *label = new.clone();
+            }
+        };
+        self.columns.iter_mut().for_each(relabel);
+        self.rows.iter_mut().for_each(|(label, _)| relabel(label));
+        self
+    }
+
+    pub fn caption(&self) -> Option<&str> {
+        self.caption.as_deref()
+    }
+
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    pub fn rows(&self) -> &[(String, Vec<Cell>)] {
+        &self.rows
+    }
+
+    /// The label and number of rows of each group of rows, in order from the first row.
+    /// Rows after the last group, such as a summary row, are in none
+    pub fn groups(&self) -> &[(String, usize)] {
+        &self.groups
+    }
+
+    /// The range of rows in each group and then of the rows in none
+    fn group_ranges(&self) -> Vec<(Option<&str>, Range<usize>)> {
+        let mut start = 0;
+        let mut ranges = Vec::new();
+        for (label, count) in &self.groups {
+            ranges.push((Some(label.as_str()), start..start + count));
+            start += count;
+        }
+        if start < self.rows.len() {
+            ranges.push((None, start..self.rows.len()));
+        }
+        ranges
+    }
+
+    /// Why each placeholder cell's figure couldn't be worked out
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    /// Sorts the rows by the values in the column labelled column, rows without a value
+    /// last. Rows with equal values keep their order, and grouped rows stay in their group
+    pub fn sorted(mut self, column: &str, order: SortOrder) -> Result<Table, CommandError> {
+        let index = self
+            .columns
+            .iter()
+            .position(|label| label == column)
+            .ok_or_else(|| CommandError(format!("no column `{}` to sort by", column)))?;
+        let ranges: Vec<Range<usize>> = self.group_ranges().into_iter().map(|(_, r)| r).collect();
+        for range in ranges {
+            self.rows[range].sort_by(|(_, a), (_, b)| match (a[index].value, b[index].value) {
+                (Some(a), Some(b)) => match order {
+                    SortOrder::Ascending => a.total_cmp(&b),
+                    SortOrder::Descending => b.total_cmp(&a),
+                },
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            });
+        }
+        Ok(self)
+    }
+
+    /// Drops the rows in which every cell is missing or zero, e.g. metrics without data for
+    /// the period. A change from zero to zero counts as zero
+    pub fn without_empty_rows(mut self) -> Table {
+        let is_empty = |cells: &[Cell]| {
+            !cells.iter().any(|cell| {
+                cell.value
+                    .is_some_and(|value| value != 0.0 && !value.is_nan())
+            })
+        };
+        let mut start = 0;
+        for (_, count) in &mut self.groups {
+            let rows = &self.rows[start..start + *count];
+            start += *count;
+            *count -= rows.iter().filter(|(_, cells)| is_empty(cells)).count();
+        }
+        self.rows.retain(|(_, cells)| !is_empty(cells));
+        self
+    }
+
+    /// Follows each column of comparison figures, such as changes, with a column of the
+    /// current period's values they compare, e.g. "change (current)", so that readers can
+    /// see what a change is of
+    pub fn with_current_values(mut self) -> Table {
+        let compares = |column: usize| {
+            self.rows
+                .iter()
+                .any(|(_, cells)| cells[column].current.is_some())
+        };
+        let companions: Vec<usize> = (0..self.columns.len()).filter(|c| compares(*c)).collect();
+        for column in companions.into_iter().rev() {
+            let label = format!("{} (current)", self.columns[column]);
+            self.columns.insert(column + 1, label);
+            for (_, cells) in &mut self.rows {
+                let current = cells[column]
+                    .current()
+                    .unwrap_or_else(|| Cell::new("", None));
+                cells.insert(column + 1, current);
+            }
+        }
+        self
+    }
+
+    /// Keeps the first count rows, e.g. the top 5 once sorted. With others, a footer
+    /// counts the rows left out, e.g. "…and 12 others"
+    pub fn limited(mut self, count: usize, others: bool) -> Table {
+        let left_out = self.rows.len().saturating_sub(count);
+        self.rows.truncate(count);
+        let mut remaining = count;
+        for (_, size) in &mut self.groups {
+            *size = (*size).min(remaining);
+            remaining -= *size;
+        }
+        self.groups.retain(|(_, size)| *size > 0);
+        if others && left_out > 0 {
+            self.footer = Some(format!(
+                "…and {}",
+                inflection::count(left_out as i64, "other")
+            ));
+        }
+        self
+    }
+
+    /// Swaps the rows and columns. Groups of rows are lost, as columns aren't grouped
+    pub fn transposed(self) -> Table {
+        let rows = self
+            .columns
+            .into_iter()
+            .enumerate()
+            .map(|(column, label)| {
+                let cells = self.rows.iter().map(|(_, cells)| cells[column].clone());
+                (label, cells.collect())
+            })
+            .collect();
+        Table {
+            caption: self.caption,
+            columns: self.rows.into_iter().map(|(label, _)| label).collect(),
+            rows,
+            groups: Vec::new(),
+            footer: self.footer,
+            warnings: self.warnings,
+        }
+    }
+
+    /// Adds a row summarising the values in each column
+    pub fn with_summary_row(mut self, summary: Summary) -> Table {
+        let cells = (0..self.columns.len())
+            .map(|column| summary.cell(self.rows.iter().map(|(_, cells)| &cells[column])))
+            .collect();
+        self.rows.push((summary.label().to_string(), cells));
+        self
+    }
+
+    /// Adds a column summarising the values in each row
+    pub fn with_summary_column(mut self, summary: Summary) -> Table {
+        for (_, cells) in &mut self.rows {
+            let cell = summary.cell(cells.iter());
+            cells.push(cell);
+        }
+        self.columns.push(summary.label().to_string());
+        self
+    }
+
+    /// Adds a column totalling each row and a row totalling each column, with the grand
+    /// total where they meet. Cells which can't be summed, such as percentage changes,
+    /// are totalled following policy
+    pub fn with_grand_totals(mut self, policy: TotalPolicy) -> Table {
+        let mut totals: Vec<Cell> = (0..self.columns.len())
+            .map(|column| policy.cell(self.rows.iter().map(|(_, cells)| &cells[column])))
+            .collect();
+        totals.push(policy.cell(self.rows.iter().flat_map(|(_, cells)| cells.iter())));
+        for (_, cells) in &mut self.rows {
+            let total = policy.cell(cells.iter());
+            cells.push(total);
+        }
+        self.columns.push(String::from("Total"));
+        self.rows.push((String::from("Total"), totals));
+        self
+    }
+
+    /// Writes the table as Markdown, with each cell written out for context and each
+    /// column padded to the same width so that it reads well before it is rendered. Each
+    /// group of rows follows a row of its label in bold. The caption follows as
+    /// `Table: caption`
+    pub fn markdown(&self, context: RenderContext) -> String {
+        let line = |label: &str, cells: Vec<String>| {
+            std::iter::once(context.escape(label))
+                .chain(cells)
+                .map(|cell| cell.replace('|', "\\|"))
+                .collect::<Vec<_>>()
+        };
+        let mut lines = vec![line(
+            "",
+            self.columns.iter().map(|c| context.escape(c)).collect(),
+        )];
+        for (group, range) in self.group_ranges() {
+            if let Some(group) = group {
+                let blank = vec![String::new(); self.columns.len()];
+                lines.push(line(&format!("**{}**", group), blank));
+            }
+            for (label, cells) in &self.rows[range] {
+                lines.push(line(
+                    label,
+                    cells
+                        .iter()
+                        .map(|c| match &c.link {
+                            Some(url) => format!("[{}]({})", c.render(context), url),
+                            None => c.render(context),
+                        })
+                        .collect(),
+                ));
+            }
+        }
+        let widths: Vec<usize> = (0..=self.columns.len())
+            .map(|column| {
+                lines
+                    .iter()
+                    .map(|cells| display_width(&cells[column]))
+                    .fold(3, usize::max)
+            })
+            .collect();
+        let pad = |cells: &Vec<String>| {
+            let cells: Vec<String> = cells
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{}{}", cell, " ".repeat(width - display_width(cell))))
+                .collect();
+            format!("| {} |", cells.join(" | "))
+        };
+        let rule: Vec<String> = widths.iter().map(|width| "-".repeat(width + 2)).collect();
+        let mut text = vec![pad(&lines[0]), format!("|{}|", rule.join("|"))];
+        text.extend(lines[1..].iter().map(pad));
+        if let Some(footer) = &self.footer {
+            text.push(format!("\n{}", context.escape(footer)));
+        }
+        if let Some(caption) = &self.caption {
+            text.push(format!("\nTable: {}", context.escape(caption)));
+        }
+        text.join("\n")
+    }
+
+    /// Writes the table as an HTML table of each cell's text for context. With classes,
+    /// each cell has its figure's class, e.g. `<td class="delta up">`, so that rising and
+    /// falling figures can be styled. Each group of rows follows a header spanning the table
+    pub fn html(&self, context: RenderContext, classes: bool) -> String {
+        let html = RenderContext::Html;
+        let mut lines = vec![String::from("<table>")];
+        if let Some(caption) = &self.caption {
+            lines.push(format!("<caption>{}</caption>", html.escape(caption)));
+        }
+        lines.push(String::from("<thead>"));
+        lines.push(format!(
+            "<tr><th></th>{}</tr>",
+            self.columns
+                .iter()
+                .map(|label| format!("<th>{}</th>", html.escape(label)))
+                .collect::<String>()
+        ));
+        lines.push(String::from("</thead>"));
+        lines.push(String::from("<tbody>"));
+        for (group, range) in self.group_ranges() {
+            if let Some(group) = group {
+                lines.push(format!(
+                    "<tr class=\"group\"><th colspan=\"{}\">{}</th></tr>",
+                    self.columns.len() + 1,
+                    html.escape(group)
+                ));
+            }
+            for (label, cells) in &self.rows[range] {
+                let cells: String = cells
+                    .iter()
+                    .map(|cell| {
+                        let mut text = html.escape(&cell.text(context));
+                        if let Some(url) = &cell.link {
+                            text = format!("<a href=\"{}\">{}</a>", html.escape(url), text);
+                        }
+                        if classes {
+                            format!("<td class=\"{}\">{}</td>", cell.class, text)
+                        } else {
+                            format!("<td>{}</td>", text)
+                        }
+                    })
+                    .collect();
+                lines.push(format!("<tr><th>{}</th>{}</tr>", html.escape(label), cells));
+            }
+        }
+        lines.push(String::from("</tbody>"));
+        if let Some(footer) = &self.footer {
+            lines.push(format!(
+                "<tfoot><tr><td colspan=\"{}\">{}</td></tr></tfoot>",
+                self.columns.len() + 1,
+                html.escape(footer)
+            ));
+        }
+        lines.push(String::from("</table>"));
+        lines.join("\n")
+    }
+
+    /// Writes the table as CSV for a spreadsheet, with each cell's raw value where it has
+    /// one and its data otherwise. Each group of rows follows a row of just its label
+    pub fn csv(&self) -> String {
+        let line = |label: &str, cells: Vec<String>| {
+            std::iter::once(csv_field(label))
+                .chain(cells.iter().map(|cell| csv_field(cell)))
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+        let mut lines = vec![line("", self.columns.clone())];
+        for (group, range) in self.group_ranges() {
+            if let Some(group) = group {
+                lines.push(line(group, vec![String::new(); self.columns.len()]));
+            }
+            for (label, cells) in &self.rows[range] {
+                lines.push(line(
+                    label,
+                    cells
+                        .iter()
+                        .map(|c| c.text_for(RenderContext::Raw))
+                        .collect(),
+                ));
+            }
+        }
+        lines.join("\n")
+    }
+
+    /// Writes the table as a reStructuredText grid table, e.g. for Sphinx, with each cell
+    /// written out for context. Each group of rows follows a row of its label in bold
+    /// spanning the table. With a caption, the table is the body of a `.. table::`
+    /// directive titled with it
+    pub fn rst(&self, context: RenderContext) -> String {
+        let line = |label: &str, cells: Vec<String>| {
+            std::iter::once(context.escape(label))
+                .chain(cells)
+                .map(|cell| cell.replace('|', "\\|"))
+                .collect::<Vec<_>>()
+        };
+        let mut lines = vec![(
+            None,
+            line("", self.columns.iter().map(|c| context.escape(c)).collect()),
+        )];
+        for (group, range) in self.group_ranges() {
+            if let Some(group) = group {
+                lines.push((Some(format!("**{}**", group)), Vec::new()));
+            }
+            for (label, cells) in &self.rows[range] {
+                lines.push((
+                    None,
+                    line(
+                        label,
+                        cells
+                            .iter()
+                            .map(|c| match &c.link {
+                                Some(url) => format!("`{} <{}>`__", c.render(context), url),
+                                None => c.render(context),
+                            })
+                            .collect(),
+                    ),
+                ));
+            }
+        }
+        let mut widths: Vec<usize> = (0..=self.columns.len())
+            .map(|column| {
+                lines
+                    .iter()
+                    .filter(|(group, _)| group.is_none())
+                    .map(|(_, cells)| display_width(&cells[column]))
+                    .fold(1, usize::max)
+            })
+            .collect();
+        let mut inner = widths.iter().map(|width| width + 3).sum::<usize>() - 3;
+        let widest_group = lines
+            .iter()
+            .filter_map(|(group, _)| group.as_deref().map(display_width))
+            .fold(0, usize::max);
+        if widest_group > inner {
+            widths[self.columns.len()] += widest_group - inner;
+            inner = widest_group;
+        }
+        let rule = |c: &str| {
+            let rule: Vec<String> = widths.iter().map(|width| c.repeat(width + 2)).collect();
+            format!("+{}+", rule.join("+"))
+        };
+        let mut text = vec![rule("-")];
+        for (index, (group, cells)) in lines.iter().enumerate() {
+            text.push(match group {
+                Some(group) => format!("| {}{} |", group, " ".repeat(inner - display_width(group))),
+                None => {
+                    let cells: Vec<String> = cells
+                        .iter()
+                        .zip(&widths)
+                        .map(|(cell, width)| {
+                            format!("{}{}", cell, " ".repeat(width - display_width(cell)))
+                        })
+                        .collect();
+                    format!("| {} |", cells.join(" | "))
+                }
+            });
+            text.push(rule(if index == 0 { "=" } else { "-" }));
+        }
+        if let Some(caption) = &self.caption {
+            text = std::iter::once(format!(".. table:: {}\n", context.escape(caption)))
+                .chain(text.into_iter().map(|line| format!("   {}", line)))
+                .collect();
+        }
+        if let Some(footer) = &self.footer {
+            text.push(format!("\n{}", context.escape(footer)));
+        }
+        text.join("\n")
+    }
+
+    /// Splits the table into tables of at most max_columns columns each, to be stacked one
+    /// above the other, each with the row labels. The caption and warnings stay with the
+    /// first
+    pub fn split(&self, max_columns: usize) -> Vec<Table> {
+        let max_columns = max_columns.max(1);
+        let chunks = (0..self.columns.len()).step_by(max_columns);
+        chunks
+            .enumerate()
+            .map(|(index, start)| {
+                let end = (start + max_columns).min(self.columns.len());
+                Table {
+                    caption: self.caption.clone().filter(|_| index == 0),
+                    columns: self.columns[start..end].to_vec(),
+                    rows: self
+                        .rows
+                        .iter()
+                        .map(|(label, cells)| (label.clone(), cells[start..end].to_vec()))
+                        .collect(),
+                    groups: self.groups.clone(),
+                    footer: self.footer.clone().filter(|_| end == self.columns.len()),
+                    warnings: match index {
+                        0 => self.warnings.clone(),
+                        _ => Vec::new(),
+                    },
+                }
+            })
+            .collect()
+    }
+
+    /// Writes the table in format for context, with cell classes in HTML. CSV ignores
+    /// context
+    pub fn write(&self, format: TableFormat, context: RenderContext) -> String {
+        match format {
+            TableFormat::Markdown => self.markdown(context),
+            TableFormat::Html => self.html(context, true),
+            TableFormat::Csv => self.csv(),
+            TableFormat::Rst => self.rst(context),
+        }
+    }
+}
+
+/// Number of characters text takes up in a terminal, leaving out ANSI escape sequences
+fn display_width(text: &str) -> usize {
+    let mut width = 0;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            chars.by_ref().find(|c| c.is_ascii_alphabetic());
+        } else {
+            width += 1;
+        }
+    }
+    width
+}
+
+/// Quotes field for CSV when it holds a comma, quote or line break
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+impl fmt::Display for Table {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.markdown(RenderContext::Words))
+    }
+}
+
+/// Metrics, their datapoints and the commands which work out table cells from them, from
+/// which tables are built for a report date
+#[derive(Clone)]
+pub struct Tables {
+    cells: BTreeMap<String, Arc<dyn CellFn>>,
+    metrics: BTreeMap<String, Metric>,
+    groups: BTreeMap<String, Vec<String>>,
+    points: HashMap<String, Arc<[Datapoint]>>,
+    report_date: NaiveDate,
+    context: RenderContext,
+    warnings: Arc<Mutex<Vec<String>>>,
+    attachments: Arc<Mutex<BTreeMap<String, String>>>,
+    cache: Arc<Mutex<HashMap<CellKey, Result<Cell, CommandError>>>>,
+    defaults: BTreeMap<String, JsonValue>,
+}
+
+/// How many datapoints the spark command draws
+const SPARK_POINTS: usize = 12;
+
+/// What a cell was worked out from: the metric as JSON, the command and the span
+type CellKey = (String, String, String);
+
+impl Tables {
+    /// Tables with the commands total, change, diff, avg_freq, a daily average, and spark,
+    /// a sparkline of the metric's last SPARK_POINTS datapoints up to the end of the span
+    pub fn new(report_date: NaiveDate) -> Tables {
+        let mut tables = Tables {
+            cells: BTreeMap::new(),
+            metrics: BTreeMap::new(),
+            groups: BTreeMap::new(),
+            points: HashMap::new(),
+            report_date,
+            context: RenderContext::Words,
+            warnings: Arc::default(),
+            attachments: Arc::default(),
+            cache: Arc::default(),
+            defaults: BTreeMap::new(),
+        };
+        tables.register(
+            "total",
+            |metric: &Metric, span: &TimeSpan, points: &[Datapoint]| {
+                require_data(metric, span, points)?;
+                let total = total_within(points, span);
+                Ok(Cell::new(&metric.format_amount(total), Some(total)).with_metric(metric))
+            },
+        );
+        tables.register("change", ChangeCell);
+        tables.register(
+            "diff",
+            |metric: &Metric, span: &TimeSpan, points: &[Datapoint]| {
+                require_data(metric, &span.prev().map_err(date_error)?, points)?;
+                require_data(metric, span, points)?;
+                let diff = FigDiff::for_span(metric.clone(), span, points).map_err(date_error)?;
+                Ok(Cell::figure(&diff))
+            },
+        );
+        tables.register(
+            "avg_freq",
+            |metric: &Metric, span: &TimeSpan, points: &[Datapoint]| {
+                require_data(metric, span, points)?;
+                Ok(Cell::figure(&FigAvgFreq::new(
+                    metric.clone(),
+                    *span,
+                    total_within(points, span),
+                    TimeFrequency::Daily,
+                    Normalization::Exact,
+                )))
+            },
+        );
+        tables.register(
+            "spark",
+            |metric: &Metric, span: &TimeSpan, points: &[Datapoint]| {
+                require_data(metric, span, points)?;
+                let spark = FigSpark::new(metric.clone(), span.end(), SPARK_POINTS, points);
+                Ok(Cell::figure(&spark).not_additive())
+            },
+        );
+        tables
+    }
+
+    /// Adds the command avg_business_day, a daily average over the weekdays which aren't
+    /// holidays in calendar
+    pub fn with_holidays(mut self, calendar: HolidayCalendar) -> Tables {
+        self.register(
+            "avg_business_day",
+            move |metric: &Metric, span: &TimeSpan, points: &[Datapoint]| {
+                require_data(metric, span, points)?;
+                Ok(Cell::figure(&FigAvgFreq::new(
+                    metric.clone(),
+                    *span,
+                    total_within(points, span),
+                    TimeFrequency::Daily,
+                    Normalization::BusinessDays(calendar.clone()),
+                )))
+            },
+        );
+        self
+    }
+
+    /// Adds the command vs_target, the total over the span against the target set for
+    /// exactly that span, from targets for any of the metrics
+    pub fn with_targets(mut self, targets: Vec<Target>) -> Tables {
+        let mut by_metric: HashMap<String, Vec<Target>> = HashMap::new();
+        for target in targets {
+            by_metric
+                .entry(target.metric().name().to_string())
+                .or_default()
+                .push(target);
+        }
+        self.register(
+            "vs_target",
+            move |metric: &Metric, span: &TimeSpan, points: &[Datapoint]| {
+                require_data(metric, span, points)?;
+                let targets = by_metric.get(metric.name()).map_or(&[][..], Vec::as_slice);
+                let figure = FigVsTarget::for_span(metric.clone(), span, points, targets)
+                    .ok_or_else(|| {
+                        CommandError(format!(
+                            "no {} target for {}",
+                            metric.name(),
+                            describe_period(span, metric.date_style())
+                        ))
+                    })?;
+                Ok(Cell::figure(&figure).not_additive())
+            },
+        );
+        self
+    }
+
+    /// Adds a command for table cells, replacing any already registered under name
+    pub fn register(&mut self, name: &str, cell: impl CellFn + 'static) {
+        self.cells.insert(name.to_string(), Arc::new(cell));
+        self.cache = Arc::default();
+    }
+
+    /// Adds a metric and its datapoints, so that tables can have it as a row or column
+    pub fn add_metric(&mut self, metric: Metric, points: Vec<Datapoint>) {
+        self.points.insert(metric.name().to_string(), points.into());
+        self.metrics.insert(metric.name().to_string(), metric);
+        self.cache = Arc::default();
+    }
+
+    /// Adds a group of metrics, such as visits by channel, whose name stands for its
+    /// members as a table's rows or columns
+    pub fn add_group(&mut self, group: &MetricGroup) {
+        self.groups
+            .insert(group.name().to_string(), group.members().to_vec());
+    }
+
+    /// Warnings from every table the table helper has written, one for each cell whose
+    /// figure couldn't be worked out
+    pub fn warnings(&self) -> Vec<String> {
+        self.warnings.lock().map(|w| w.clone()).unwrap_or_default()
+    }
+
+    /// Like warnings, but clears them, so that rendering the next report starts afresh
+    pub fn take_warnings(&self) -> Vec<String> {
+        self.warnings
+            .lock()
+            .map(|mut w| std::mem::take(&mut *w))
+            .unwrap_or_default()
+    }
+
+    /// The CSV of every table the table helper was asked to attach with csv_file, by the
+    /// name it was given, for the caller to write out wherever it sees fit
+    pub fn attachments(&self) -> BTreeMap<String, String> {
+        self.attachments
+            .lock()
+            .map(|a| a.clone())
+            .unwrap_or_default()
+    }
+
+    /// Sets the context the table function writes cells out for, Words by default
+    pub fn set_context(&mut self, context: RenderContext) {
+        self.context = context;
+    }
+
+    /// Reads names, each a frequency, a command or a metric, as a table's rows or columns
+    /// A single name `last:<n>:<frequency>`, e.g. `last:6:weekly`, is the last n periods,
+    /// and a single group name is its metrics
+    pub fn axis(&self, names: &[&str]) -> Result<Axis, CommandError> {
+        if let [name] = names {
+            if let Some(members) = self.groups.get(*name) {
+                let members: Vec<&str> = members.iter().map(String::as_str).collect();
+                return self.axis(&members);
+            }
+            if let Some(spec) = name.strip_prefix("last:") {
+                let periods = spec
+                    .split_once(':')
+                    .and_then(|(n, name)| Some((n.parse().ok()?, frequency(name)?)))
+                    .ok_or_else(|| CommandError(format!("couldn't read `{}` as periods", name)))?;
+                return Axis::last(periods.0, periods.1, &self.report_date).map_err(date_error);
+            }
+        }
+        if let Some(frequencies) = names.iter().map(|name| frequency(name)).collect() {
+            return Ok(Axis::Frequencies(frequencies));
+        }
+        if names.iter().all(|name| self.cells.contains_key(*name)) {
+            return Ok(Axis::Commands(
+                names.iter().map(|n| n.to_string()).collect(),
+            ));
+        }
+        names
+            .iter()
+            .map(|name| {
+                self.metrics.get(*name).cloned().ok_or_else(|| {
+                    CommandError(format!(
+                        "`{}` isn't a metric, table command or frequency",
+                        name
+                    ))
+                })
+            })
+            .collect::<Result<_, _>>()
+            .map(Axis::Metrics)
+    }
+
+    /// Works out each cell of the table spec describes
+    pub fn table(&self, spec: &TableSpec) -> Result<Table, CommandError> {
+        if spec.rows.same_kind(&spec.columns) {
+            return Err(CommandError(String::from(
+                "a table's rows and columns must be different kinds",
+            )));
+        }
+        self.build(spec, spec.fixed())
+    }
+
+    /// Works out command for each metric of group side by side, in a single row labelled
+    /// with the group's name, e.g. total visits from each channel
+    pub fn pivot(&self, group: &str, spec: &TableSpec) -> Result<Table, CommandError> {
+        let members = self
+            .groups
+            .get(group)
+            .ok_or_else(|| CommandError(format!("no metric group `{}`", group)))?;
+        let members: Vec<&str> = members.iter().map(String::as_str).collect();
+        let command = spec
+            .command
+            .clone()
+            .unwrap_or_else(|| String::from("total"));
+        let mut spec = spec.clone();
+        spec.rows = Axis::Commands(vec![command]);
+        spec.columns = self.axis(&members)?;
+        let mut table = self.table(&spec)?;
+        for (label, _) in &mut table.rows {
+            *label = group.to_string();
+        }
+        Ok(table)
+    }
+
+    /// Works out a table for each entry of groups, labelled with it, e.g. a table per
+    /// frequency of metrics against commands
+    pub fn grouped(
+        &self,
+        spec: &TableSpec,
+        groups: &Axis,
+    ) -> Result<Vec<(String, Table)>, CommandError> {
+        if spec.rows.same_kind(&spec.columns)
+            || groups.same_kind(&spec.rows)
+            || groups.same_kind(&spec.columns)
+        {
+            return Err(CommandError(String::from(
+                "a grouped table's rows, columns and groups must be different kinds",
+            )));
+        }
+        groups
+            .labels()
+            .into_iter()
+            .enumerate()
+            .map(|(index, label)| {
+                let mut fixed = spec.fixed();
+                groups.select(index, &mut fixed);
+                Ok((label, self.build(spec, fixed)?))
+            })
+            .collect()
+    }
+
+    fn build(&self, spec: &TableSpec, fixed: Selection<'_>) -> Result<Table, CommandError> {
+        let columns = spec.columns.labels();
+        let mut rows = Vec::new();
+        let mut warnings = Vec::new();
+        for (row, label) in spec.rows.labels().into_iter().enumerate() {
+            let mut cells = Vec::new();
+            for (column, column_label) in columns.iter().enumerate() {
+                let mut selection = fixed;
+                spec.rows.select(row, &mut selection);
+                spec.columns.select(column, &mut selection);
+                let precise = spec
+                    .precisions
+                    .get(column_label)
+                    .zip(selection.metric)
+                    .map(|(precision, metric)| metric.clone().with_precision(*precision));
+                if let Some(metric) = &precise {
+                    selection.metric = Some(metric);
+                }
+                let mut cell = self.cell(selection)?.unwrap_or_else(|e| {
+                    warnings.push(e.0);
+                    Cell::missing(&spec.placeholder)
+                });
+                if let Some(context) = spec.contexts.get(column_label) {
+                    cell = cell.in_context(*context);
+                }
+                if let (Some(template), Some(metric)) = (&spec.links, selection.metric) {
+                    if cell.class != "missing" {
+                        let span = self.span(metric, selection)?;
+                        let url = template
+                            .replace("{metric}", metric.name())
+                            .replace("{start}", &span.start().to_string())
+                            .replace("{end}", &span.end().to_string());
+                        cell = cell.with_link(&url);
+                    }
+                }
+                cells.push(cell);
+            }
+            rows.push((label, cells));
+        }
+        Ok(Table {
+            caption: None,
+            columns,
+            rows,
+            groups: Vec::new(),
+            footer: None,
+            warnings,
+        })
+    }
+
+    /// Works out the cell for selection, or reuses it when an earlier table worked out the
+    /// same metric, command and span. The outer error is a mistake in the table, the inner
+    /// one a figure which couldn't be worked out
+    fn cell(&self, selection: Selection<'_>) -> Result<Result<Cell, CommandError>, CommandError> {
+        let metric = selection
+            .metric
+            .ok_or_else(|| CommandError(String::from("a table needs a metric")))?;
+        let command = selection
+            .command
+            .ok_or_else(|| CommandError(String::from("a table needs a command")))?;
+        let cell = self
+            .cells
+            .get(command)
+            .ok_or_else(|| CommandError(format!("no table command `{}`", command)))?;
+        let span = self.span(metric, selection)?;
+        let (adjusted, scale) = match selection.partial {
+            Some(policy) => match span.with_policy(policy, &self.report_date) {
+                Ok(adjusted) => adjusted,
+                Err(e) => return Ok(Err(date_error(e))),
+            },
+            None => (span, 1.0),
+        };
+        // An extrapolated span keeps its full length, so that it is compared with whole
+        // earlier spans, and only its data so far is scaled up
+        let span = if scale != 1.0 { span } else { adjusted };
+        let key = (
+            serde_json::to_string(metric).map_err(|e| CommandError(e.to_string()))?,
+            command.to_string(),
+            format!("{:?} {:?} {}", span, selection.comparison, scale),
+        );
+        if let Some(cached) = self.cache.lock().ok().and_then(|c| c.get(&key).cloned()) {
+            return Ok(cached);
+        }
+        let mut points = self.points.get(metric.name()).map_or(&[][..], Arc::as_ref);
+        let scaled: Vec<Datapoint>;
+        if scale != 1.0 {
+            scaled = points
+                .iter()
+                .map(|point| {
+                    let value = if adjusted.contains(&point.when()) {
+                        point.value() * scale
+                    } else {
+                        point.value()
+                    };
+                    Datapoint::new(value, metric.clone(), point.when())
+                })
+                .collect();
+            points = &scaled;
+        }
+        let result = match selection.comparison {
+            Some(comparison) => cell.compared(metric, &span, points, comparison),
+            None => cell.cell(metric, &span, points),
+        };
+        if let Ok(mut cache) = self.cache.lock() {
+            cache.insert(key, result.clone());
+        }
+        Ok(result)
+    }
+
+    /// The span of selection's period, moved onto the metric's own weeks, or else the span
+    /// it describes, e.g. `trailing:12w` or `mtd`, in the metric's weeks, or the period of
+    /// its frequency or the metric's own up to the report date
+    fn span(&self, metric: &Metric, selection: Selection<'_>) -> Result<TimeSpan, CommandError> {
+        match (selection.period, selection.span) {
+            (Some(period), _)
+                if period.frequency() == TimeFrequency::Weekly
+                    && period.week_start() != metric.week_start() =>
+            {
+                let anchored = |week_start| {
+                    TimeSpan::anchored(&self.report_date, TimeFrequency::Weekly, week_start)
+                        .map_err(date_error)
+                };
+                let back = (anchored(period.week_start())?.start() - period.start()).num_weeks();
+                anchored(metric.week_start())?
+                    .shift(-(back as i32))
+                    .map_err(date_error)
+            }
+            (Some(period), _) => Ok(period),
+            (None, Some(spec)) => {
+                let week_start = metric.week_start();
+                TimeSpan::parse_trailing(spec, &self.report_date, week_start)
+                    .or_else(|_| TimeSpan::parse_to_date(spec, &self.report_date, week_start))
+                    .map_err(|_| CommandError(format!("couldn't read `{}` as a span", spec)))
+            }
+            (None, None) => {
+                let frequency = selection.frequency.unwrap_or_else(|| metric.frequency());
+                TimeSpan::anchored(&self.report_date, frequency, metric.week_start())
+                    .map_err(date_error)
+            }
+        }
+    }
+
+    /// Registers a Handlebars helper named table, e.g.
+    /// `{{table rows="users,visits" columns="total,change" frequency="weekly"}}`, taking
+    /// rows and columns as lists of names and a fixed metric, command or frequency.
+    /// Rows or columns of `last:6:weekly` are the last 6 weeks up to the report date, and
+    /// row_groups in place of rows groups them under headers, e.g.
+    /// `Web: users, visits; Email: signups`.
+    ///
+    /// Options:
+    /// - links links each cell to a URL from a template of `{metric}`, `{start}` and
+    ///   `{end}`, e.g. a dashboard
+    /// - placeholder sets the text of cells whose figure couldn't be worked out, whose
+    ///   warnings are kept for warnings
+    /// - contexts and precision write columns their own way, e.g. `change:raw` and
+    ///   `total:decimals:1`
+    /// - hide_empty=true drops rows whose cells are all missing or zero
+    /// - current_values=true follows each column of changes with the current values
+    /// - sort orders the rows by the named column, `ascending` unless order is
+    ///   `descending`
+    /// - limit keeps the first rows once sorted, and others=true counts those left out
+    /// - summary_row and summary_column add a row or column, `sum` or `average`
+    /// - grand_totals=true adds a total row and column, totalling changes from the values
+    ///   they compare unless non_additive is `average` or `blank`
+    /// - labels renames rows and columns, e.g. `avg_freq:Daily average`, and caption
+    ///   captions the table
+    /// - transpose=true swaps the rows and columns once the table is sorted and summarised
+    /// - pivot in place of rows and columns is a metric group, each of whose metrics is a
+    ///   column of a single row, worked out with command or total
+    /// - groups writes a table for each of a third list of names, each under a heading
+    /// - format is `markdown`, `html`, `csv` or `rst`, and csv_file also keeps the table's
+    ///   CSV under that name for attachments
+    /// - max_columns splits Markdown, HTML and reStructuredText tables wider than it into
+    ///   stacked tables
+    /// - span in place of a frequency is a window up to the report date, e.g. `trailing:12w`
+    ///   for the last 12 weeks, or a period to date, `wtd`, `mtd`, `qtd` or `ytd`
+    /// - partial is `exclude_partial`, `include_partial` or `extrapolate`, what spans which
+    ///   haven't finished by the report date are read as: the last finished one, the span
+    ///   so far, or the span so far scaled up to its full length
+    /// - compare is `prev` or `yoy`, the earlier span changes are from
+    ///
+    /// Also registers a block helper named if_figure, which writes its block only when a
+    /// figure is worked out and passes its thresholds, and its else block otherwise, e.g.
+    /// `{{#if_figure metric="users" command="change" above="10%"}}`. It takes a metric, a
+    /// command, total by default, a frequency or span, a partial policy and a comparison as
+    /// for the table, and above and below as numbers or percentages.
+    ///
+    /// And a block helper named each_metric, which writes its block once for each metric
+    /// of a group or whose name matches a pattern, with `this` the metric's name, e.g.
+    /// `{{#each_metric "web_*"}}{{table rows=this columns="total"}}{{/each_metric}}`. Its
+    /// else block is written when no metric matches.
+    ///
+    /// And a helper named figure, which writes one figure for the table's context, e.g.
+    /// `{{figure metric="users" command="change"}}`, taking a metric, a command, total by
+    /// default, a frequency or span, a partial policy and a comparison. It fails when the
+    /// figure can't be worked out, and can be bound to a name with let.
+    ///
+    /// And a block helper named defaults, whose arguments the table, if_figure and figure
+    /// helpers within its block take when they leave them out, e.g.
+    /// `{{#defaults frequency="weekly" placeholder="n/a"}}`. A helper's own arguments come
+    /// first, then those of the nearest defaults block around it, then the next and so on.
+    pub fn install(&self, hbs: &mut Handlebars) {
+        hbs.register_helper("table", Box::new(TableHelper(self.clone())));
+        hbs.register_helper("if_figure", Box::new(ConditionHelper(self.clone())));
+        hbs.register_helper("each_metric", Box::new(EachMetricHelper(self.clone())));
+        hbs.register_helper("figure", Box::new(FigureHelper(self.clone())));
+        hbs.register_helper("defaults", Box::new(DefaultsHelper(self.clone())));
+    }
+
+    /// The members of the group named pattern, or else the names of the metrics it
+    /// matches, where `*` matches any run of characters
+    fn matching(&self, pattern: &str) -> Vec<String> {
+        match self.groups.get(pattern) {
+            Some(members) => members.clone(),
+            None => self
+                .metrics
+                .keys()
+                .filter(|name| matches_glob(pattern, name))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// hash with the arguments of enclosing defaults blocks filled in for those of args it
+    /// leaves out
+    fn defaulted<'a>(
+        &'a self,
+        args: &[&str],
+        hash: &BTreeMap<&'a str, JsonValue>,
+    ) -> BTreeMap<&'a str, JsonValue> {
+        let mut hash = hash.clone();
+        for (key, value) in &self.defaults {
+            if args.contains(&key.as_str()) {
+                hash.entry(key.as_str()).or_insert_with(|| value.clone());
+            }
+        }
+        hash
+    }
+
+    /// Works out the cell for the metric, command, total by default, and frequency a
+    /// helper's arguments name, failing on arguments other than args
+    fn figure_for(
+        &self,
+        helper: &str,
+        args: &[&str],
+        hash: &BTreeMap<&str, JsonValue>,
+    ) -> Result<Result<Cell, CommandError>, CommandError> {
+        if let Some(key) = hash.keys().find(|key| !args.contains(key)) {
+            return Err(CommandError(format!("unknown arg `{}`", key)));
+        }
+        let hash = &self.defaulted(args, hash);
+        let name = hash
+            .get("metric")
+            .and_then(JsonValue::as_str)
+            .ok_or_else(|| CommandError(format!("{} needs a metric", helper)))?;
+        let metric = self
+            .metrics
+            .get(name)
+            .ok_or_else(|| CommandError(format!("no metric `{}`", name)))?;
+        let frequency = match hash.get("frequency").and_then(JsonValue::as_str) {
+            Some(name) => Some(
+                frequency(name).ok_or_else(|| CommandError(format!("no frequency `{}`", name)))?,
+            ),
+            None => None,
+        };
+        let comparison = match hash.get("compare").and_then(JsonValue::as_str) {
+            Some(spec) => Some(
+                Comparison::parse(spec)
+                    .map_err(|_| CommandError(format!("no comparison `{}`", spec)))?,
+            ),
+            None => None,
+        };
+        let partial = match hash.get("partial").and_then(JsonValue::as_str) {
+            Some(policy) => Some(partial_policy(policy)?),
+            None => None,
+        };
+        self.cell(Selection {
+            metric: Some(metric),
+            command: Some(
+                hash.get("command")
+                    .and_then(JsonValue::as_str)
+                    .unwrap_or("total"),
+            ),
+            frequency,
+            period: None,
+            span: hash.get("span").and_then(JsonValue::as_str),
+            partial,
+            comparison,
+        })
+    }
+
+    /// Whether the figure the if_figure helper's arguments describe can be worked out and
+    /// is within their thresholds
+    fn holds(&self, hash: &BTreeMap<&str, JsonValue>) -> Result<bool, CommandError> {
+        let value = match self.figure_for("if_figure", &CONDITION_ARGS, hash)? {
+            Ok(cell) => cell.value(),
+            Err(_) => return Ok(false),
+        };
+        let threshold = |key: &str| hash.get(key).map(threshold).transpose();
+        Ok(match (threshold("above")?, threshold("below")?, value) {
+            (None, None, _) => true,
+            (_, _, None) => false,
+            (above, below, Some(value)) => {
+                above.is_none_or(|above| value > above) && below.is_none_or(|below| value < below)
+            }
+        })
+    }
+
+    /// Writes the table, or with groups the table for each group under a heading, that the
+    /// table helper's arguments describe
+    fn render_for(&self, hash: &BTreeMap<&str, JsonValue>) -> Result<String, CommandError> {
+        if let Some(key) = hash.keys().find(|key| !TABLE_ARGS.contains(key)) {
+            return Err(CommandError(format!("unknown arg `{}`", key)));
+        }
+        let hash = &self.defaulted(&TABLE_ARGS, hash);
+        let axis = |key: &str| {
+            let names = hash
+                .get(key)
+                .map(names)
+                .ok_or_else(|| CommandError(format!("a table needs {}", key)))?;
+            self.axis(&names.iter().map(String::as_str).collect::<Vec<_>>())
+        };
+        let format = table_format(hash)?;
+        let sections = if let Some(group) = hash.get("pivot").and_then(JsonValue::as_str) {
+            let spec =
+                self.spec_for(hash, Axis::Commands(Vec::new()), Axis::Commands(Vec::new()))?;
+            vec![(None, self.pivot(group, &spec)?)]
+        } else if let Some(row_groups) = hash.get("row_groups").and_then(JsonValue::as_str) {
+            vec![(None, self.nested_for(hash, row_groups, axis("columns")?)?)]
+        } else if hash.contains_key("groups") {
+            let spec = self.spec_for(hash, axis("rows")?, axis("columns")?)?;
+            self.grouped(&spec, &axis("groups")?)?
+                .into_iter()
+                .map(|(label, table)| (Some(label), table))
+                .collect()
+        } else {
+            let spec = self.spec_for(hash, axis("rows")?, axis("columns")?)?;
+            vec![(None, self.table(&spec)?)]
+        };
+        let max_columns = hash
+            .get("max_columns")
+            .and_then(JsonValue::as_u64)
+            .map(|max| max as usize);
+        let sections = sections
+            .into_iter()
+            .map(|(label, table)| Ok((label, self.options(table, hash)?)))
+            .collect::<Result<Vec<_>, CommandError>>()?;
+        if let Ok(mut warnings) = self.warnings.lock() {
+            for (_, table) in &sections {
+                warnings.extend(table.warnings.iter().cloned());
+            }
+        }
+        let write = |format: TableFormat, heading: &dyn Fn(&str) -> String| {
+            sections
+                .iter()
+                .map(|(label, table)| {
+                    let text = match (format, max_columns) {
+                        (
+                            TableFormat::Markdown | TableFormat::Html | TableFormat::Rst,
+                            Some(max),
+                        ) => table
+                            .split(max)
+                            .iter()
+                            .map(|table| table.write(format, self.context))
+                            .collect::<Vec<_>>()
+                            .join("\n\n"),
+                        _ => table.write(format, self.context),
+                    };
+                    match label {
+                        Some(label) => format!("{}\n\n{}", heading(label), text),
+                        None => text,
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("\n\n")
+        };
+        if let Some(name) = hash.get("csv_file").and_then(JsonValue::as_str) {
+            let csv = write(TableFormat::Csv, &|label| csv_field(label));
+            if let Ok(mut attachments) = self.attachments.lock() {
+                attachments.insert(name.to_string(), csv + "\n");
+            }
+        }
+        Ok(write(format, &|label| self.context.heading(label)))
+    }
+
+    /// Works out the table of groups of rows written `Label: name, name; Label: name`
+    fn nested_for(
+        &self,
+        hash: &BTreeMap<&str, JsonValue>,
+        row_groups: &str,
+        columns: Axis,
+    ) -> Result<Table, CommandError> {
+        let sections = row_groups
+            .split(';')
+            .map(|group| {
+                let (label, rows) = group.split_once(':').ok_or_else(|| {
+                    CommandError(format!("couldn't read `{}` as Label: names", group))
+                })?;
+                let rows: Vec<&str> = rows.split(',').map(str::trim).collect();
+                let spec = self.spec_for(hash, self.axis(&rows)?, columns.clone())?;
+                Ok((label.trim().to_string(), self.table(&spec)?))
+            })
+            .collect::<Result<_, CommandError>>()?;
+        Table::nested(sections)
+    }
+
+    /// Reads the table helper's fixed metric, command and frequency
+    fn spec_for(
+        &self,
+        hash: &BTreeMap<&str, JsonValue>,
+        rows: Axis,
+        columns: Axis,
+    ) -> Result<TableSpec, CommandError> {
+        let mut spec = TableSpec::new(rows, columns);
+        if let Some(placeholder) = hash.get("placeholder").and_then(JsonValue::as_str) {
+            spec = spec.with_placeholder(placeholder);
+        }
+        if let Some(contexts) = hash.get("contexts").and_then(JsonValue::as_str) {
+            for (column, context) in pairs(contexts)? {
+                let context = RenderContext::parse(&context)
+                    .ok_or_else(|| CommandError(format!("no render context `{}`", context)))?;
+                spec = spec.with_column_context(&column, context);
+            }
+        }
+        if let Some(precisions) = hash.get("precision").and_then(JsonValue::as_str) {
+            for (column, precision) in pairs(precisions)? {
+                let precision = Precision::parse(&precision)
+                    .ok_or_else(|| CommandError(format!("no precision `{}`", precision)))?;
+                spec = spec.with_column_precision(&column, precision);
+            }
+        }
+        if let Some(name) = hash.get("metric").and_then(JsonValue::as_str) {
+            let metric = self
+                .metrics
+                .get(name)
+                .ok_or_else(|| CommandError(format!("no metric `{}`", name)))?;
+            spec = spec.with_metric(metric.clone());
+        }
+        if let Some(command) = hash.get("command").and_then(JsonValue::as_str) {
+            spec = spec.with_command(command);
+        }
+        if let Some(template) = hash.get("links").and_then(JsonValue::as_str) {
+            spec = spec.with_links(template);
+        }
+        if let Some(name) = hash.get("frequency").and_then(JsonValue::as_str) {
+            let frequency =
+                frequency(name).ok_or_else(|| CommandError(format!("no frequency `{}`", name)))?;
+            spec = spec.with_frequency(frequency);
+        }
+        if let Some(span) = hash.get("span").and_then(JsonValue::as_str) {
+            spec = spec.with_span(span);
+        }
+        if let Some(policy) = hash.get("partial").and_then(JsonValue::as_str) {
+            spec = spec.with_partial(partial_policy(policy)?);
+        }
+        if let Some(spec_text) = hash.get("compare").and_then(JsonValue::as_str) {
+            let comparison = Comparison::parse(spec_text)
+                .map_err(|_| CommandError(format!("no comparison `{}`", spec_text)))?;
+            spec = spec.with_comparison(comparison);
+        }
+        Ok(spec)
+    }
+
+    /// Applies the table helper's sort and summaries to table
+    fn options(
+        &self,
+        mut table: Table,
+        hash: &BTreeMap<&str, JsonValue>,
+    ) -> Result<Table, CommandError> {
+        if hash.get("hide_empty").and_then(JsonValue::as_bool) == Some(true) {
+            table = table.without_empty_rows();
+        }
+        if hash.get("current_values").and_then(JsonValue::as_bool) == Some(true) {
+            table = table.with_current_values();
+        }
+        if let Some(column) = hash.get("sort").and_then(JsonValue::as_str) {
+            let order = match hash.get("order").and_then(JsonValue::as_str) {
+                Some(spec) => SortOrder::parse(spec)
+                    .ok_or_else(|| CommandError(format!("no sort order `{}`", spec)))?,
+                None => SortOrder::default(),
+            };
+            table = table.sorted(column, order)?;
+        }
+        if let Some(limit) = hash.get("limit").and_then(JsonValue::as_u64) {
+            let others = hash.get("others").and_then(JsonValue::as_bool) == Some(true);
+            table = table.limited(limit as usize, others);
+        }
+        if let Some(spec) = hash.get("summary_column").and_then(JsonValue::as_str) {
+            table = table.with_summary_column(summary(spec)?);
+        }
+        if let Some(spec) = hash.get("summary_row").and_then(JsonValue::as_str) {
+            table = table.with_summary_row(summary(spec)?);
+        }
+        if hash.get("grand_totals").and_then(JsonValue::as_bool) == Some(true) {
+            let policy = match hash.get("non_additive").and_then(JsonValue::as_str) {
+                Some(spec) => TotalPolicy::parse(spec)
+                    .ok_or_else(|| CommandError(format!("no total policy `{}`", spec)))?,
+                None => TotalPolicy::default(),
+            };
+            table = table.with_grand_totals(policy);
+        }
+        if let Some(spec) = hash.get("labels").and_then(JsonValue::as_str) {
+            table = table.relabelled(&pairs(spec)?);
+        }
+        if let Some(caption) = hash.get("caption").and_then(JsonValue::as_str) {
+            table = table.with_caption(caption);
+        }
+        if hash.get("transpose").and_then(JsonValue::as_bool) == Some(true) {
+            table = table.transposed();
+        }
+        Ok(table)
+    }
+}
+
+/// Reads a frequency written like `weekly` or `Weekly`
+fn frequency(name: &str) -> Option<TimeFrequency> {
+    [
+        TimeFrequency::Yearly,
+        TimeFrequency::Quarterly,
+        TimeFrequency::Monthly,
+        TimeFrequency::Weekly,
+        TimeFrequency::Daily,
+    ]
+    .into_iter()
+    .find(|frequency| format!("{:?}", frequency).eq_ignore_ascii_case(name))
+}
+
+/// The arguments the table helper takes
+const TABLE_ARGS: [&str; 31] = [
+    "rows",
+    "columns",
+    "row_groups",
+    "groups",
+    "pivot",
+    "metric",
+    "command",
+    "frequency",
+    "placeholder",
+    "contexts",
+    "precision",
+    "links",
+    "hide_empty",
+    "current_values",
+    "sort",
+    "order",
+    "limit",
+    "others",
+    "summary_column",
+    "summary_row",
+    "grand_totals",
+    "non_additive",
+    "labels",
+    "caption",
+    "transpose",
+    "format",
+    "csv_file",
+    "max_columns",
+    "span",
+    "partial",
+    "compare",
+];
+
+/// Whether name matches pattern, in which `*` matches any run of characters
+fn matches_glob(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let mut rest = match name.strip_prefix(parts.next().unwrap_or_default()) {
+        Some(rest) => rest,
+        None => return false,
+    };
+    let parts: Vec<&str> = parts.collect();
+    match parts.split_last() {
+        None => rest.is_empty(),
+        Some((last, middle)) => {
+            for part in middle {
+                match rest.find(part) {
+                    Some(index) => rest = &rest[index + part.len()..],
+                    None => return false,
+                }
+            }
+            rest.ends_with(last)
+        }
+    }
+}
+
+/// The arguments the figure helper takes
+const FIGURE_ARGS: [&str; 6] = [
+    "metric",
+    "command",
+    "frequency",
+    "span",
+    "partial",
+    "compare",
+];
+
+/// The arguments the if_figure helper takes
+const CONDITION_ARGS: [&str; 8] = [
+    "metric",
+    "command",
+    "frequency",
+    "span",
+    "partial",
+    "compare",
+    "above",
+    "below",
+];
+
+/// Reads a partial period policy, e.g. `extrapolate`
+fn partial_policy(spec: &str) -> Result<PartialPolicy, CommandError> {
+    PartialPolicy::parse(spec).map_err(|_| CommandError(format!("no partial policy `{}`", spec)))
+}
+
+/// Reads a threshold written as a number or a percentage, e.g. 0.1 or "10%"
+fn threshold(value: &JsonValue) -> Result<f64, CommandError> {
+    let parsed = match value {
+        JsonValue::String(text) => match text.trim().strip_suffix('%') {
+            Some(percent) => percent.trim().parse::<f64>().ok().map(|p| p / 100.0),
+            None => text.trim().parse().ok(),
+        },
+        _ => value.as_f64(),
+    };
+    parsed.ok_or_else(|| CommandError(format!("couldn't read {} as a threshold", value)))
+}
+
+/// Reads the table helper's format, Markdown by default
+fn table_format(hash: &BTreeMap<&str, JsonValue>) -> Result<TableFormat, CommandError> {
+    match hash.get("format").and_then(JsonValue::as_str) {
+        Some(spec) => TableFormat::parse(spec)
+            .ok_or_else(|| CommandError(format!("no table format `{}`", spec))),
+        None => Ok(TableFormat::default()),
+    }
+}
+
+fn summary(spec: &str) -> Result<Summary, CommandError> {
+    Summary::parse(spec).ok_or_else(|| CommandError(format!("no summary `{}`", spec)))
+}
+
+/// Reads `name:value` pairs separated by commas, e.g. `avg_freq:Daily average, total:Total`.
+/// Values may themselves contain colons, e.g. `total:decimals:1`
+fn pairs(spec: &str) -> Result<BTreeMap<String, String>, CommandError> {
+    spec.split(',')
+        .map(|pair| {
+            let (name, value) = pair
+                .split_once(':')
+                .ok_or_else(|| CommandError(format!("couldn't read `{}` as name:value", pair)))?;
+            Ok((name.trim().to_string(), value.trim().to_string()))
+        })
+        .collect()
+}
+
+/// Reads a list of names written either as a comma separated string or as an array
+fn names(value: &JsonValue) -> Vec<String> {
+    match value {
+        JsonValue::Array(values) => values
+            .iter()
+            .filter_map(JsonValue::as_str)
+            .map(String::from)
+            .collect(),
+        JsonValue::String(text) => text
+            .split(',')
+            .map(|name| name.trim().to_string())
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+struct TableHelper(Tables);
+
+impl HelperDef for TableHelper {
+    fn call<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'reg, 'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc Context,
+        _: &mut handlebars::RenderContext<'reg, 'rc>,
+        out: &mut dyn Output,
+    ) -> HelperResult {
+        let hash: BTreeMap<&str, JsonValue> = h
+            .hash()
+            .iter()
+            .map(|(key, value)| (*key, value.value().clone()))
+            .collect();
+        let text = self
+            .0
+            .render_for(&hash)
+            .map_err(|e| RenderError::new(e.to_string()))?;
+        out.write(&text)?;
+        Ok(())
+    }
+}
+
+struct ConditionHelper(Tables);
+
+impl HelperDef for ConditionHelper {
+    fn call<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'reg, 'rc>,
+        r: &'reg Handlebars<'reg>,
+        ctx: &'rc Context,
+        rc: &mut handlebars::RenderContext<'reg, 'rc>,
+        out: &mut dyn Output,
+    ) -> HelperResult {
+        let hash: BTreeMap<&str, JsonValue> = h
+            .hash()
+            .iter()
+            .map(|(key, value)| (*key, value.value().clone()))
+            .collect();
+        let holds = self
+            .0
+            .holds(&hash)
+            .map_err(|e| RenderError::new(e.to_string()))?;
+        let block = if holds { h.template() } else { h.inverse() };
+        if let Some(block) = block {
+            block.render(r, ctx, rc, out)?;
+        }
+        Ok(())
+    }
+}
+
+struct FigureHelper(Tables);
+
+impl HelperDef for FigureHelper {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'reg, 'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc Context,
+        _: &mut handlebars::RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'reg, 'rc>, RenderError> {
+        let hash: BTreeMap<&str, JsonValue> = h
+            .hash()
+            .iter()
+            .map(|(key, value)| (*key, value.value().clone()))
+            .collect();
+        let cell = self
+            .0
+            .figure_for("figure", &FIGURE_ARGS, &hash)
+            .and_then(|cell| cell)
+            .map_err(|e| RenderError::new(e.to_string()))?;
+        Ok(ScopedJson::Derived(JsonValue::from(
+            cell.render(self.0.context),
+        )))
+    }
+}
+
+/// Stands in for the helpers which take defaults within a defaults block, with its
+/// arguments over those of the blocks around it
+struct DefaultsHelper(Tables);
+
+impl DefaultsHelper {
+    fn register_local(rc: &mut handlebars::RenderContext<'_, '_>, tables: &Tables) {
+        rc.register_local_helper("table", Box::new(TableHelper(tables.clone())));
+        rc.register_local_helper("if_figure", Box::new(ConditionHelper(tables.clone())));
+        rc.register_local_helper("figure", Box::new(FigureHelper(tables.clone())));
+        rc.register_local_helper("defaults", Box::new(DefaultsHelper(tables.clone())));
+    }
+}
+
+impl HelperDef for DefaultsHelper {
+    fn call<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'reg, 'rc>,
+        r: &'reg Handlebars<'reg>,
+        ctx: &'rc Context,
+        rc: &mut handlebars::RenderContext<'reg, 'rc>,
+        out: &mut dyn Output,
+    ) -> HelperResult {
+        let mut tables = self.0.clone();
+        for (key, value) in h.hash() {
+            if ![&TABLE_ARGS[..], &CONDITION_ARGS, &FIGURE_ARGS]
+                .iter()
+                .any(|args| args.contains(key))
+            {
+                return Err(RenderError::new(format!("unknown arg `{}`", key)));
+            }
+            tables
+                .defaults
+                .insert(key.to_string(), value.value().clone());
+        }
+        let template = match h.template() {
+            Some(template) => template,
+            None => return Ok(()),
+        };
+        DefaultsHelper::register_local(rc, &tables);
+        let rendered = template.render(r, ctx, rc, out);
+        if self.0.defaults.is_empty() {
+            for name in ["table", "if_figure", "figure", "defaults"] {
+                rc.unregister_local_helper(name);
+            }
+        } else {
+            DefaultsHelper::register_local(rc, &self.0);
+        }
+        rendered
+    }
+}
+
+struct EachMetricHelper(Tables);
+
+impl HelperDef for EachMetricHelper {
+    fn call<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'reg, 'rc>,
+        r: &'reg Handlebars<'reg>,
+        ctx: &'rc Context,
+        rc: &mut handlebars::RenderContext<'reg, 'rc>,
+        out: &mut dyn Output,
+    ) -> HelperResult {
+        let pattern = h
+            .param(0)
+            .and_then(|param| param.value().as_str())
+            .ok_or_else(|| RenderError::new("each_metric needs a group or pattern"))?;
+        let names = self.0.matching(pattern);
+        if names.is_empty() {
+            if let Some(inverse) = h.inverse() {
+                inverse.render(r, ctx, rc, out)?;
+            }
+            return Ok(());
+        }
+        if let Some(template) = h.template() {
+            for (index, name) in names.iter().enumerate() {
+                let mut block = BlockContext::new();
+                block.set_base_value(to_json(name));
+                block.set_local_var("index", to_json(index));
+                block.set_local_var("first", to_json(index == 0));
+                block.set_local_var("last", to_json(index == names.len() - 1));
+                rc.push_block(block);
+                let rendered = template.render(r, ctx, rc, out);
+                rc.pop_block();
+                rendered?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use chrono::Weekday;
+
+    fn weekly(name: &str, values: &[f64]) -> (Metric, Vec<Datapoint>) {
+        let metric = Metric::new(
+            name.to_string(),
+            None,
+            format!("{} were {{}}", name),
+            TimeFrequency::Weekly,
+        );
+        let start = NaiveDate::from_ymd_opt(2022, 1, 3).unwrap();
+        let points = values
+            .iter()
+            .zip(start.iter_weeks())
+            .map(|(value, when)| Datapoint::new(*value, metric.clone(), when))
+            .collect();
+        (metric, points)
+    }
+
+    fn tables() -> Tables {
+        let mut tables = Tables::new(NaiveDate::from_ymd_opt(2022, 1, 12).unwrap());
+        let (users, points) = weekly("users", &[100.0, 125.0]);
+        tables.add_metric(users, points);
+        let (visits, points) = weekly("visits", &[400.0, 300.0]);
+        tables.add_metric(visits, points);
+        tables
+    }
+
+    #[test]
+    fn targets_in_cells() {
+        let tables = tables();
+        let week = TimeSpan::new(&tables.report_date, TimeFrequency::Weekly).unwrap();
+        let users = tables.metrics["users"].clone();
+        let tables = tables.with_targets(vec![Target::new(100.0, users, &week)]);
+        let spec = TableSpec::new(
+            tables.axis(&["users", "visits"]).unwrap(),
+            tables.axis(&["vs_target"]).unwrap(),
+        )
+        .with_placeholder("n/a");
+        let table = tables.table(&spec).unwrap();
+        assert_eq!(
+            table.to_string(),
+            "|        | vs_target             |\n\
+             |--------|-----------------------|\n\
+             | users  | 25.0% ahead of target |\n\
+             | visits | n/a                   |"
+        );
+        assert!(!table.rows[0].1[0].is_additive());
+        assert_eq!(
+            table.warnings(),
+            [String::from("no visits target for the week of 10 Jan")]
+        );
+    }
+
+    #[test]
+    fn business_day_averages_skip_holidays() {
+        let holiday = NaiveDate::from_ymd_opt(2022, 1, 12).unwrap();
+        let tables = tables().with_holidays(HolidayCalendar::new([holiday]));
+        let spec = TableSpec::new(
+            tables.axis(&["users"]).unwrap(),
+            tables.axis(&["avg_freq", "avg_business_day"]).unwrap(),
+        );
+        assert_eq!(
+            tables.table(&spec).unwrap().to_string(),
+            "|       | avg_freq      | avg_business_day       |\n\
+             |-------|---------------|------------------------|\n\
+             | users | 17.86 per day | 31.25 per business day |"
+        );
+    }
+
+    #[test]
+    fn trailing_spans() {
+        let tables = tables();
+        let mut hbs = Handlebars::new();
+        tables.install(&mut hbs);
+        assert_eq!(
+            hbs.render_template(
+                "{{table rows=\"users,visits\" columns=\"total\" span=\"trailing:2w\"}}",
+                &()
+            )
+            .unwrap(),
+            "|        | total |\n\
+             |--------|-------|\n\
+             | users  | 225   |\n\
+             | visits | 700   |"
+        );
+        assert_eq!(
+            hbs.render_template(
+                "{{figure metric=\"users\" command=\"total\" span=\"trailing:1w\"}}",
+                &()
+            )
+            .unwrap(),
+            "125"
+        );
+        assert_eq!(
+            hbs.render_template(
+                "{{figure metric=\"visits\" command=\"total\" span=\"mtd\"}}",
+                &()
+            )
+            .unwrap(),
+            "700"
+        );
+        assert!(hbs
+            .render_template(
+                "{{table rows=\"users\" columns=\"total\" span=\"trailing:2x\"}}",
+                &()
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn partial_periods() {
+        let tables = tables();
+        let mut hbs = Handlebars::new();
+        tables.install(&mut hbs);
+        let render = |partial: &str| {
+            hbs.render_template(
+                &format!(
+                    "{{{{figure metric=\"users\" command=\"total\" partial=\"{}\"}}}}",
+                    partial
+                ),
+                &(),
+            )
+        };
+        assert_eq!(render("include_partial").unwrap(), "125");
+        assert_eq!(render("exclude_partial").unwrap(), "100");
+        assert_eq!(render("extrapolate").unwrap(), "291.7");
+        assert!(render("partial").is_err());
+
+        let change = hbs.render_template(
+            "{{figure metric=\"users\" command=\"change\" partial=\"extrapolate\"}}",
+            &(),
+        );
+        assert_eq!(change.unwrap(), "up 191.7%");
+        let change = hbs.render_template(
+            "{{figure metric=\"users\" command=\"change\" partial=\"extrapolate\" \
+             compare=\"prev\"}}",
+            &(),
+        );
+        assert_eq!(change.unwrap(), "up 191.7%");
+    }
+
+    #[test]
+    fn year_on_year_changes() {
+        let mut tables = Tables::new(NaiveDate::from_ymd_opt(2022, 1, 12).unwrap());
+        let (users, mut points) = weekly("users", &[100.0, 125.0]);
+        points.push(Datapoint::new(
+            80.0,
+            users.clone(),
+            NaiveDate::from_ymd_opt(2021, 1, 11).unwrap(),
+        ));
+        tables.add_metric(users, points);
+
+        let mut hbs = Handlebars::new();
+        tables.install(&mut hbs);
+        assert_eq!(
+            hbs.render_template(
+                "{{table rows=\"users\" columns=\"change\" compare=\"yoy\"}}",
+                &()
+            )
+            .unwrap(),
+            "|       | change                |\n\
+             |-------|-----------------------|\n\
+             | users | up 56.3% year on year |"
+        );
+        assert_eq!(
+            hbs.render_template(
+                "{{figure metric=\"users\" command=\"change\" compare=\"prev\"}}",
+                &()
+            )
+            .unwrap(),
+            "up 25.0%"
+        );
+        assert!(hbs
+            .render_template(
+                "{{table rows=\"users\" columns=\"change\" compare=\"mom\"}}",
+                &()
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn metrics_side_by_side() {
+        let tables = tables();
+        let spec = TableSpec::new(
+            tables.axis(&["users", "visits"]).unwrap(),
+            tables.axis(&["total", "change"]).unwrap(),
+        );
+        assert_eq!(
+            tables.table(&spec).unwrap().to_string(),
+            "|        | total | change     |\n\
+             |--------|-------|------------|\n\
+             | users  | 125   | up 25.0%   |\n\
+             | visits | 300   | down 25.0% |"
+        );
+        assert_eq!(display_width("\x1b[32mup 25.0%\x1b[0m"), 8);
+
+        let mut hbs = Handlebars::new();
+        tables.install(&mut hbs);
+        assert_eq!(
+            hbs.render_template(
+                "{{table rows=\"weekly, monthly\" columns=\"users,visits\" command=\"total\"}}",
+                &()
+            )
+            .unwrap(),
+            "|         | users | visits |\n\
+             |---------|-------|--------|\n\
+             | Weekly  | 125   | 300    |\n\
+             | Monthly | 225   | 700    |"
+        );
+        assert!(hbs
+            .render_template("{{table rows=\"users\" columns=\"visits\"}}", &())
+            .is_err());
+        let error = hbs
+            .render_template(
+                "{{table rows=\"users\" columns=\"total\" frequncy=\"weekly\"}}",
+                &(),
+            )
+            .unwrap_err();
+        assert_eq!(error.desc, "command failed: unknown arg `frequncy`");
+    }
+
+    #[test]
+    fn sorted_by_change() {
+        let tables = tables();
+        let mut hbs = Handlebars::new();
+        tables.install(&mut hbs);
+        assert_eq!(
+            hbs.render_template(
+                "{{table rows=\"visits,users\" columns=\"change\" sort=\"change\" order=\"descending\"}}",
+                &()
+            )
+            .unwrap(),
+            "|        | change     |\n\
+             |--------|------------|\n\
+             | users  | up 25.0%   |\n\
+             | visits | down 25.0% |"
+        );
+        assert!(hbs
+            .render_template(
+                "{{table rows=\"visits\" columns=\"change\" sort=\"total\"}}",
+                &()
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn grouped_by_frequency() {
+        let tables = tables();
+        let mut hbs = Handlebars::new();
+        tables.install(&mut hbs);
+        assert_eq!(
+            hbs.render_template(
+                "{{table rows=\"users\" columns=\"total\" groups=\"weekly,monthly\"}}",
+                &()
+            )
+            .unwrap(),
+            "Weekly\n\n|       | total |\n|-------|-------|\n| users | 125   |\n\n\
+             Monthly\n\n|       | total |\n|-------|-------|\n| users | 225   |"
+        );
+        let spec = TableSpec::new(
+            tables.axis(&["users"]).unwrap(),
+            tables.axis(&["total"]).unwrap(),
+        );
+        assert!(tables
+            .grouped(&spec, &tables.axis(&["visits"]).unwrap())
+            .is_err());
+    }
+
+    #[test]
+    fn html_tables() {
+        let tables = tables();
+        let spec = TableSpec::new(
+            tables.axis(&["users"]).unwrap(),
+            tables.axis(&["total", "change"]).unwrap(),
+        );
+        let table = tables.table(&spec).unwrap();
+        assert_eq!(
+            table.html(RenderContext::Words, true),
+            "<table>\n<thead>\n<tr><th></th><th>total</th><th>change</th></tr>\n</thead>\n\
+             <tbody>\n<tr><th>users</th><td class=\"figure\">125</td>\
+             <td class=\"delta up\">up 25.0%</td></tr>\n</tbody>\n</table>"
+        );
+        assert!(table
+            .html(RenderContext::Raw, false)
+            .contains("<tr><th>users</th><td>125</td><td>0.25</td></tr>"));
+    }
+
+    #[test]
+    fn csv_export() {
+        let tables = tables();
+        let spec = TableSpec::new(
+            tables.axis(&["users", "visits"]).unwrap(),
+            tables.axis(&["total", "change"]).unwrap(),
+        );
+        assert_eq!(
+            tables.table(&spec).unwrap().csv(),
+            ",total,change\nusers,125,0.25\nvisits,300,-0.25"
+        );
+        assert_eq!(csv_field("up 1,200"), "\"up 1,200\"");
+        assert_eq!(csv_field("a \"b\""), "\"a \"\"b\"\"\"");
+
+        let mut hbs = Handlebars::new();
+        tables.install(&mut hbs);
+        let template = "{{table rows=\"users\" columns=\"total\" csv_file=\"users.csv\"}}";
+        assert_eq!(
+            hbs.render_template(template, &()).unwrap(),
+            "|       | total |\n|-------|-------|\n| users | 125   |"
+        );
+        assert_eq!(
+            tables.attachments(),
+            BTreeMap::from([(
+                String::from("users.csv"),
+                String::from(",total\nusers,125\n")
+            )])
+        );
+    }
+
+    #[test]
+    fn transposed() {
+        let tables = tables();
+        let mut hbs = Handlebars::new();
+        tables.install(&mut hbs);
+        assert_eq!(
+            hbs.render_template(
+                "{{table rows=\"users,visits\" columns=\"total\" summary_row=\"sum\" transpose=true}}",
+                &()
+            )
+            .unwrap(),
+            "|       | users | visits | Total |\n\
+             |-------|-------|--------|-------|\n\
+             | total | 125   | 300    | 425   |"
+        );
+    }
+
+    #[test]
+    fn captions_and_labels() {
+        let tables = tables();
+        let mut hbs = Handlebars::new();
+        tables.install(&mut hbs);
+        let template = "{{table rows=\"users\" columns=\"total,avg_freq\" \
+                        labels=\"avg_freq:Daily average, users:Users\" caption=\"This week\"}}";
+        assert_eq!(
+            hbs.render_template(template, &()).unwrap(),
+            "|       | total | Daily average |\n\
+             |-------|-------|---------------|\n\
+             | Users | 125   | 17.86 per day |\n\
+             \n\
+             Table: This week"
+        );
+        assert!(hbs
+            .render_template(
+                "{{table rows=\"users\" columns=\"total\" labels=\"Users\"}}",
+                &()
+            )
+            .is_err());
+
+        let spec = TableSpec::new(
+            tables.axis(&["users"]).unwrap(),
+            tables.axis(&["total"]).unwrap(),
+        );
+        let table = tables.table(&spec).unwrap().with_caption("Users & visits");
+        assert!(table
+            .html(RenderContext::Words, false)
+            .starts_with("<table>\n<caption>Users &amp; visits</caption>\n<thead>"));
+    }
+
+    #[test]
+    fn placeholders_for_missing_data() {
+        let mut tables = tables();
+        let (signups, points) = weekly("signups", &[]);
+        tables.add_metric(signups, points);
+        let mut hbs = Handlebars::new();
+        tables.install(&mut hbs);
+        assert_eq!(
+            hbs.render_template(
+                "{{table rows=\"users,signups\" columns=\"total\" placeholder=\"n/a\"}}",
+                &()
+            )
+            .unwrap(),
+            "|         | total |\n|---------|-------|\n| users   | 125   |\n| signups | n/a   |"
+        );
+        assert_eq!(
+            tables.warnings(),
+            vec![String::from("no signups data for the week of 10 Jan")]
+        );
+        assert_eq!(tables.take_warnings().len(), 1);
+        assert!(tables.warnings().is_empty());
+
+        let spec = TableSpec::new(
+            tables.axis(&["signups"]).unwrap(),
+            tables.axis(&["change"]).unwrap(),
+        );
+        let table = tables.table(&spec).unwrap();
+        assert_eq!(table.rows()[0].1, vec![Cell::missing("—")]);
+        assert_eq!(table.warnings().len(), 1);
+    }
+
+    #[test]
+    fn empty_rows_hidden() {
+        let mut tables = tables();
+        let (signups, points) = weekly("signups", &[]);
+        tables.add_metric(signups, points);
+        let (refunds, points) = weekly("refunds", &[0.0, 0.0]);
+        tables.add_metric(refunds, points);
+        let mut hbs = Handlebars::new();
+        tables.install(&mut hbs);
+        assert_eq!(
+            hbs.render_template(
+                "{{table rows=\"signups,users,refunds\" columns=\"total,change\" hide_empty=true}}",
+                &()
+            )
+            .unwrap(),
+            "|       | total | change   |\n|-------|-------|----------|\n| users | 125   | up 25.0% |"
+        );
+    }
+
+    #[test]
+    fn column_contexts() {
+        let tables = tables();
+        let mut hbs = Handlebars::new();
+        tables.install(&mut hbs);
+        assert_eq!(
+            hbs.render_template(
+                "{{table rows=\"users\" columns=\"total,change,avg_freq\" \
+                 contexts=\"change:raw\" precision=\"total:decimals:1, avg_freq:sigfigs:2\"}}",
+                &()
+            )
+            .unwrap(),
+            "|       | total | change | avg_freq   |\n\
+             |-------|-------|--------|------------|\n\
+             | users | 125.0 | 0.25   | 18 per day |"
+        );
+        assert!(hbs
+            .render_template(
+                "{{table rows=\"users\" columns=\"change\" contexts=\"change:numbers\"}}",
+                &()
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn last_periods() {
+        let tables = tables();
+        let mut hbs = Handlebars::new();
+        tables.install(&mut hbs);
+        assert_eq!(
+            hbs.render_template(
+                "{{table rows=\"users,visits\" columns=\"last:3:weekly\" command=\"total\"}}",
+                &()
+            )
+            .unwrap(),
+            "|        | 27 Dec | 3 Jan | 10 Jan |\n\
+             |--------|--------|-------|--------|\n\
+             | users  | —      | 100   | 125    |\n\
+             | visits | —      | 400   | 300    |"
+        );
+        assert!(hbs
+            .render_template(
+                "{{table rows=\"users\" columns=\"last:three:weekly\" command=\"total\"}}",
+                &()
+            )
+            .is_err());
+
+        let mut tables = tables;
+        let (signups, _) = weekly("signups", &[]);
+        let signups = signups.with_week_start(Weekday::Wed);
+        let points = [(4, 7.0), (11, 9.0)]
+            .iter()
+            .map(|(day, value)| {
+                let when = NaiveDate::from_ymd_opt(2022, 1, *day).unwrap();
+                Datapoint::new(*value, signups.clone(), when)
+            })
+            .collect();
+        tables.add_metric(signups, points);
+        let spec = TableSpec::new(
+            tables.axis(&["users", "signups"]).unwrap(),
+            tables.axis(&["last:2:weekly"]).unwrap(),
+        )
+        .with_command("total");
+        assert_eq!(
+            tables.table(&spec).unwrap().to_string(),
+            "|         | 3 Jan | 10 Jan |\n\
+             |---------|-------|--------|\n\
+             | users   | 100   | 125    |\n\
+             | signups | 9     | —      |"
+        );
+        assert!(tables
+            .table(&TableSpec::new(
+                Axis::last(2, TimeFrequency::Monthly, &tables.report_date).unwrap(),
+                tables.axis(&["weekly"]).unwrap(),
+            ))
+            .is_err());
+    }
+
+    #[test]
+    fn cells_cached() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let mut tables = tables();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counted = Arc::clone(&calls);
+        tables.register("count", move |_: &Metric, _: &TimeSpan, _: &[Datapoint]| {
+            counted.fetch_add(1, Ordering::Relaxed);
+            Ok(Cell::new("1", Some(1.0)))
+        });
+        let mut hbs = Handlebars::new();
+        tables.install(&mut hbs);
+        let template =
+            "{{table rows=\"users,visits\" columns=\"count\" groups=\"weekly,monthly\"}} \
+                        {{table rows=\"users\" columns=\"weekly\" command=\"count\"}}";
+        hbs.render_template(template, &()).unwrap();
+        assert_eq!(calls.load(Ordering::Relaxed), 4);
+
+        let (visits, points) = weekly("visits", &[1.0]);
+        tables.add_metric(visits, points);
+        let spec = TableSpec::new(
+            tables.axis(&["visits"]).unwrap(),
+            tables.axis(&["count"]).unwrap(),
+        );
+        tables.table(&spec).unwrap();
+        assert_eq!(calls.load(Ordering::Relaxed), 5);
+    }
+
+    #[test]
+    fn nested_rows() {
+        let mut tables = tables();
+        let (signups, points) = weekly("signups", &[10.0, 40.0]);
+        tables.add_metric(signups, points);
+        let mut hbs = Handlebars::new();
+        tables.install(&mut hbs);
+        let template = "{{table row_groups=\"Web: users, visits; Email: signups\" \
+                        columns=\"total\" sort=\"total\" order=\"descending\" summary_row=\"sum\"}}";
+        assert_eq!(
+            hbs.render_template(template, &()).unwrap(),
+            "|           | total |\n\
+             |-----------|-------|\n\
+             | **Web**   |       |\n\
+             | visits    | 300   |\n\
+             | users     | 125   |\n\
+             | **Email** |       |\n\
+             | signups   | 40    |\n\
+             | Total     | 465   |"
+        );
+
+        let spec = |rows: &[&str]| {
+            TableSpec::new(tables.axis(rows).unwrap(), tables.axis(&["total"]).unwrap())
+        };
+        let table = Table::nested(vec![
+            (
+                String::from("Web"),
+                tables.table(&spec(&["users"])).unwrap(),
+            ),
+            (
+                String::from("Email"),
+                tables.table(&spec(&["signups"])).unwrap(),
+            ),
+        ])
+        .unwrap();
+        assert!(table
+            .html(RenderContext::Words, false)
+            .contains("<tr class=\"group\"><th colspan=\"2\">Email</th></tr>"));
+        assert_eq!(table.csv(), ",total\nWeb,\nusers,125\nEmail,\nsignups,40");
+        assert_eq!(
+            table.rst(RenderContext::Words),
+            "+---------+-------+\n\
+             |         | total |\n\
+             +=========+=======+\n\
+             | **Web**         |\n\
+             +---------+-------+\n\
+             | users   | 125   |\n\
+             +---------+-------+\n\
+             | **Email**       |\n\
+             +---------+-------+\n\
+             | signups | 40    |\n\
+             +---------+-------+"
+        );
+        assert!(table
+            .with_caption("Signups")
+            .rst(RenderContext::Words)
+            .starts_with(".. table:: Signups\n\n   +---------+-------+\n   |         | total |"));
+    }
+
+    #[test]
+    fn wide_tables_split() {
+        let tables = tables();
+        let mut hbs = Handlebars::new();
+        tables.install(&mut hbs);
+        assert_eq!(
+            hbs.render_template(
+                "{{table rows=\"users\" columns=\"last:3:weekly\" command=\"total\" max_columns=2}}",
+                &()
+            )
+            .unwrap(),
+            "|       | 27 Dec | 3 Jan |\n\
+             |-------|--------|-------|\n\
+             | users | —      | 100   |\n\
+             \n\
+             |       | 10 Jan |\n\
+             |-------|--------|\n\
+             | users | 125    |"
+        );
+        let spec = TableSpec::new(
+            tables.axis(&["users"]).unwrap(),
+            tables.axis(&["total"]).unwrap(),
+        );
+        assert_eq!(tables.table(&spec).unwrap().split(0).len(), 1);
+    }
+
+    #[test]
+    fn top_rows() {
+        let mut tables = tables();
+        let (signups, points) = weekly("signups", &[10.0, 40.0]);
+        tables.add_metric(signups, points);
+        let mut hbs = Handlebars::new();
+        tables.install(&mut hbs);
+        assert_eq!(
+            hbs.render_template(
+                "{{table rows=\"users,visits,signups\" columns=\"total\" sort=\"total\" \
+                 order=\"descending\" limit=1 others=true}}",
+                &()
+            )
+            .unwrap(),
+            "|        | total |\n|--------|-------|\n| visits | 300   |\n\n…and 2 others"
+        );
+
+        let spec = TableSpec::new(
+            tables.axis(&["users", "visits", "signups"]).unwrap(),
+            tables.axis(&["total"]).unwrap(),
+        );
+        let table = tables.table(&spec).unwrap().limited(2, true);
+        assert!(table
+            .html(RenderContext::Words, false)
+            .contains("<tfoot><tr><td colspan=\"2\">…and 1 other</td></tr></tfoot>"));
+        assert_eq!(
+            tables.table(&spec).unwrap().limited(5, true).rows().len(),
+            3
+        );
+    }
+
+    #[test]
+    fn current_value_columns() {
+        let tables = tables();
+        let mut hbs = Handlebars::new();
+        tables.install(&mut hbs);
+        assert_eq!(
+            hbs.render_template(
+                "{{table rows=\"users,visits\" columns=\"change,total\" current_values=true \
+                 sort=\"change (current)\"}}",
+                &()
+            )
+            .unwrap(),
+            "|        | change     | change (current) | total |\n\
+             |--------|------------|------------------|-------|\n\
+             | users  | up 25.0%   | 125              | 125   |\n\
+             | visits | down 25.0% | 300              | 300   |"
+        );
+    }
+
+    #[test]
+    fn tables_built_by_hand() {
+        fn build(values: &[(&str, f64)]) -> Result<Table, CommandError> {
+            let mut table = Table::new(&["value"]);
+            for (label, value) in values {
+                table.push_row(label, vec![Cell::new(&value.to_string(), Some(*value))])?;
+            }
+            Ok(table.with_caption("By hand"))
+        }
+
+        let table = build(&[("a", 2.0), ("b", 1.0)]).unwrap();
+        let stored = serde_json::to_string(&table).unwrap();
+        let table: Table = serde_json::from_str(&stored).unwrap();
+        assert_eq!(
+            table
+                .sorted("value", SortOrder::Ascending)
+                .unwrap()
+                .to_string(),
+            "|     | value |\n|-----|-------|\n| b   | 1     |\n| a   | 2     |\n\nTable: By hand"
+        );
+        assert!(Table::new(&["value"]).push_row("c", Vec::new()).is_err());
+    }
+
+    #[test]
+    fn pivoted_groups() {
+        let mut tables = tables();
+        let (email, points) = weekly("email", &[10.0, 20.0]);
+        tables.add_metric(email, points);
+        tables.add_group(&MetricGroup::new(
+            String::from("channels"),
+            vec![String::from("visits"), String::from("email")],
+        ));
+        let mut hbs = Handlebars::new();
+        tables.install(&mut hbs);
+        assert_eq!(
+            hbs.render_template("{{table pivot=\"channels\"}}", &())
+                .unwrap(),
+            "|          | visits | email |\n\
+             |----------|--------|-------|\n\
+             | channels | 300    | 20    |"
+        );
+        assert_eq!(
+            hbs.render_template("{{table pivot=\"channels\" command=\"change\"}}", &())
+                .unwrap(),
+            "|          | visits     | email     |\n\
+             |----------|------------|-----------|\n\
+             | channels | down 25.0% | up 100.0% |"
+        );
+        assert!(hbs
+            .render_template("{{table pivot=\"devices\"}}", &())
+            .is_err());
+    }
+
+    #[test]
+    fn drill_down_links() {
+        let mut tables = tables();
+        let mut hbs = Handlebars::new();
+        tables.install(&mut hbs);
+        let template = "{{table rows=\"users\" columns=\"total\" \
+                        links=\"https://dash.example.com/{metric}?from={start}&to={end}\"}}";
+        assert_eq!(
+            hbs.render_template(template, &()).unwrap(),
+            "|       | total                                                               |\n\
+             |-------|---------------------------------------------------------------------|\n\
+             | users | [125](https://dash.example.com/users?from=2022-01-10&to=2022-01-16) |"
+        );
+
+        let (signups, _) = weekly("signups", &[]);
+        tables.add_metric(signups, Vec::new());
+        let spec = TableSpec::new(
+            tables.axis(&["users", "signups"]).unwrap(),
+            tables.axis(&["total"]).unwrap(),
+        )
+        .with_links("/{metric}");
+        let table = tables.table(&spec).unwrap();
+        let html = table.html(RenderContext::Words, false);
+        assert!(html.contains("<td><a href=\"/users\">125</a></td>"));
+        assert!(html.contains("<td>—</td>"));
+        assert!(table
+            .rst(RenderContext::Words)
+            .contains("| `125 </users>`__ |"));
+    }
+
+    #[test]
+    fn sparkline_columns() {
+        let mut tables = tables();
+        let (signups, points) = weekly("signups", &[3.0, 1.0, 4.0, 1.0, 5.0, 9.0, 2.0, 6.0]);
+        tables.add_metric(signups, points);
+        let mut hbs = Handlebars::new();
+        tables.install(&mut hbs);
+        assert_eq!(
+            hbs.render_template(
+                "{{table rows=\"users,signups\" columns=\"total,spark\" frequency=\"monthly\"}}",
+                &()
+            )
+            .unwrap(),
+            "|         | total | spark |\n\
+             |---------|-------|-------|\n\
+             | users   | 225   | ▁█    |\n\
+             | signups | 14    | ▅▁▆▁█ |"
+        );
+    }
+
+    #[test]
+    fn conditional_blocks() {
+        let mut tables = tables();
+        let (signups, _) = weekly("signups", &[]);
+        tables.add_metric(signups, Vec::new());
+        let mut hbs = Handlebars::new();
+        tables.install(&mut hbs);
+        let render = |args: &str| {
+            hbs.render_template(
+                &format!(
+                    "{{{{#if_figure {}}}}}shown{{{{else}}}}hidden{{{{/if_figure}}}}",
+                    args
+                ),
+                &(),
+            )
+        };
+        assert_eq!(render("metric=\"users\"").unwrap(), "shown");
+        assert_eq!(render("metric=\"signups\"").unwrap(), "hidden");
+        assert_eq!(
+            render("metric=\"users\" command=\"change\" above=\"10%\"").unwrap(),
+            "shown"
+        );
+        assert_eq!(
+            render("metric=\"visits\" command=\"change\" above=\"10%\"").unwrap(),
+            "hidden"
+        );
+        assert_eq!(
+            render("metric=\"visits\" frequency=\"monthly\" above=500 below=1000").unwrap(),
+            "shown"
+        );
+        assert!(render("metric=\"users\" abov=1").is_err());
+        assert!(render("metric=\"users\" above=\"lots\"").is_err());
+    }
+
+    #[test]
+    fn blocks_for_each_metric() {
+        let mut tables = tables();
+        tables.add_group(&MetricGroup::new(
+            String::from("audience"),
+            vec![String::from("visits"), String::from("users")],
+        ));
+        let mut hbs = Handlebars::new();
+        tables.install(&mut hbs);
+        let template = "{{#each_metric \"audience\"}}{{@index}}. {{this}}: \
+                        {{table rows=this columns=\"total\" format=\"csv\"}}\n\
+                        {{/each_metric}}";
+        assert_eq!(
+            hbs.render_template(template, &()).unwrap(),
+            "0. visits: ,total\nvisits,300\n1. users: ,total\nusers,125\n"
+        );
+        assert_eq!(
+            hbs.render_template(
+                "{{#each_metric \"*s*\"}}{{this}}{{#unless @last}}, {{/unless}}{{/each_metric}}",
+                &()
+            )
+            .unwrap(),
+            "users, visits"
+        );
+        assert_eq!(
+            hbs.render_template(
+                "{{#each_metric \"web_*\"}}{{this}}{{else}}none{{/each_metric}}",
+                &()
+            )
+            .unwrap(),
+            "none"
+        );
+        assert!(matches_glob("web_visits", "web_visits"));
+        assert!(!matches_glob("web_*_total", "web_visits"));
+    }
+
+    #[test]
+    fn figures_bound_with_let() {
+        let tables = tables();
+        let mut hbs = Handlebars::new();
+        tables.install(&mut hbs);
+        crate::install_report_helpers(&mut hbs);
+        let template = "{{let \"growth\" (figure metric=\"users\" command=\"change\")}}\
+                        Users were {{@growth}}. Visits were {{figure metric=\"visits\"}}. \
+                        Again, users were {{@growth}}.";
+        assert_eq!(
+            hbs.render_template(template, &()).unwrap(),
+            "Users were up 25.0%. Visits were 300. Again, users were up 25.0%."
+        );
+        assert!(hbs
+            .render_template("{{figure metric=\"users\" frequency=\"daily\"}}", &())
+            .is_err());
+    }
+
+    #[test]
+    fn nested_defaults() {
+        let tables = tables();
+        let mut hbs = Handlebars::new();
+        tables.install(&mut hbs);
+        let template = "{{#defaults frequency=\"monthly\" placeholder=\"n/a\"}}\
+                        {{figure metric=\"users\"}} \
+                        {{#defaults frequency=\"weekly\"}}{{figure metric=\"users\"}} \
+                        {{figure metric=\"users\" frequency=\"monthly\"}}{{/defaults}} \
+                        {{figure metric=\"users\"}} \
+                        {{table rows=\"users\" columns=\"diff\" format=\"csv\"}}\
+                        {{/defaults}} {{figure metric=\"users\"}}";
+        assert_eq!(
+            hbs.render_template(template, &()).unwrap(),
+            "225 125 225 225 ,diff\nusers,n/a 125"
+        );
+        assert!(hbs
+            .render_template("{{#defaults frequncy=\"weekly\"}}{{/defaults}}", &())
+            .is_err());
+    }
+
+    #[test]
+    fn summaries() {
+        let tables = tables();
+        let spec = TableSpec::new(
+            tables.axis(&["users", "visits"]).unwrap(),
+            tables.axis(&["weekly", "monthly"]).unwrap(),
+        )
+        .with_command("total");
+        let table = tables
+            .table(&spec)
+            .unwrap()
+            .with_summary_column(Summary::Average)
+            .with_summary_row(Summary::Sum);
+        assert_eq!(
+            table.rows()[2],
+            (
+                String::from("Total"),
+                vec![
+                    Cell::new("425", Some(425.0)),
+                    Cell::new("925", Some(925.0)),
+                    Cell::new("675", Some(675.0)),
+                ]
+            )
+        );
+        assert_eq!(table.columns()[2], "Average");
+
+        let spec = TableSpec::new(
+            tables.axis(&["weekly", "monthly"]).unwrap(),
+            tables.axis(&["users", "visits"]).unwrap(),
+        )
+        .with_command("total")
+        .with_column_precision("users", Precision::Decimals(1));
+        let table = tables.table(&spec).unwrap().with_summary_row(Summary::Sum);
+        assert_eq!(table.rows()[2].1[0].data(), "350.0");
+        assert_eq!(table.rows()[2].1[1].data(), "1,000");
+
+        let spec = TableSpec::new(
+            tables.axis(&["users", "visits"]).unwrap(),
+            tables.axis(&["total", "change"]).unwrap(),
+        );
+        let table = tables.table(&spec).unwrap().with_summary_row(Summary::Sum);
+        assert_eq!(table.rows()[2].1[0].data(), "425");
+        assert_eq!(table.rows()[2].1[1], Cell::new("", None));
+
+        let spec = TableSpec::new(
+            tables.axis(&["users", "visits"]).unwrap(),
+            tables.axis(&["total", "change"]).unwrap(),
+        );
+        let table = tables.table(&spec).unwrap();
+        assert_eq!(
+            table
+                .clone()
+                .with_grand_totals(TotalPolicy::Recompute)
+                .to_string(),
+            "|        | total | change     | Total |\n\
+             |--------|-------|------------|-------|\n\
+             | users  | 125   | up 25.0%   |       |\n\
+             | visits | 300   | down 25.0% |       |\n\
+             | Total  | 425   | down 15.0% |       |"
+        );
+        assert_eq!(
+            table.with_grand_totals(TotalPolicy::Blank).rows()[2].1[1],
+            Cell::new("", None)
+        );
+
+        let mut hbs = Handlebars::new();
+        tables.install(&mut hbs);
+        assert!(hbs
+            .render_template(
+                "{{table rows=\"users\" columns=\"total\" summary_row=\"median\"}}",
+                &()
+            )
+            .is_err());
+    }
+}
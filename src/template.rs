@@ -0,0 +1,246 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use handlebars::{
+    Context, Handlebars, Helper, HelperDef, HelperResult, Output, RenderError, Renderable,
+};
+
+/// Registers template under name for rendering reports, once its comments are stripped.
+/// Errors can be described with describe_template_error
+pub fn register_report(
+    hbs: &mut Handlebars,
+    name: &str,
+    template: &str,
+) -> Result<(), RenderError> {
+    Ok(hbs.register_template_string(name, strip_comments(template)?)?)
+}
+
+/// Registers the report template file at path under name, along with each file it
+/// includes with `{{include "header.md"}}`, found relative to the including file and
+/// registered under its canonical path. Included files can include others, but not themselves
+pub fn register_report_file(
+    hbs: &mut Handlebars,
+    name: &str,
+    path: impl AsRef<Path>,
+) -> Result<(), RenderError> {
+    register_file(hbs, name, path.as_ref(), &mut Vec::new())
+}
+
+fn register_file(
+    hbs: &mut Handlebars,
+    name: &str,
+    path: &Path,
+    including: &mut Vec<PathBuf>,
+) -> Result<(), RenderError> {
+    let path = fs::canonicalize(path).map_err(|e| unreadable(path, e))?;
+    let source = fs::read_to_string(&path).map_err(|e| unreadable(&path, e))?;
+    let source = strip_comments(&source)?;
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+    including.push(path.clone());
+    let mut template = String::new();
+    let mut rest = source.as_str();
+    while let Some((start, end)) = find_include(rest) {
+        // Compared once canonical, so that `../parts/self.md` is still caught as itself
+        let joined = dir.join(&rest[start..end]);
+        let included = fs::canonicalize(&joined).map_err(|e| unreadable(&joined, e))?;
+        if including.contains(&included) {
+            return Err(RenderError::new(format!(
+                "{} includes itself",
+                included.display()
+            )));
+        }
+        let included_name = included.to_string_lossy().replace('\\', "/");
+        if !hbs.has_template(&included_name) {
+            register_file(hbs, &included_name, &included, including)?;
+        }
+        template.push_str(&rest[..start]);
+        template.push_str(&included_name);
+        rest = &rest[end..];
+    }
+    template.push_str(rest);
+    including.pop();
+    Ok(hbs.register_template_string(name, template)?)
+}
+
+fn unreadable(path: &Path, error: io::Error) -> RenderError {
+    RenderError::new(format!("couldn't read {}: {}", path.display(), error))
+}
+
+/// Where the file name of the first `{{include "file"}}` in text starts and ends
+fn find_include(text: &str) -> Option<(usize, usize)> {
+    text.match_indices("{{").find_map(|(index, _)| {
+        let after = text[index + 2..].trim_start_matches('~').trim_start();
+        let quoted = after
+            .strip_prefix("include")?
+            .trim_start()
+            .strip_prefix('"')?;
+        let start = text.len() - quoted.len();
+        Some((start, start + quoted.find('"')?))
+    })
+}
+
+/// Registers the report helpers which don't need data:
+/// - let, which binds a value to a name for the rest of the block, e.g.
+///   `{{let "growth" (figure metric="users" command="change")}}`, written later as
+///   `{{@growth}}`, or `{{@../growth}}` in a block within it. The value is worked out
+///   once, so sentences using it can't disagree
+/// - include, which writes a template registered by register_report_file in the
+///   including block's context
+pub fn install_report_helpers(hbs: &mut Handlebars) {
+    hbs.register_helper("let", Box::new(LetHelper));
+    hbs.register_helper("include", Box::new(IncludeHelper));
+}
+
+/// Removes each `{# comment #}` from template, so that report authors can annotate a
+/// report or disable part of it without deleting it. Comments can span lines, whose
+/// newlines are kept so that errors still point at the right line
+pub fn strip_comments(template: &str) -> Result<String, RenderError> {
+    let mut output = String::new();
+    let mut rest = template;
+    while let Some(start) = find_comment(rest) {
+        output.push_str(&rest[..start]);
+        let end = rest[start..].find("#}").ok_or_else(|| {
+            let before = &template[..template.len() - rest.len() + start];
+            let line = before.matches('\n').count() + 1;
+            let column = before.len() - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+            let mut error = RenderError::new("unclosed comment");
+            error.line_no = Some(line);
+            error.column_no = Some(column);
+            error
+        })?;
+        output.extend(rest[start..start + end].matches('\n'));
+        rest = &rest[start + end + 2..];
+    }
+    output.push_str(rest);
+    Ok(output)
+}
+
+/// Where the first `{#` which doesn't open a Handlebars block, like `{{#if`, starts
+fn find_comment(text: &str) -> Option<usize> {
+    text.match_indices("{#")
+        .map(|(index, _)| index)
+        .find(|index| !text[..*index].ends_with('{'))
+}
+
+struct LetHelper;
+
+impl HelperDef for LetHelper {
+    fn call<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'reg, 'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc Context,
+        rc: &mut handlebars::RenderContext<'reg, 'rc>,
+        _: &mut dyn Output,
+    ) -> HelperResult {
+        let name = h
+            .param(0)
+            .and_then(|param| param.value().as_str())
+            .ok_or_else(|| RenderError::new("let needs a name"))?;
+        let value = h
+            .param(1)
+            .ok_or_else(|| RenderError::new(format!("let needs a value for {}", name)))?;
+        if let Some(block) = rc.block_mut() {
+            block.set_local_var(name, value.value().clone());
+        }
+        Ok(())
+    }
+}
+
+struct IncludeHelper;
+
+impl HelperDef for IncludeHelper {
+    fn call<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'reg, 'rc>,
+        r: &'reg Handlebars<'reg>,
+        ctx: &'rc Context,
+        rc: &mut handlebars::RenderContext<'reg, 'rc>,
+        out: &mut dyn Output,
+    ) -> HelperResult {
+        let name = h
+            .param(0)
+            .and_then(|param| param.value().as_str())
+            .ok_or_else(|| RenderError::new("include needs a file"))?;
+        let template = r
+            .get_template(name)
+            .ok_or_else(|| RenderError::new(format!("no included template {}", name)))?;
+        template.render(r, ctx, rc, out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::describe_template_error;
+
+    #[test]
+    fn let_bindings() {
+        let mut hbs = Handlebars::new();
+        install_report_helpers(&mut hbs);
+        let template = "{{let \"team\" \"Web\"}}{{let \"count\" (len this)}}\
+                        {{@team}} has {{@count}}: \
+                        {{#each this}}{{this}} of {{@../count}}{{#unless @last}}, {{/unless}}{{/each}}";
+        assert_eq!(
+            hbs.render_template(template, &["a", "b"]).unwrap(),
+            "Web has 2: a of 2, b of 2"
+        );
+        assert!(hbs.render_template("{{let \"team\"}}", &()).is_err());
+    }
+
+    #[test]
+    fn included_files() {
+        let dir = std::env::temp_dir().join("reports_included_files");
+        fs::create_dir_all(dir.join("parts")).unwrap();
+        fs::write(
+            dir.join("report.md"),
+            "{{include \"parts/header.md\"}}\n{{#each this}}{{ include \"parts/item.md\" }}{{/each}}",
+        )
+        .unwrap();
+        fs::write(dir.join("parts/header.md"), "# Report{# draft #}").unwrap();
+        fs::write(
+            dir.join("parts/item.md"),
+            "- {{this}}{{include \"end.md\"}}",
+        )
+        .unwrap();
+        fs::write(dir.join("parts/end.md"), "\n").unwrap();
+        fs::write(dir.join("loop.md"), "{{include \"loop.md\"}}").unwrap();
+        fs::write(
+            dir.join("parts/cycle.md"),
+            "{{include \"../parts/./cycle.md\"}}",
+        )
+        .unwrap();
+
+        let mut hbs = Handlebars::new();
+        install_report_helpers(&mut hbs);
+        register_report_file(&mut hbs, "report", dir.join("report.md")).unwrap();
+        assert_eq!(
+            hbs.render("report", &["users", "visits"]).unwrap(),
+            "# Report\n- users\n- visits\n"
+        );
+        assert!(register_report_file(&mut hbs, "loop", dir.join("loop.md")).is_err());
+        let error =
+            register_report_file(&mut hbs, "cycle", dir.join("parts/cycle.md")).unwrap_err();
+        assert!(error.desc.ends_with("cycle.md includes itself"));
+        assert!(register_report_file(&mut hbs, "none", dir.join("none.md")).is_err());
+    }
+
+    #[test]
+    fn comments_stripped() {
+        let mut hbs = Handlebars::new();
+        let template = "{# Weekly report, see the wiki #}Users{# were up #}\n\
+                        {{#if this}}rose{{/if}}{# disabled:\n{{missing}} #}.";
+        register_report(&mut hbs, "report", template).unwrap();
+        assert_eq!(hbs.render("report", &true).unwrap(), "Users\nrose\n.");
+
+        let template = "Users\n  {# never closed";
+        let error = register_report(&mut hbs, "report", template).unwrap_err();
+        assert_eq!(
+            describe_template_error(template, &error),
+            "unclosed comment at line 2, column 3\n2 |   {# never closed\n  |   ^"
+        );
+    }
+}